@@ -1,5 +1,6 @@
 use std::env;
 use unified_chess_gui::cli::UniversalChessInterface;
+use unified_chess_gui::engine_match;
 use unified_chess_gui::ChessApplication;
 
 /** Given no arguments the application will run*/
@@ -11,14 +12,14 @@ fn main() {
             "uci-mode" => {
                 UniversalChessInterface::run(args);
             }
+            "match" => engine_match::run(&args[2..]),
+            "sprt" => engine_match::sprt::run(&args[2..]),
             _ => {
                 println!("Unknown argument {}", args[1]);
             }
         }
     } else {
-        let mut application: ChessApplication = ChessApplication {
-            game_instance: None,
-        };
+        let mut application = ChessApplication::new();
 
         application.run().expect("Error from iced.");
     }
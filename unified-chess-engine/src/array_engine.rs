@@ -1,5 +1,6 @@
 use crate::fen::{
-    is_fen_valid, parse_fen_castling_ability, parse_fen_epawn, parse_fen_full_move_counter,
+    generate_fen_castling_ability, generate_fen_epawn, generate_fen_piece_placement, is_fen_valid,
+    parse_fen_castling_ability, parse_fen_epawn, parse_fen_full_move_counter,
     parse_fen_half_move_clock, parse_fen_piece_placement, parse_fen_side_to_move, split_at_space,
 };
 
@@ -7,7 +8,11 @@ use crate::fen::{
 mod chess_display;
 pub mod chess_errors;
 pub mod chess_moves;
-pub(crate) mod perft;
+mod material;
+pub mod perft;
+pub mod termination;
+mod validation;
+pub mod zobrist;
 
 use crate::fen::FEN_START_POSITION;
 use chess_errors::InvalidFen;
@@ -48,6 +53,23 @@ impl Piece {
     pub fn new(color: Color, piece_type: PieceType) -> Piece {
         Piece { color, piece_type }
     }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn piece_type(&self) -> PieceType {
+        self.piece_type
+    }
+}
+
+impl Color {
+    pub fn opposite(&self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
 }
 
 #[derive(PartialEq, Copy, Clone, Debug)]
@@ -69,6 +91,24 @@ pub struct MoveMetaData {
     //move_result: MoveResult,
 }
 
+impl MoveMetaData {
+    pub fn piece_to_move(&self) -> PieceType {
+        self.piece_to_move
+    }
+
+    pub fn piece_to_capture(&self) -> Option<PieceType> {
+        self.piece_to_capture
+    }
+
+    pub fn is_castling_move(&self) -> bool {
+        self.is_castling_move
+    }
+
+    pub fn is_en_passant_move(&self) -> bool {
+        self.is_en_passant_move
+    }
+}
+
 /* Chessboard specific implementations */
 #[derive(Debug, Clone, Copy)]
 pub struct ChessBoard {
@@ -90,6 +130,12 @@ pub struct Move {
     pub meta_data: MoveMetaData,
 }
 
+impl Default for ChessBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Implements chess functionality
 impl ChessBoard {
     pub fn new() -> ChessBoard {
@@ -118,6 +164,36 @@ impl ChessBoard {
         }
     }
 
+    pub fn side_to_move(&self) -> Color {
+        if self.white_is_side_to_move {
+            Color::White
+        } else {
+            Color::Black
+        }
+    }
+
+    /** Returns the piece on a position given as (file, rank), both zero indexed. */
+    pub fn piece_at(&self, position: Position) -> Option<Piece> {
+        self.board[position.1][position.0]
+    }
+
+    /** Returns the castling ability in the order: WKingside, WQueenside, BKingside, BQueenside */
+    pub fn castling_ability(&self) -> [bool; 4] {
+        self.castling_ability
+    }
+
+    pub fn en_passant_target_square(&self) -> Option<Position> {
+        self.en_passant_target_square
+    }
+
+    pub fn half_move_clock(&self) -> u64 {
+        self.half_move_clock
+    }
+
+    pub fn full_move_counter(&self) -> u64 {
+        self.full_move_counter
+    }
+
     // Implements FEN functionality
     pub fn set_fen_position_arr(&mut self, fen: &str) -> Result<(), InvalidFen> {
         if !is_fen_valid(fen) {
@@ -126,35 +202,47 @@ impl ChessBoard {
         let split_fen = split_at_space(fen);
 
         /* Piece placement */
-        let parsed_board = parse_fen_piece_placement(&split_fen[0].as_str());
+        let parsed_board = parse_fen_piece_placement(split_fen[0].as_str());
 
         self.board = parsed_board;
 
         /* Side to move */
-        let is_white_move = parse_fen_side_to_move(&split_fen[1].as_str());
+        let is_white_move = parse_fen_side_to_move(split_fen[1].as_str());
 
         self.white_is_side_to_move = is_white_move;
 
         /* Castling ability*/
-        let castling_ability = parse_fen_castling_ability(&split_fen[2].as_str());
+        let castling_ability = parse_fen_castling_ability(split_fen[2].as_str());
 
         self.castling_ability = castling_ability;
 
         /* En Passant */
-        let en_passant = parse_fen_epawn(&split_fen[3].as_str());
+        let en_passant = parse_fen_epawn(split_fen[3].as_str());
 
         self.en_passant_target_square = en_passant;
 
         /* Half move clock */
-        let half_moves = parse_fen_half_move_clock(&split_fen[4].as_str());
+        let half_moves = parse_fen_half_move_clock(split_fen[4].as_str());
 
         self.half_move_clock = half_moves;
 
         /* Full move counter */
-        let full_moves = parse_fen_full_move_counter(&split_fen[5].as_str());
+        let full_moves = parse_fen_full_move_counter(split_fen[5].as_str());
 
         self.full_move_counter = full_moves;
 
         Ok(())
     }
+
+    pub fn to_fen(&self) -> String {
+        format!(
+            "{} {} {} {} {} {}",
+            generate_fen_piece_placement(&self.board),
+            if self.white_is_side_to_move { 'w' } else { 'b' },
+            generate_fen_castling_ability(self.castling_ability),
+            generate_fen_epawn(self.en_passant_target_square),
+            self.half_move_clock,
+            self.full_move_counter
+        )
+    }
 }
@@ -3,49 +3,77 @@ use std::fmt;
 use crate::array_engine::PieceType::{Bishop, King, Knight, Pawn, Queen, Rook};
 use crate::array_engine::{Board, ChessBoard, Color, Move, Square};
 
-const T_LINE: &str = "┌—————┬—————┬—————┬—————┬—————┬—————┬—————┬—————┐\n";
-const H_LINE: &str = "|—————|—————|—————|—————|—————|—————|—————|—————|\n";
-const B_LINE: &str = "└—————┴—————┴—————┴—————┴—————┴—————┴—————┴—————┘\n";
+const T_LINE: &str = "┌—————┬—————┬—————┬—————┬—————┬—————┬—————┬—————┐";
+const H_LINE: &str = "|—————|—————|—————|—————|—————|—————|—————|—————|";
+const B_LINE: &str = "└—————┴—————┴—————┴—————┴—————┴—————┴—————┴—————┘";
+const FILES: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
 
-/* This printing function will only print from the perspective of white. This means that the board
- * goes from top to bottom, respectively rank 8 to 1 */
+/** Prints a chessboard from the perspective of one side, which is at the bottom of the board. The
+ranks are labeled on the left and the files below the board. */
+pub struct BoardDisplay<'a> {
+    chess_board: &'a ChessBoard,
+    perspective: Color,
+}
+
+impl ChessBoard {
+    /** Displays the board with the given side at the bottom. */
+    pub fn display_from(&self, perspective: Color) -> BoardDisplay<'_> {
+        BoardDisplay {
+            chess_board: self,
+            perspective,
+        }
+    }
+}
+
+/* The board is printed from the perspective of white, from rank 8 at the top to rank 1. */
 impl fmt::Display for ChessBoard {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let string_chessboard: Vec<String> = parse_chessboard_to_string(&self.board);
+        write!(f, "{}", self.display_from(Color::White))
+    }
+}
 
-        if string_chessboard.len() != 8 {
-            return write!(f, "Error parsing chessboard!");
+impl fmt::Display for BoardDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let string_chessboard: Vec<String> = parse_chessboard_to_string(&self.chess_board.board);
+        let flipped = self.perspective == Color::Black;
+
+        /* White sees rank 8 at the top, black sees rank 1 at the top with the files mirrored. */
+        let ranks: Vec<usize> = match flipped {
+            false => (0..8).rev().collect(),
+            true => (0..8).collect(),
+        };
+
+        writeln!(f, "   {T_LINE}")?;
+
+        for (i, &rank) in ranks.iter().enumerate() {
+            if i > 0 {
+                writeln!(f, "   {H_LINE}")?;
+            }
+
+            let rank_string: String = match flipped {
+                false => string_chessboard[rank].clone(),
+                true => string_chessboard[rank].chars().rev().collect(),
+            };
+            writeln!(f, " {} {rank_string}", rank + 1)?;
         }
-        // Prints the 8 ranks of a chessboard, zero indexed.
-        write!(
-            f,
-            "{}{}\n{}{}\n{}{}\n{}{}\n{}{}\n{}{}\n{}{}\n{}{}\n{}",
-            T_LINE,
-            string_chessboard[7],
-            H_LINE,
-            string_chessboard[6],
-            H_LINE,
-            string_chessboard[5],
-            H_LINE,
-            string_chessboard[4],
-            H_LINE,
-            string_chessboard[3],
-            H_LINE,
-            string_chessboard[2],
-            H_LINE,
-            string_chessboard[1],
-            H_LINE,
-            string_chessboard[0],
-            B_LINE
-        )
+
+        writeln!(f, "   {B_LINE}")?;
+
+        let mut files = FILES;
+        if flipped {
+            files.reverse();
+        }
+
+        let file_labels: String = files.iter().map(|file| format!("   {file}  ")).collect();
+        writeln!(f, "   {}", file_labels.trim_end())
     }
 }
 
 impl fmt::Display for Move {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
+        writeln!(
             f,
-            "start_pos: ({}, {}), end_pos: ({}, {})\n",
+            "start_pos: ({}, {}), end_pos: ({}, {})",
             self.start_pos.0, self.start_pos.1, self.end_pos.0, self.end_pos.1
         )
     }
@@ -54,11 +82,11 @@ impl fmt::Display for Move {
 fn parse_chessboard_to_string(board: &Board) -> Vec<String> {
     let mut printable_board = Vec::new();
 
-    for rank in 0..=7 {
+    for rank in board {
         let mut pieces: Vec<char> = Vec::new();
 
-        for file in 0..=7 {
-            pieces.push(piece_type_to_char(board[rank][file]));
+        for square in rank {
+            pieces.push(piece_type_to_char(*square));
         }
 
         let rank_string: String = format!(
@@ -96,3 +124,39 @@ fn piece_type_to_char(square: Square) -> char {
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_perspective() {
+        let chess_board = ChessBoard::new_from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+
+        let white = chess_board.to_string();
+        let lines: Vec<&str> = white.lines().collect();
+        assert_eq!(
+            lines[1],
+            " 8 |     |     |     |     |  k  |     |     |     |"
+        );
+        assert_eq!(
+            lines[15],
+            " 1 |  R  |     |     |     |  K  |     |     |     |"
+        );
+        assert_eq!(
+            lines[17],
+            "      a     b     c     d     e     f     g     h"
+        );
+
+        let black = chess_board.display_from(Color::Black).to_string();
+        let lines: Vec<&str> = black.lines().collect();
+        assert_eq!(
+            lines[1],
+            " 1 |     |     |     |  K  |     |     |     |  R  |"
+        );
+        assert_eq!(
+            lines[17],
+            "      h     g     f     e     d     c     b     a"
+        );
+    }
+}
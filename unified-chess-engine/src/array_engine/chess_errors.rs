@@ -22,3 +22,105 @@ impl fmt::Display for InvalidFen {
         write!(f, "Invalid FEN string")
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct InvalidOpeningBook {
+    pub(crate) reason: String,
+}
+
+impl error::Error for InvalidOpeningBook {}
+impl fmt::Display for InvalidOpeningBook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid opening book: {}", self.reason)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidSan {
+    pub(crate) san: String,
+    /* Set when several legal moves match the SAN move. */
+    pub(crate) ambiguous: bool,
+}
+
+impl InvalidSan {
+    pub fn is_ambiguous(&self) -> bool {
+        self.ambiguous
+    }
+}
+
+impl error::Error for InvalidSan {}
+impl fmt::Display for InvalidSan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.ambiguous {
+            write!(f, "Ambiguous SAN move: {}", self.san)
+        } else {
+            write!(f, "Invalid or illegal SAN move: {}", self.san)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidTablebase {
+    pub(crate) reason: String,
+}
+
+impl error::Error for InvalidTablebase {}
+impl fmt::Display for InvalidTablebase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid tablebase: {}", self.reason)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidPgn {
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) reason: String,
+}
+
+impl InvalidPgn {
+    /** The line of the error, counted from 1. */
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /** The column of the error, counted from 1. */
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl error::Error for InvalidPgn {}
+impl fmt::Display for InvalidPgn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid PGN at line {}, column {}: {}",
+            self.line, self.column, self.reason
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidEpd {
+    pub(crate) reason: String,
+}
+
+impl error::Error for InvalidEpd {}
+impl fmt::Display for InvalidEpd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid EPD: {}", self.reason)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidPosition {
+    pub(crate) reason: String,
+}
+
+impl error::Error for InvalidPosition {}
+impl fmt::Display for InvalidPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid position: {}", self.reason)
+    }
+}
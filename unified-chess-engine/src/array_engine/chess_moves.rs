@@ -4,9 +4,10 @@
 mod legal_moves;
 pub mod meta_data;
 mod piece_logic;
+pub mod san;
 
 use crate::array_engine::chess_errors::IllegalMove;
-use crate::array_engine::{Board, ChessBoard, Color, Move, Position};
+use crate::array_engine::{Board, ChessBoard, Color, Move, PieceType, Position};
 
 impl ChessBoard {
     pub fn make_move(&mut self, move_to_make: Move) -> Result<Move, IllegalMove> {
@@ -42,12 +43,25 @@ pub struct MoveDirection {
 }
 
 impl Move {
+    /** A move is noisy if it changes the material on the board, i.e. captures or promotions. */
+    pub fn is_noisy(&self) -> bool {
+        self.meta_data.piece_to_capture.is_some() || self.meta_data.promotion_piece.is_some()
+    }
+
     pub fn move_to_string(&self) -> String {
         let start_file: char = Self::file_to_char(self.start_pos.0);
         let end_file: char = Self::file_to_char(self.end_pos.0);
 
+        let promotion: &str = match self.meta_data.promotion_piece {
+            Some(PieceType::Queen) => "q",
+            Some(PieceType::Rook) => "r",
+            Some(PieceType::Bishop) => "b",
+            Some(PieceType::Knight) => "n",
+            _ => "",
+        };
+
         format!(
-            "{start_file}{}{end_file}{}",
+            "{start_file}{}{end_file}{}{promotion}",
             self.start_pos.1 + 1,
             self.end_pos.1 + 1
         )
@@ -83,7 +97,7 @@ impl MoveDirection {
         let new_i8_x: i8 = self.dx + casted_x;
         let new_i8_y: i8 = self.dy + casted_y;
 
-        if new_i8_x > 7 || new_i8_x < 0 || new_i8_y > 7 || new_i8_y < 0 {
+        if !(0..=7).contains(&new_i8_x) || !(0..=7).contains(&new_i8_y) {
             return false;
         }
 
@@ -94,13 +108,7 @@ impl MoveDirection {
         match target_piece {
             None => true,
 
-            Some(piece) => {
-                if piece.color == *friendly_piece_color {
-                    false
-                } else {
-                    true
-                }
-            }
+            Some(piece) => piece.color != *friendly_piece_color,
         }
     }
 
@@ -122,7 +130,7 @@ impl MoveDirection {
         let new_i8_x: i8 = self.dx + casted_x;
         let new_i8_y: i8 = self.dy + casted_y;
 
-        if new_i8_x > 7 || new_i8_x < 0 || new_i8_y > 7 || new_i8_y < 0 {
+        if !(0..=7).contains(&new_i8_x) || !(0..=7).contains(&new_i8_y) {
             return false;
         }
 
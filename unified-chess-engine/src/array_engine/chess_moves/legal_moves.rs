@@ -14,19 +14,28 @@ use crate::array_engine::chess_moves::legal_moves::pawn_piece::get_pawn_moves;
 use crate::array_engine::chess_moves::legal_moves::queen_piece::get_queen_moves;
 use crate::array_engine::chess_moves::legal_moves::rook_piece::get_rook_moves;
 use crate::array_engine::PieceType::{Bishop, King, Knight, Pawn, Queen, Rook};
-use crate::array_engine::{ChessBoard, Color, Move, Piece, PieceType, Position};
+use crate::array_engine::{
+    ChessBoard, Color, Move, Piece, PieceType, Position, COL_SIZE, ROW_SIZE,
+};
 
 impl ChessBoard {
     pub fn legal_moves(&self) -> Vec<Move> {
-        let mut legal_moves: Vec<Move> = Vec::with_capacity(50);
+        self.filter_legal_moves(self.pseudo_legal_moves())
+    }
 
-        let current_color: Color = if self.white_is_side_to_move {
-            Color::White
-        } else {
-            Color::Black
-        };
+    /** Splits the pseudo legal moves into the captures and promotions, i.e. the moves that change
+    the material on the board, and the quiet moves. Together with `keeps_king_safe` this allows a
+    caller to generate moves in stages and only check the legality of the moves it uses. **/
+    pub(crate) fn pseudo_legal_noisy_and_quiet_moves(&self) -> (Vec<Move>, Vec<Move>) {
+        self.pseudo_legal_moves()
+            .into_iter()
+            .partition(|piece_move| piece_move.is_noisy())
+    }
 
-        let pseudo_legal_moves: Vec<Move> = self.pseudo_legal_moves();
+    /** Returns true if a pseudo legal move does not leave the king of the side to move in
+    check, i.e. if it is legal. **/
+    pub(crate) fn keeps_king_safe(&self, piece_move: &Move) -> bool {
+        let current_color: Color = self.side_to_move();
 
         let king_position = find_first_matching_chess_piece(
             &self.board,
@@ -34,86 +43,129 @@ impl ChessBoard {
         )
         .expect("Both kings most exist on all boards!");
 
-        for piece_move in pseudo_legal_moves {
-            let position_to_check: Position =
-                if piece_move.meta_data.piece_to_move == PieceType::King {
-                    piece_move.end_pos
-                } else {
-                    king_position
-                };
+        self.keeps_king_on_position_safe(piece_move, &king_position, &current_color)
+    }
+
+    /** Checks if a move is legal by only generating the moves of the piece on its start
+    position, which is a lot cheaper than generating all legal moves. **/
+    pub fn is_legal_move(&self, move_to_check: &Move) -> bool {
+        let pseudo_legal_moves = self.pseudo_legal_moves_from_position(&move_to_check.start_pos);
 
-            let mut board_copy = *self;
-            board_copy.make_move_on_board(&piece_move);
+        pseudo_legal_moves.contains(move_to_check) && self.keeps_king_safe(move_to_check)
+    }
+
+    /** Finds the legal move between two positions, the promotion piece is only used to pick
+    between the promotion moves of a pawn. **/
+    pub fn find_legal_move(
+        &self,
+        start_pos: Position,
+        end_pos: Position,
+        promotion_piece: Option<PieceType>,
+    ) -> Option<Move> {
+        let candidate_moves: Vec<Move> = self
+            .pseudo_legal_moves_from_position(&start_pos)
+            .into_iter()
+            .filter(|piece_move| {
+                piece_move.end_pos == end_pos
+                    && piece_move.meta_data.promotion_piece == promotion_piece
+            })
+            .collect();
+
+        self.filter_legal_moves(candidate_moves).first().copied()
+    }
 
-            if !king_is_checked(&board_copy.board, &position_to_check, &current_color) {
+    /** Returns true if the king of the side to move is in check. **/
+    pub fn king_is_in_check(&self) -> bool {
+        let current_color: Color = self.side_to_move();
+
+        let king_position = find_first_matching_chess_piece(
+            &self.board,
+            &Piece::new(current_color, PieceType::King),
+        )
+        .expect("Both kings most exist on all boards!");
+
+        king_is_checked(&self.board, &king_position, &current_color)
+    }
+
+    /** Returns true if the king of the color is attacked, or None if the color has no king. The
+    king of the side not to move is never attacked in a legal position. **/
+    pub(crate) fn king_is_attacked(&self, color: Color) -> Option<bool> {
+        let king_position =
+            find_first_matching_chess_piece(&self.board, &Piece::new(color, PieceType::King))?;
+
+        Some(king_is_checked(&self.board, &king_position, &color))
+    }
+
+    /** Removes the moves from a list of pseudo legal moves that leave the king in check. **/
+    fn filter_legal_moves(&self, pseudo_legal_moves: Vec<Move>) -> Vec<Move> {
+        let mut legal_moves: Vec<Move> = Vec::with_capacity(pseudo_legal_moves.len());
+
+        let current_color: Color = self.side_to_move();
+
+        let king_position = find_first_matching_chess_piece(
+            &self.board,
+            &Piece::new(current_color, PieceType::King),
+        )
+        .expect("Both kings most exist on all boards!");
+
+        for piece_move in pseudo_legal_moves {
+            if self.keeps_king_on_position_safe(&piece_move, &king_position, &current_color) {
                 legal_moves.push(piece_move);
             }
         }
         legal_moves
     }
 
+    fn keeps_king_on_position_safe(
+        &self,
+        piece_move: &Move,
+        king_position: &Position,
+        current_color: &Color,
+    ) -> bool {
+        let position_to_check: Position = if piece_move.meta_data.piece_to_move == PieceType::King {
+            piece_move.end_pos
+        } else {
+            *king_position
+        };
+
+        let mut board_copy = *self;
+        board_copy.make_move_on_board(piece_move);
+
+        !king_is_checked(&board_copy.board, &position_to_check, current_color)
+    }
+
     /** This function returns all possible moves, but does not check for pinned pieces,
     checks and other special moves related to king checks **/
     fn pseudo_legal_moves(&self) -> Vec<Move> {
         let mut pseudo_legal_moves: Vec<Move> = Vec::new();
 
-        let friendly_color = if self.white_is_side_to_move {
-            Color::White
-        } else {
-            Color::Black
-        };
-
-        for (row, squares) in self.board.iter().enumerate() {
-            for (column, square) in squares.iter().enumerate() {
-                match square {
-                    None => continue,
-                    Some(piece) => {
-                        if piece.color == friendly_color {
-                            match piece.piece_type {
-                                King => {
-                                    let mut moves =
-                                        get_king_moves(self, &friendly_color, &(column, row));
-                                    pseudo_legal_moves.append(&mut moves);
-                                }
-
-                                Queen => {
-                                    let mut moves =
-                                        get_queen_moves(self, &friendly_color, &(column, row));
-                                    pseudo_legal_moves.append(&mut moves);
-                                }
-
-                                Rook => {
-                                    let mut moves =
-                                        get_rook_moves(self, &friendly_color, &(column, row));
-                                    pseudo_legal_moves.append(&mut moves);
-                                }
-
-                                Bishop => {
-                                    let mut moves =
-                                        get_bishop_moves(self, &friendly_color, &(column, row));
-                                    pseudo_legal_moves.append(&mut moves);
-                                }
-
-                                Knight => {
-                                    let mut moves =
-                                        get_knight_moves(self, &friendly_color, &(column, row));
-                                    pseudo_legal_moves.append(&mut moves);
-                                }
-
-                                Pawn => {
-                                    let mut moves =
-                                        get_pawn_moves(self, &friendly_color, &(column, row));
-                                    pseudo_legal_moves.append(&mut moves);
-                                }
-                            }
-                        }
-                    }
-                }
+        for row in 0..ROW_SIZE {
+            for column in 0..COL_SIZE {
+                let mut moves = self.pseudo_legal_moves_from_position(&(column, row));
+                pseudo_legal_moves.append(&mut moves);
             }
         }
         pseudo_legal_moves
     }
 
+    /** Returns the pseudo legal moves of the piece on the given position, if the piece belongs to
+    the side to move. **/
+    fn pseudo_legal_moves_from_position(&self, position: &Position) -> Vec<Move> {
+        let friendly_color = self.side_to_move();
+
+        match self.board[position.1][position.0] {
+            Some(piece) if piece.color == friendly_color => match piece.piece_type {
+                King => get_king_moves(self, &friendly_color, position),
+                Queen => get_queen_moves(self, &friendly_color, position),
+                Rook => get_rook_moves(self, &friendly_color, position),
+                Bishop => get_bishop_moves(self, &friendly_color, position),
+                Knight => get_knight_moves(self, &friendly_color, position),
+                Pawn => get_pawn_moves(self, &friendly_color, position),
+            },
+            _ => Vec::new(),
+        }
+    }
+
     pub fn make_move_on_board(&mut self, move_to_make: &Move) {
         if move_to_make.meta_data.is_castling_move {
             let (rook_start_position, rook_end_position): (Position, Position) =
@@ -17,11 +17,7 @@ pub fn get_single_step_moves(
             let new_position: Position = direction.walk_from_position(*piece_position);
 
             let optional_piece_to_capture: Option<PieceType> =
-                if let Some(piece) = chess_board.board[new_position.1][new_position.0] {
-                    Some(piece.piece_type)
-                } else {
-                    None
-                };
+                chess_board.board[new_position.1][new_position.0].map(|piece| piece.piece_type);
 
             let meta_data: MoveMetaData = MoveMetaData {
                 piece_to_move: piece_type,
@@ -64,7 +60,7 @@ pub fn get_multi_step_moves(
                 if let Some(piece) = chess_board.board[current_position.1][current_position.0] {
                     move_obstructed = true;
                     MoveMetaData {
-                        piece_to_move: PieceType::Pawn,
+                        piece_to_move: piece_type,
                         piece_to_capture: Some(piece.piece_type),
                         promotion_piece: None,
                         is_castling_move: false,
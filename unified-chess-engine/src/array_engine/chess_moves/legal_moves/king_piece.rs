@@ -59,6 +59,11 @@ fn check_for_castling_move(
     let king_end_position: Position;
     let rook_start_position: Position;
 
+    // Castling is only possible from the king's starting file.
+    if current_position.0 != 4 {
+        return None;
+    }
+
     if is_king_side_castle {
         rook_start_position = (current_position.0 + 3, current_position.1);
 
@@ -75,12 +80,15 @@ fn check_for_castling_move(
             return None;
         }
 
+        if !rook_is_on_position(board, &rook_start_position, king_color) {
+            return None;
+        }
+
         if !all_given_positions_not_in_check(
             [
                 *current_position,
                 (current_position.0 + 1, current_position.1),
                 (current_position.0 + 2, current_position.1),
-                rook_start_position,
             ]
             .as_slice(),
             board,
@@ -117,13 +125,16 @@ fn check_for_castling_move(
             return None;
         }
 
+        if !rook_is_on_position(board, &rook_start_position, king_color) {
+            return None;
+        }
+
+        // The square next to the rook only has to be empty, it may be attacked.
         if !all_given_positions_not_in_check(
             [
                 *current_position,
                 (current_position.0 - 1, current_position.1),
                 (current_position.0 - 2, current_position.1),
-                (current_position.0 - 3, current_position.1),
-                rook_start_position,
             ]
             .as_slice(),
             board,
@@ -198,7 +209,7 @@ pub fn king_is_checked(board: &Board, king_position: &Position, king_color: &Col
 
     // Check for Knight attacks
 
-    if check_multi_step_for_piece_exists(
+    if check_single_step_for_piece_exists(
         Piece::new(enemy_color, Knight),
         board,
         KNIGHT_DIRECTION.as_slice(),
@@ -241,10 +252,15 @@ fn all_given_positions_not_in_check(
     true
 }
 
+/** Returns true if a rook of the given color is placed on the given position */
+fn rook_is_on_position(board: &Board, position: &Position, rook_color: &Color) -> bool {
+    board[position.1][position.0] == Some(Piece::new(*rook_color, Rook))
+}
+
 /** Returns true if all given positions are empty */
 fn all_given_positions_are_empty(positions: &[Position], board: &Board) -> bool {
     for position in positions {
-        if board[position.1][position.0] != None {
+        if board[position.1][position.0].is_some() {
             return false;
         }
     }
@@ -21,7 +21,7 @@ pub fn get_pawn_moves(
         piece_position,
     ));
 
-    let single_pawn_move_is_legal: bool = if pawn_moves.len() > 0 { true } else { false };
+    let single_pawn_move_is_legal: bool = !pawn_moves.is_empty();
 
     let travelable_attack_direction: [bool; 2] = [
         attack_direction[0].piece_can_travel(&chess_board.board, friendly_color, piece_position),
@@ -46,6 +46,16 @@ pub fn get_pawn_moves(
         ));
     }
 
+    if let Some(en_passant_target_square) = chess_board.en_passant_target_square {
+        for direction in attack_direction {
+            if let Some(en_passant_move) =
+                get_en_passant_attack(direction, &en_passant_target_square, piece_position)
+            {
+                pawn_moves.push(en_passant_move);
+            }
+        }
+    }
+
     if single_pawn_move_is_legal {
         if let Some(move_exists) = get_pawn_double_move(
             friendly_color,
@@ -70,7 +80,7 @@ fn get_moves(
     if move_direction.move_is_within_bounds(*current_position) {
         let new_position = move_direction.walk_from_position(*current_position);
 
-        if board[new_position.1][new_position.0] == None {
+        if board[new_position.1][new_position.0].is_none() {
             if new_position.1 == 7 || new_position.1 == 0 {
                 moves.append(&mut get_promotions(current_position, &new_position, None));
             } else {
@@ -111,8 +121,8 @@ fn get_attacks(
                 ));
             } else {
                 attack_moves.push(Move {
-                    start_pos: (0, 0),
-                    end_pos: (0, 0),
+                    start_pos: *current_position,
+                    end_pos: new_position,
                     meta_data: MoveMetaData {
                         piece_to_move: PieceType::Pawn,
                         piece_to_capture: Some(piece.piece_type),
@@ -129,6 +139,35 @@ fn get_attacks(
     attack_moves
 }
 
+fn get_en_passant_attack(
+    attack_direction: MoveDirection,
+    en_passant_target_square: &Position,
+    current_position: &Position,
+) -> Option<Move> {
+    if !attack_direction.move_is_within_bounds(*current_position) {
+        return None;
+    }
+
+    let new_position = attack_direction.walk_from_position(*current_position);
+
+    if new_position != *en_passant_target_square {
+        return None;
+    }
+
+    Some(Move {
+        start_pos: *current_position,
+        end_pos: new_position,
+        meta_data: MoveMetaData {
+            piece_to_move: PieceType::Pawn,
+            piece_to_capture: Some(PieceType::Pawn),
+            promotion_piece: None,
+            is_castling_move: false,
+            generates_en_passant: false,
+            is_en_passant_move: true,
+        },
+    })
+}
+
 fn get_promotions(
     start_position: &Position,
     end_position: &Position,
@@ -181,7 +220,7 @@ fn get_pawn_double_move(
     if pawn_starting_rank == piece_position.1 {
         let new_position = double_move_direction.walk_from_position(*piece_position);
 
-        if board[new_position.1][new_position.0] == None {
+        if board[new_position.1][new_position.0].is_none() {
             let double_move: Move = Move {
                 start_pos: *piece_position,
                 end_pos: new_position,
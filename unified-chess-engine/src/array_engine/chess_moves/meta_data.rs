@@ -3,6 +3,9 @@ use crate::array_engine::{ChessBoard, Move, PieceType, Position};
 
 impl ChessBoard {
     pub fn update_meta_data(&mut self, move_to_make: &Move) {
+        // An en passant target square is only valid for the move directly after a double push.
+        self.set_no_en_passant();
+
         match move_to_make.meta_data.piece_to_move {
             PieceType::King => {
                 self.update_castling_move_abilities();
@@ -18,15 +21,19 @@ impl ChessBoard {
                 }
             }
 
-            PieceType::Rook => match move_to_make.meta_data.piece_to_capture {
-                Some(piece_to_capture) => {
-                    self.handle_capture_target(piece_to_capture, *move_to_make);
-                }
+            PieceType::Rook => {
+                self.update_rook_move_castling_ability(move_to_make.start_pos);
 
-                None => {
-                    self.increment_half_move_clock();
+                match move_to_make.meta_data.piece_to_capture {
+                    Some(piece_to_capture) => {
+                        self.handle_capture_target(piece_to_capture, *move_to_make);
+                    }
+
+                    None => {
+                        self.increment_half_move_clock();
+                    }
                 }
-            },
+            }
 
             PieceType::Pawn => match move_to_make.meta_data.piece_to_capture {
                 Some(piece_to_capture) => {
@@ -97,16 +104,24 @@ impl ChessBoard {
     fn handle_rook_capture_target(&mut self, rook_position: Position) {
         if self.white_is_side_to_move {
             if rook_position == (7, 7) {
-                self.set_no_castling_ability(3);
-            } else if rook_position == (0, 7) {
                 self.set_no_castling_ability(2);
+            } else if rook_position == (0, 7) {
+                self.set_no_castling_ability(3);
             }
-        } else {
-            if rook_position == (7, 0) {
-                self.set_no_castling_ability(1);
-            } else if rook_position == (0, 0) {
-                self.set_no_castling_ability(0);
-            }
+        } else if rook_position == (7, 0) {
+            self.set_no_castling_ability(0);
+        } else if rook_position == (0, 0) {
+            self.set_no_castling_ability(1);
+        }
+    }
+
+    fn update_rook_move_castling_ability(&mut self, rook_position: Position) {
+        match rook_position {
+            (7, 0) => self.set_no_castling_ability(0),
+            (0, 0) => self.set_no_castling_ability(1),
+            (7, 7) => self.set_no_castling_ability(2),
+            (0, 7) => self.set_no_castling_ability(3),
+            _ => {}
         }
     }
 
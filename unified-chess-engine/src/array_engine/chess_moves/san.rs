@@ -0,0 +1,252 @@
+/* This submodule converts moves to and from Standard Algebraic Notation (SAN), the notation used
+ * in PGN files, e.g. "Nf3", "exd5", "O-O" or "e8=Q+". */
+use crate::array_engine::chess_errors::InvalidSan;
+use crate::array_engine::{ChessBoard, Move, PieceType, Position};
+
+impl ChessBoard {
+    /** Returns the move in SAN, the move must be legal on the board. */
+    pub fn move_to_san(&self, chess_move: &Move) -> String {
+        let mut san = String::new();
+        let piece_type = chess_move.meta_data.piece_to_move;
+
+        if chess_move.meta_data.is_castling_move {
+            san.push_str(if chess_move.end_pos.0 > chess_move.start_pos.0 {
+                "O-O"
+            } else {
+                "O-O-O"
+            });
+        } else {
+            let is_capture = chess_move.meta_data.piece_to_capture.is_some()
+                || chess_move.meta_data.is_en_passant_move;
+
+            if piece_type == PieceType::Pawn {
+                if is_capture {
+                    san.push(file_to_char(chess_move.start_pos.0));
+                }
+            } else {
+                san.push(piece_to_char(piece_type));
+                san.push_str(&self.disambiguation(chess_move));
+            }
+
+            if is_capture {
+                san.push('x');
+            }
+
+            san.push_str(&position_to_string(chess_move.end_pos));
+
+            if let Some(promotion_piece) = chess_move.meta_data.promotion_piece {
+                san.push('=');
+                san.push(piece_to_char(promotion_piece));
+            }
+        }
+
+        let mut board_after_move = *self;
+        board_after_move.make_move_on_board(chess_move);
+        board_after_move.update_meta_data(chess_move);
+
+        if board_after_move.king_is_in_check() {
+            san.push(if board_after_move.legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+
+        san
+    }
+
+    /** Finds the legal move described by the SAN string. Check marks, annotations like "!?" and
+    castling written with zeros are accepted. */
+    pub fn move_from_san(&self, san: &str) -> Result<Move, InvalidSan> {
+        let invalid_san = || InvalidSan {
+            san: san.to_string(),
+            ambiguous: false,
+        };
+
+        let trimmed = san.trim_end_matches(['+', '#', '!', '?']);
+        let legal_moves = self.legal_moves();
+
+        let castling_side = match trimmed {
+            "O-O" | "0-0" => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _ => None,
+        };
+
+        if let Some(kingside) = castling_side {
+            return legal_moves
+                .into_iter()
+                .find(|chess_move| {
+                    chess_move.meta_data.is_castling_move
+                        && (chess_move.end_pos.0 > chess_move.start_pos.0) == kingside
+                })
+                .ok_or_else(invalid_san);
+        }
+
+        let mut characters: Vec<char> = trimmed.chars().filter(|c| *c != 'x').collect();
+
+        let promotion_piece = match characters.last().copied().and_then(char_to_piece) {
+            Some(piece_type) if characters.len() > 2 => {
+                characters.pop();
+                if characters.last() == Some(&'=') {
+                    characters.pop();
+                }
+                Some(piece_type)
+            }
+            _ => None,
+        };
+
+        let piece_type = match characters.first().copied().and_then(char_to_piece) {
+            Some(piece_type) => {
+                characters.remove(0);
+                piece_type
+            }
+            None => PieceType::Pawn,
+        };
+
+        if characters.len() < 2 {
+            return Err(invalid_san());
+        }
+
+        let square: String = characters
+            .split_off(characters.len() - 2)
+            .into_iter()
+            .collect();
+        let end_pos = string_to_position(&square).ok_or_else(invalid_san)?;
+
+        let mut start_file: Option<usize> = None;
+        let mut start_rank: Option<usize> = None;
+
+        for c in characters {
+            match c {
+                'a'..='h' => start_file = Some(c as usize - 'a' as usize),
+                '1'..='8' => start_rank = Some(c as usize - '1' as usize),
+                _ => return Err(invalid_san()),
+            }
+        }
+
+        let mut matching_moves = legal_moves.into_iter().filter(|chess_move| {
+            chess_move.meta_data.piece_to_move == piece_type
+                && !chess_move.meta_data.is_castling_move
+                && chess_move.end_pos == end_pos
+                && chess_move.meta_data.promotion_piece == promotion_piece
+                && start_file.is_none_or(|file| chess_move.start_pos.0 == file)
+                && start_rank.is_none_or(|rank| chess_move.start_pos.1 == rank)
+        });
+
+        match (matching_moves.next(), matching_moves.next()) {
+            (Some(chess_move), None) => Ok(chess_move),
+            (Some(_), Some(_)) => Err(InvalidSan {
+                san: san.to_string(),
+                ambiguous: true,
+            }),
+            _ => Err(invalid_san()),
+        }
+    }
+
+    /** Returns the file, rank or square needed to tell the move apart from other moves of the
+    same piece type to the same square. */
+    fn disambiguation(&self, chess_move: &Move) -> String {
+        let others: Vec<Position> = self
+            .legal_moves()
+            .into_iter()
+            .filter(|other| {
+                other.meta_data.piece_to_move == chess_move.meta_data.piece_to_move
+                    && other.end_pos == chess_move.end_pos
+                    && other.start_pos != chess_move.start_pos
+            })
+            .map(|other| other.start_pos)
+            .collect();
+
+        if others.is_empty() {
+            String::new()
+        } else if others.iter().all(|other| other.0 != chess_move.start_pos.0) {
+            file_to_char(chess_move.start_pos.0).to_string()
+        } else if others.iter().all(|other| other.1 != chess_move.start_pos.1) {
+            (chess_move.start_pos.1 + 1).to_string()
+        } else {
+            position_to_string(chess_move.start_pos)
+        }
+    }
+}
+
+fn piece_to_char(piece_type: PieceType) -> char {
+    match piece_type {
+        PieceType::Pawn => 'P',
+        PieceType::Knight => 'N',
+        PieceType::Bishop => 'B',
+        PieceType::Rook => 'R',
+        PieceType::Queen => 'Q',
+        PieceType::King => 'K',
+    }
+}
+
+fn char_to_piece(c: char) -> Option<PieceType> {
+    match c {
+        'N' => Some(PieceType::Knight),
+        'B' => Some(PieceType::Bishop),
+        'R' => Some(PieceType::Rook),
+        'Q' => Some(PieceType::Queen),
+        'K' => Some(PieceType::King),
+        _ => None,
+    }
+}
+
+fn file_to_char(file: usize) -> char {
+    (b'a' + file as u8) as char
+}
+
+fn position_to_string(position: Position) -> String {
+    format!("{}{}", file_to_char(position.0), position.1 + 1)
+}
+
+fn string_to_position(square: &str) -> Option<Position> {
+    let mut characters = square.chars();
+    let file = characters.next()?;
+    let rank = characters.next()?;
+
+    if !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+        return None;
+    }
+
+    Some((file as usize - 'a' as usize, rank as usize - '1' as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_san_round_trip_in_start_position() {
+        let chess_board = ChessBoard::new();
+
+        for chess_move in chess_board.legal_moves() {
+            let san = chess_board.move_to_san(&chess_move);
+            assert_eq!(chess_board.move_from_san(&san).unwrap(), chess_move);
+        }
+
+        assert_eq!(
+            chess_board.move_from_san("Nf3").unwrap().move_to_string(),
+            "g1f3"
+        );
+        assert!(chess_board.move_from_san("Nf4").is_err());
+    }
+
+    #[test]
+    fn test_san_disambiguation_castling_and_promotion() {
+        let rook_board = ChessBoard::new_from_fen("4k3/8/8/8/8/8/8/R4RK1 w - - 0 1").unwrap();
+        let rook_move = rook_board.move_from_san("Rab1").unwrap();
+        assert_eq!(rook_move.move_to_string(), "a1b1");
+        assert_eq!(rook_board.move_to_san(&rook_move), "Rab1");
+        assert!(rook_board.move_from_san("Rb1").unwrap_err().is_ambiguous());
+
+        let chess_board =
+            ChessBoard::new_from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+
+        let castling_move = chess_board.move_from_san("O-O").unwrap();
+        assert_eq!(chess_board.move_to_san(&castling_move), "O-O");
+
+        let promotion = chess_board.move_from_san("bxa8=Q+").unwrap();
+        assert_eq!(promotion.move_to_string(), "b7a8q");
+        assert_eq!(chess_board.move_to_san(&promotion), "bxa8=Q+");
+    }
+}
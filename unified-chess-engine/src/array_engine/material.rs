@@ -0,0 +1,136 @@
+/* Whether a side can still mate decides two rules: a game is drawn when neither side can ever
+ * mate, and running out of time only loses when the opponent could still mate (FIDE article
+ * 6.9). Mate only has to be possible with some series of legal moves, even with the help of the
+ * side that gets mated. */
+use crate::array_engine::{ChessBoard, Color, PieceType, COL_SIZE, ROW_SIZE};
+
+impl ChessBoard {
+    /** The pieces of the color besides its king, with the color of the square they stand on. */
+    fn pieces_besides_king(&self, color: Color) -> Vec<(PieceType, usize)> {
+        let mut pieces = Vec::new();
+
+        for rank in 0..ROW_SIZE {
+            for file in 0..COL_SIZE {
+                if let Some(piece) = self.piece_at((file, rank)) {
+                    if piece.color() == color && piece.piece_type() != PieceType::King {
+                        pieces.push((piece.piece_type(), (file + rank) % 2));
+                    }
+                }
+            }
+        }
+
+        pieces
+    }
+
+    /** Returns true if the color could mate with some series of legal moves. A lone king never
+    mates, and a single knight only mates when the opponent has more than the king, like in KNvKP,
+    where the pawn can block the escape of its king. Bishops on squares of one color only mate
+    when the opponent has a piece that is not a bishop on those squares to block with, since they
+    can never attack the squares of the other color. */
+    pub fn has_mating_material(&self, color: Color) -> bool {
+        let pieces = self.pieces_besides_king(color);
+        let opponent_pieces = self.pieces_besides_king(color.opposite());
+
+        if pieces.is_empty() {
+            return false;
+        }
+
+        if pieces
+            .iter()
+            .all(|(piece_type, _)| *piece_type == PieceType::Bishop)
+        {
+            let square_color = pieces[0].1;
+            if pieces.iter().any(|(_, other)| *other != square_color) {
+                return true;
+            }
+
+            return opponent_pieces
+                .iter()
+                .any(|piece| *piece != (PieceType::Bishop, square_color));
+        }
+
+        match pieces.as_slice() {
+            [(PieceType::Knight, _)] => !opponent_pieces.is_empty(),
+            _ => true,
+        }
+    }
+
+    /** Returns true if neither side can ever mate, so the game is drawn. */
+    pub fn is_insufficient_material(&self) -> bool {
+        !self.has_mating_material(Color::White) && !self.has_mating_material(Color::Black)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::array_engine::{ChessBoard, Color};
+
+    fn has_mating_material(fen: &str, color: Color) -> bool {
+        ChessBoard::new_from_fen(fen)
+            .unwrap()
+            .has_mating_material(color)
+    }
+
+    #[test]
+    fn test_mating_material() {
+        assert!(has_mating_material(
+            "8/8/4k3/8/2B5/3NK3/8/8 w - - 0 1",
+            Color::White
+        ));
+        assert!(!has_mating_material(
+            "8/8/4k3/8/2B5/3NK3/8/8 w - - 0 1",
+            Color::Black
+        ));
+
+        /* A knight mates with the help of a pawn, but not against a lone king. */
+        assert!(has_mating_material(
+            "8/8/4k3/8/2n5/4K3/P7/8 w - - 0 1",
+            Color::Black
+        ));
+        assert!(has_mating_material(
+            "8/8/4k3/8/2n5/4K3/P7/8 w - - 0 1",
+            Color::White
+        ));
+        assert!(!has_mating_material(
+            "8/8/4k3/8/2n5/4K3/8/8 w - - 0 1",
+            Color::Black
+        ));
+
+        /* Bishops on squares of one color never mate without an opposing piece to block with on
+         * the squares they cannot attack. */
+        assert!(!has_mating_material(
+            "8/8/4k3/8/2B5/4K3/8/8 w - - 0 1",
+            Color::White
+        ));
+        assert!(!has_mating_material(
+            "8/8/4k3/8/2B5/4K3/2b5/8 w - - 0 1",
+            Color::White
+        ));
+        assert!(has_mating_material(
+            "8/8/4k3/8/2B5/4K3/3b4/8 w - - 0 1",
+            Color::White
+        ));
+        assert!(!has_mating_material(
+            "8/8/4k3/8/2B1B3/4K3/8/8 w - - 0 1",
+            Color::White
+        ));
+        assert!(has_mating_material(
+            "8/8/4k3/8/2B1B3/4K3/8/4n3 w - - 0 1",
+            Color::White
+        ));
+        assert!(has_mating_material(
+            "8/8/4k3/8/2BB4/4K3/8/8 w - - 0 1",
+            Color::White
+        ));
+
+        assert!(
+            ChessBoard::new_from_fen("8/8/4k3/8/2B5/4K3/2b5/8 w - - 0 1")
+                .unwrap()
+                .is_insufficient_material()
+        );
+        assert!(ChessBoard::new_from_fen("8/8/4k3/8/2n5/4K3/8/8 w - - 0 1")
+            .unwrap()
+            .is_insufficient_material());
+        assert!(!ChessBoard::new().is_insufficient_material());
+    }
+}
@@ -47,9 +47,9 @@ impl ChessBoard {
 
         if depth > 1 {
             for legal_move in legal_moves {
-                let mut new_chessboard = chess_board.clone();
+                let mut new_chessboard = chess_board;
 
-                let _move_result = match new_chessboard.make_move(legal_move) {
+                match new_chessboard.make_move(legal_move) {
                     Ok(_) => {}
 
                     Err(_error) => {
@@ -69,3 +69,39 @@ impl ChessBoard {
         count
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perft_leaves(fen: &str, depth: i64) -> i64 {
+        let chess_board = ChessBoard::new_from_fen(fen).expect("test FEN should be valid");
+
+        ChessBoard::count_legal_move_leaves(chess_board, depth)
+    }
+
+    #[test]
+    fn test_perft_start_position() {
+        assert_eq!(perft_leaves(crate::fen::FEN_START_POSITION, 3), 8902);
+    }
+
+    #[test]
+    fn test_perft_kiwipete() {
+        assert_eq!(perft_leaves(KIWIPETE_FEN_POSITION, 2), 2039);
+    }
+
+    #[test]
+    fn test_perft_rook_end_game() {
+        assert_eq!(perft_leaves(ROOK_END_GAME_FEN, 3), 2812);
+    }
+
+    #[test]
+    fn test_perft_promotions() {
+        assert_eq!(perft_leaves(TEST_POS_1_FEN, 2), 1486);
+    }
+
+    #[test]
+    fn test_perft_middle_game() {
+        assert_eq!(perft_leaves(TEST_POS_2_FEN, 2), 2079);
+    }
+}
@@ -0,0 +1,97 @@
+/* This submodule ends games by the rules of chess: checkmate, stalemate, the fifty move rule,
+ * threefold repetition and insufficient material. The match runner, the GUI and the search all
+ * use it, so they agree on when a game is over. */
+use crate::array_engine::{ChessBoard, Color};
+
+pub const HALF_MOVES_FOR_DRAW: u64 = 100;
+
+/** How a game ended by the rules of chess. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Termination {
+    Checkmate { winner: Color },
+    Stalemate,
+    FiftyMoveRule,
+    ThreefoldRepetition,
+    InsufficientMaterial,
+}
+
+impl ChessBoard {
+    /** Returns true after 100 half moves without a capture or a pawn move. */
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.half_move_clock >= HALF_MOVES_FOR_DRAW
+    }
+
+    /** Returns how the game ended in this position, or None if it goes on. `position_history`
+    holds the hashes of the earlier positions of the game. A mate on the last move before the
+    fifty move rule still wins. */
+    pub fn termination(&self, position_history: &[u64]) -> Option<Termination> {
+        if self.legal_moves().is_empty() {
+            return Some(match self.king_is_in_check() {
+                true => Termination::Checkmate {
+                    winner: self.side_to_move().opposite(),
+                },
+                false => Termination::Stalemate,
+            });
+        }
+
+        if self.is_fifty_move_draw() {
+            return Some(Termination::FiftyMoveRule);
+        }
+
+        let hash = self.zobrist_hash();
+        let repetitions = position_history
+            .iter()
+            .filter(|previous_hash| **previous_hash == hash)
+            .count();
+        if repetitions >= 2 {
+            return Some(Termination::ThreefoldRepetition);
+        }
+
+        if self.is_insufficient_material() {
+            return Some(Termination::InsufficientMaterial);
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn termination(fen: &str, position_history: &[u64]) -> Option<Termination> {
+        ChessBoard::new_from_fen(fen)
+            .unwrap()
+            .termination(position_history)
+    }
+
+    #[test]
+    fn test_termination() {
+        assert_eq!(
+            termination("R5k1/5ppp/8/8/8/8/8/6K1 b - - 99 80", &[]),
+            Some(Termination::Checkmate {
+                winner: Color::White
+            })
+        );
+        assert_eq!(
+            termination("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", &[]),
+            Some(Termination::Stalemate)
+        );
+        assert_eq!(
+            termination("6k1/5ppp/8/8/8/8/8/R5K1 b - - 100 80", &[]),
+            Some(Termination::FiftyMoveRule)
+        );
+        assert_eq!(
+            termination("6k1/8/8/8/8/8/8/5BK1 w - - 0 1", &[]),
+            Some(Termination::InsufficientMaterial)
+        );
+
+        let chess_board = ChessBoard::new();
+        let hash = chess_board.zobrist_hash();
+        assert_eq!(chess_board.termination(&[hash, 1, 2, 3]), None);
+        assert_eq!(
+            chess_board.termination(&[hash, 1, 2, 3, hash, 4, 5, 6]),
+            Some(Termination::ThreefoldRepetition)
+        );
+    }
+}
@@ -0,0 +1,157 @@
+/* A FEN only describes where the pieces are, so it can describe positions that cannot occur in a
+ * game, like a missing king or a pawn on the last rank. The move generation assumes a position
+ * that can occur, so positions set up by users are validated before they are played. */
+use crate::array_engine::chess_errors::InvalidPosition;
+use crate::array_engine::{ChessBoard, Color, Piece, PieceType, COL_SIZE, ROW_SIZE};
+
+const MAX_PAWNS: usize = 8;
+const MAX_PIECES: usize = 16;
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    }
+}
+
+fn invalid(reason: String) -> Result<(), InvalidPosition> {
+    Err(InvalidPosition { reason })
+}
+
+impl ChessBoard {
+    /** Checks that the position can occur in a game: each side has one king and at most eight
+    pawns and sixteen pieces, no pawn is on the first or last rank, the side not to move is not in
+    check, and the castling rights and the en passant square fit the pieces on the board. */
+    pub fn validate(&self) -> Result<(), InvalidPosition> {
+        for color in [Color::White, Color::Black] {
+            let pieces: Vec<PieceType> = self
+                .board
+                .iter()
+                .flatten()
+                .flatten()
+                .filter(|piece| piece.color() == color)
+                .map(|piece| piece.piece_type())
+                .collect();
+            let count = |piece_type| pieces.iter().filter(|p| **p == piece_type).count();
+            let name = color_name(color);
+
+            match count(PieceType::King) {
+                0 => return invalid(format!("{name} has no king")),
+                1 => {}
+                kings => return invalid(format!("{name} has {kings} kings")),
+            }
+            if count(PieceType::Pawn) > MAX_PAWNS {
+                return invalid(format!("{name} has more than {MAX_PAWNS} pawns"));
+            }
+            if pieces.len() > MAX_PIECES {
+                return invalid(format!("{name} has more than {MAX_PIECES} pieces"));
+            }
+        }
+
+        for rank in [0, ROW_SIZE - 1] {
+            if self.board[rank]
+                .iter()
+                .flatten()
+                .any(|piece| piece.piece_type() == PieceType::Pawn)
+            {
+                return invalid(format!("a pawn is on rank {}", rank + 1));
+            }
+        }
+
+        let side_to_move = self.side_to_move();
+        if self.king_is_attacked(side_to_move.opposite()) == Some(true) {
+            return invalid(format!(
+                "{} is in check with {} to move",
+                color_name(side_to_move.opposite()),
+                color_name(side_to_move)
+            ));
+        }
+
+        self.validate_castling_ability()?;
+        self.validate_en_passant_target_square()
+    }
+
+    /** A side can only castle with its king and rook on their starting squares. */
+    fn validate_castling_ability(&self) -> Result<(), InvalidPosition> {
+        let castlings = [
+            (Color::White, 0, COL_SIZE - 1, "kingside"),
+            (Color::White, 0, 0, "queenside"),
+            (Color::Black, ROW_SIZE - 1, COL_SIZE - 1, "kingside"),
+            (Color::Black, ROW_SIZE - 1, 0, "queenside"),
+        ];
+
+        for ((color, rank, rook_file, side), can_castle) in
+            castlings.into_iter().zip(self.castling_ability)
+        {
+            let king = self.piece_at((4, rank)) == Some(Piece::new(color, PieceType::King));
+            let rook = self.piece_at((rook_file, rank)) == Some(Piece::new(color, PieceType::Rook));
+
+            if can_castle && !(king && rook) {
+                return invalid(format!(
+                    "{} cannot castle {side} without its king and rook on their squares",
+                    color_name(color)
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /** The en passant square is behind a pawn of the side not to move that just moved two
+    squares, so the square and the starting square of the pawn are empty. */
+    fn validate_en_passant_target_square(&self) -> Result<(), InvalidPosition> {
+        let Some((file, rank)) = self.en_passant_target_square else {
+            return Ok(());
+        };
+
+        let (target_rank, pawn_rank, start_rank) = match self.side_to_move() {
+            Color::White => (5, 4, 6),
+            Color::Black => (2, 3, 1),
+        };
+        let pawn = Piece::new(self.side_to_move().opposite(), PieceType::Pawn);
+
+        if rank != target_rank
+            || self.piece_at((file, pawn_rank)) != Some(pawn)
+            || self.piece_at((file, rank)).is_some()
+            || self.piece_at((file, start_rank)).is_some()
+        {
+            return invalid(format!(
+                "no pawn can be captured en passant on {}{}",
+                (b'a' + file as u8) as char,
+                rank + 1
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::array_engine::ChessBoard;
+
+    fn validate(fen: &str) -> Result<(), String> {
+        ChessBoard::new_from_fen(fen)
+            .unwrap()
+            .validate()
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_ok());
+        assert!(validate("rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 1").is_ok());
+
+        assert_eq!(
+            validate("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+            Err("Invalid position: Black has no king".to_string())
+        );
+        assert!(validate("4k3/8/8/8/8/8/8/3KK3 w - - 0 1").is_err());
+        assert!(validate("4k2P/8/8/8/8/8/8/4K3 w - - 0 1").is_err());
+        assert!(validate("4k3/8/8/8/8/8/8/4K2R b - - 0 1").is_ok());
+        assert!(validate("4k3/8/8/8/8/8/8/4K2r b - - 0 1").is_err());
+        assert!(validate("4k3/8/8/8/8/8/8/4K2R w KQ - 0 1").is_err());
+        assert!(validate("4k3/8/8/3pP3/8/8/8/4K3 w - e6 0 1").is_err());
+        assert!(validate("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").is_ok());
+    }
+}
@@ -0,0 +1,111 @@
+/* This module implements Zobrist hashing of a chessboard. The hash identifies a position in the
+ * transposition table and is used to detect repeated positions. */
+use crate::array_engine::{ChessBoard, Color, PieceType, COL_SIZE, ROW_SIZE};
+
+const PIECE_KEYS: usize = 12 * ROW_SIZE * COL_SIZE;
+const SIDE_TO_MOVE_KEY: usize = PIECE_KEYS;
+const CASTLING_KEYS: usize = SIDE_TO_MOVE_KEY + 1;
+const EN_PASSANT_KEYS: usize = CASTLING_KEYS + 4;
+const KEY_COUNT: usize = EN_PASSANT_KEYS + COL_SIZE;
+
+const ZOBRIST_KEYS: [u64; KEY_COUNT] = generate_zobrist_keys();
+
+/** Generates the keys at compile time using the SplitMix64 generator, this keeps the hashes
+stable between runs and builds. */
+const fn generate_zobrist_keys() -> [u64; KEY_COUNT] {
+    let mut keys: [u64; KEY_COUNT] = [0; KEY_COUNT];
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut i: usize = 0;
+
+    while i < KEY_COUNT {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z: u64 = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+
+    keys
+}
+
+fn piece_key_index(color: Color, piece_type: PieceType, file: usize, rank: usize) -> usize {
+    let color_index: usize = match color {
+        Color::White => 0,
+        Color::Black => 6,
+    };
+
+    let piece_index: usize = match piece_type {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    };
+
+    (color_index + piece_index) * ROW_SIZE * COL_SIZE + rank * COL_SIZE + file
+}
+
+impl ChessBoard {
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash: u64 = 0;
+
+        for (rank, squares) in self.board.iter().enumerate() {
+            for (file, square) in squares.iter().enumerate() {
+                if let Some(piece) = square {
+                    hash ^=
+                        ZOBRIST_KEYS[piece_key_index(piece.color, piece.piece_type, file, rank)];
+                }
+            }
+        }
+
+        if !self.white_is_side_to_move {
+            hash ^= ZOBRIST_KEYS[SIDE_TO_MOVE_KEY];
+        }
+
+        for (i, can_castle) in self.castling_ability.iter().enumerate() {
+            if *can_castle {
+                hash ^= ZOBRIST_KEYS[CASTLING_KEYS + i];
+            }
+        }
+
+        if let Some((file, _rank)) = self.en_passant_target_square {
+            hash ^= ZOBRIST_KEYS[EN_PASSANT_KEYS + file];
+        }
+
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::array_engine::ChessBoard;
+
+    #[test]
+    fn test_transposition_has_same_hash() {
+        let mut first_board = ChessBoard::new();
+        let mut second_board = ChessBoard::new();
+
+        for move_string in ["g1f3", "g8f6", "b1c3", "b8c6"] {
+            play_move(&mut first_board, move_string);
+        }
+
+        for move_string in ["b1c3", "b8c6", "g1f3", "g8f6"] {
+            play_move(&mut second_board, move_string);
+        }
+
+        assert_eq!(first_board.zobrist_hash(), second_board.zobrist_hash());
+        assert_ne!(first_board.zobrist_hash(), ChessBoard::new().zobrist_hash());
+    }
+
+    fn play_move(chess_board: &mut ChessBoard, move_string: &str) {
+        let chess_move = chess_board
+            .legal_moves()
+            .into_iter()
+            .find(|chess_move| chess_move.move_to_string() == move_string)
+            .expect("test move should be legal");
+
+        chess_board.make_move(chess_move).unwrap();
+    }
+}
@@ -0,0 +1,374 @@
+/* This module reads and writes Extended Position Description (EPD) records, which test suites
+ * and analysis dumps use. A record starts with the first four fields of a FEN, followed by
+ * operations made of an opcode, its operands and a semicolon, e.g.
+ * `r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5; id "Ruy Lopez";`.
+ * The clocks missing from the position come from the hmvc and fmvn operations. */
+use crate::array_engine::chess_errors::InvalidEpd;
+use crate::array_engine::{ChessBoard, Move};
+use std::fmt;
+use std::str::FromStr;
+
+/* Opcodes whose operand is a string, which is always written in quotes. */
+const STRING_OPCODES: [&str; 11] = [
+    "id", "c0", "c1", "c2", "c3", "c4", "c5", "c6", "c7", "c8", "c9",
+];
+
+#[derive(Debug, Clone)]
+pub struct Epd {
+    pub chess_board: ChessBoard,
+    /* The operations in the order they were read, as opcode and operands. */
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl Epd {
+    pub fn new(chess_board: ChessBoard) -> Epd {
+        Epd {
+            chess_board,
+            operations: Vec::new(),
+        }
+    }
+
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    /** Sets the operands of an operation, replacing an existing operation with the same opcode. */
+    pub fn set_operation(&mut self, opcode: &str, operands: Vec<String>) {
+        match self.operations.iter_mut().find(|(name, _)| name == opcode) {
+            Some((_, existing)) => *existing = operands,
+            None => self.operations.push((opcode.to_string(), operands)),
+        }
+    }
+
+    pub fn remove_operation(&mut self, opcode: &str) {
+        self.operations.retain(|(name, _)| name != opcode);
+    }
+
+    /** The name of the position in a test suite. */
+    pub fn id(&self) -> Option<&str> {
+        self.string_operand("id")
+    }
+
+    pub fn set_id(&mut self, id: &str) {
+        self.set_operation("id", vec![id.to_string()]);
+    }
+
+    /** One of the comments c0 to c9. */
+    pub fn comment(&self, index: usize) -> Option<&str> {
+        self.string_operand(&format!("c{index}"))
+    }
+
+    pub fn set_comment(&mut self, index: usize, comment: &str) {
+        self.set_operation(&format!("c{index}"), vec![comment.to_string()]);
+    }
+
+    /** The best moves of the position, any of them solves a test position. */
+    pub fn best_moves(&self) -> Result<Vec<Move>, InvalidEpd> {
+        self.moves_of("bm")
+    }
+
+    pub fn set_best_moves(&mut self, best_moves: &[Move]) {
+        self.set_moves("bm", best_moves);
+    }
+
+    /** The moves to avoid in the position, a test position is solved by any other move. */
+    pub fn avoid_moves(&self) -> Result<Vec<Move>, InvalidEpd> {
+        self.moves_of("am")
+    }
+
+    pub fn set_avoid_moves(&mut self, avoid_moves: &[Move]) {
+        self.set_moves("am", avoid_moves);
+    }
+
+    /** The predicted variation, where every move is played from the position after the moves
+    before it. */
+    pub fn principal_variation(&self) -> Result<Vec<Move>, InvalidEpd> {
+        let mut chess_board = self.chess_board;
+        let mut principal_variation: Vec<Move> = Vec::new();
+
+        for san in self.operation("pv").unwrap_or_default() {
+            let chess_move = resolve_san(&chess_board, "pv", san)?;
+            chess_board.make_move_on_board(&chess_move);
+            chess_board.update_meta_data(&chess_move);
+            principal_variation.push(chess_move);
+        }
+
+        Ok(principal_variation)
+    }
+
+    pub fn set_principal_variation(&mut self, principal_variation: &[Move]) {
+        let mut chess_board = self.chess_board;
+        let mut sans: Vec<String> = Vec::with_capacity(principal_variation.len());
+
+        for chess_move in principal_variation {
+            sans.push(chess_board.move_to_san(chess_move));
+            chess_board.make_move_on_board(chess_move);
+            chess_board.update_meta_data(chess_move);
+        }
+
+        self.set_operation("pv", sans);
+    }
+
+    /** The evaluation in centipawns from the view of the side to move. */
+    pub fn centipawn_evaluation(&self) -> Option<i32> {
+        self.number_operand("ce")
+    }
+
+    pub fn set_centipawn_evaluation(&mut self, centipawns: i32) {
+        self.set_operation("ce", vec![centipawns.to_string()]);
+    }
+
+    /** The depth in plies the position was analysed to. */
+    pub fn analysis_depth(&self) -> Option<u32> {
+        self.number_operand("acd")
+    }
+
+    pub fn set_analysis_depth(&mut self, depth: u32) {
+        self.set_operation("acd", vec![depth.to_string()]);
+    }
+
+    /** The number of moves of a forced mate for the side to move. */
+    pub fn direct_mate(&self) -> Option<u32> {
+        self.number_operand("dm")
+    }
+
+    fn string_operand(&self, opcode: &str) -> Option<&str> {
+        self.operation(opcode)?
+            .first()
+            .map(|operand| operand.as_str())
+    }
+
+    fn number_operand<T: FromStr>(&self, opcode: &str) -> Option<T> {
+        self.string_operand(opcode)?.parse().ok()
+    }
+
+    fn moves_of(&self, opcode: &str) -> Result<Vec<Move>, InvalidEpd> {
+        self.operation(opcode)
+            .unwrap_or_default()
+            .iter()
+            .map(|san| resolve_san(&self.chess_board, opcode, san))
+            .collect()
+    }
+
+    fn set_moves(&mut self, opcode: &str, moves: &[Move]) {
+        let sans = moves
+            .iter()
+            .map(|chess_move| self.chess_board.move_to_san(chess_move))
+            .collect();
+        self.set_operation(opcode, sans);
+    }
+}
+
+fn resolve_san(chess_board: &ChessBoard, opcode: &str, san: &str) -> Result<Move, InvalidEpd> {
+    chess_board.move_from_san(san).map_err(|e| InvalidEpd {
+        reason: format!("{e} in operation {opcode}"),
+    })
+}
+
+impl FromStr for Epd {
+    type Err = InvalidEpd;
+
+    fn from_str(line: &str) -> Result<Epd, InvalidEpd> {
+        let mut rest = line.trim();
+        let mut fields: Vec<&str> = Vec::with_capacity(4);
+
+        while fields.len() < 4 {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+
+            if end == 0 {
+                return Err(InvalidEpd {
+                    reason: format!("expected four position fields in {line}"),
+                });
+            }
+
+            fields.push(&rest[..end]);
+            rest = rest[end..].trim_start();
+        }
+
+        let operations = parse_operations(rest)?;
+        let clock = |opcode: &str, default: &str| {
+            operations
+                .iter()
+                .find(|(name, _)| name == opcode)
+                .and_then(|(_, operands)| operands.first().cloned())
+                .unwrap_or(default.to_string())
+        };
+        let fen = format!(
+            "{} {} {}",
+            fields.join(" "),
+            clock("hmvc", "0"),
+            clock("fmvn", "1")
+        );
+
+        let chess_board = ChessBoard::new_from_fen(&fen).map_err(|_| InvalidEpd {
+            reason: format!("invalid position {}", fields.join(" ")),
+        })?;
+
+        Ok(Epd {
+            chess_board,
+            operations,
+        })
+    }
+}
+
+/** Parses operations like `bm Nf3 e4; id "name";`. Operands in quotes may contain spaces and
+semicolons, and a backslash escapes a quote or a backslash inside them. */
+fn parse_operations(text: &str) -> Result<Vec<(String, Vec<String>)>, InvalidEpd> {
+    let mut operations: Vec<(String, Vec<String>)> = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        if chars.peek().is_none() {
+            return Ok(operations);
+        }
+
+        let mut opcode = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ';') {
+            opcode.push(c);
+        }
+
+        let is_valid_opcode = opcode.starts_with(|c: char| c.is_ascii_alphabetic())
+            && opcode
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_valid_opcode {
+            return Err(InvalidEpd {
+                reason: format!("invalid opcode {opcode:?}"),
+            });
+        }
+
+        let mut operands: Vec<String> = Vec::new();
+
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+            match chars.next() {
+                None => {
+                    return Err(InvalidEpd {
+                        reason: format!("operation {opcode} is not terminated with ;"),
+                    })
+                }
+                Some(';') => break,
+                Some('"') => {
+                    let mut operand = String::new();
+
+                    loop {
+                        match chars.next() {
+                            Some('\\') => operand.extend(chars.next()),
+                            Some('"') => break,
+                            Some(c) => operand.push(c),
+                            None => {
+                                return Err(InvalidEpd {
+                                    reason: format!("unterminated string in operation {opcode}"),
+                                })
+                            }
+                        }
+                    }
+
+                    operands.push(operand);
+                }
+                Some(c) => {
+                    let mut operand = c.to_string();
+                    while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ';') {
+                        operand.push(c);
+                    }
+                    operands.push(operand);
+                }
+            }
+        }
+
+        operations.push((opcode, operands));
+    }
+}
+
+impl fmt::Display for Epd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fen = self.chess_board.to_fen();
+        let position: Vec<&str> = fen.split(' ').take(4).collect();
+        write!(f, "{}", position.join(" "))?;
+
+        for (opcode, operands) in &self.operations {
+            write!(f, " {opcode}")?;
+
+            for operand in operands {
+                let needs_quotes = STRING_OPCODES.contains(&opcode.as_str())
+                    || operand.is_empty()
+                    || operand.contains(|c: char| c.is_whitespace() || c == ';' || c == '"');
+
+                if needs_quotes {
+                    let escaped = operand.replace('\\', "\\\\").replace('"', "\\\"");
+                    write!(f, " \"{escaped}\"")?;
+                } else {
+                    write!(f, " {operand}")?;
+                }
+            }
+
+            write!(f, ";")?;
+        }
+
+        Ok(())
+    }
+}
+
+/** Parses every record of an EPD file, skipping empty lines. */
+pub fn parse_epd(text: &str) -> Result<Vec<Epd>, InvalidEpd> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(Epd::from_str)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_operations() {
+        let epd: Epd = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5 Bc4; am d4; id \"Open; game\"; c0 \"say \\\"hi\\\"\"; ce -15; acd 12; pv Bb5 a6 Ba4; fmvn 3;"
+            .parse()
+            .unwrap();
+
+        assert_eq!(epd.chess_board.full_move_counter(), 3);
+        assert_eq!(epd.id(), Some("Open; game"));
+        assert_eq!(epd.comment(0), Some("say \"hi\""));
+        assert_eq!(epd.centipawn_evaluation(), Some(-15));
+        assert_eq!(epd.analysis_depth(), Some(12));
+
+        let best_moves: Vec<String> = epd
+            .best_moves()
+            .unwrap()
+            .iter()
+            .map(|chess_move| chess_move.move_to_string())
+            .collect();
+        assert_eq!(best_moves, vec!["f1b5", "f1c4"]);
+        assert_eq!(epd.avoid_moves().unwrap()[0].move_to_string(), "d2d4");
+        assert_eq!(epd.principal_variation().unwrap().len(), 3);
+
+        let written = epd.to_string();
+        assert_eq!(written, "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5 Bc4; am d4; id \"Open; game\"; c0 \"say \\\"hi\\\"\"; ce -15; acd 12; pv Bb5 a6 Ba4; fmvn 3;");
+        assert_eq!(written.parse::<Epd>().unwrap().operations, epd.operations);
+    }
+
+    #[test]
+    fn test_errors_and_setters() {
+        assert!("8/8/8/8 w - -".parse::<Epd>().is_err());
+        assert!("4k3/8/8/8/8/8/8/4K3 w - - bm Kd1".parse::<Epd>().is_err());
+
+        let mut epd: Epd = "4k3/8/8/8/8/8/8/4K3 w - - bm Qd1;".parse().unwrap();
+        assert!(epd.best_moves().is_err());
+
+        let king_move = epd.chess_board.move_from_san("Kd2").unwrap();
+        epd.set_best_moves(&[king_move]);
+        epd.set_principal_variation(&[king_move]);
+        epd.set_id("kings");
+        assert_eq!(
+            epd.to_string(),
+            "4k3/8/8/8/8/8/8/4K3 w - - bm Kd2; pv Kd2; id \"kings\";"
+        );
+    }
+}
@@ -36,7 +36,7 @@ pub fn split_at_space(fen: &str) -> Vec<String> {
 
     for c in fen.chars() {
         if c == ' ' {
-            if string_buffer.len() > 0 {
+            if !string_buffer.is_empty() {
                 split_fen.push(string_buffer.clone());
                 string_buffer.clear()
             } else {
@@ -47,7 +47,7 @@ pub fn split_at_space(fen: &str) -> Vec<String> {
         }
     }
 
-    if string_buffer.len() > 0 {
+    if !string_buffer.is_empty() {
         split_fen.push(string_buffer);
     }
 
@@ -86,7 +86,8 @@ fn fen_check_board_validity_optimized(fen: &str) -> bool {
             }
         }
     }
-    true
+
+    current_row == 0 && squares_on_rows.iter().all(|&x| x == ROW_SIZE as u32)
 }
 
 fn fen_check_side_to_move(side_to_move: &str) -> bool {
@@ -114,28 +115,28 @@ fn fen_check_castling_ability(castling_string: &str) -> bool {
         match c {
             'K' => {
                 current = 0;
-                if castling_ability[current] == true {
+                if castling_ability[current] {
                     return false;
                 }
                 castling_ability[current] = true;
             }
             'Q' => {
                 current = 1;
-                if castling_ability[current] == true {
+                if castling_ability[current] {
                     return false;
                 }
                 castling_ability[current] = true;
             }
             'k' => {
                 current = 2;
-                if castling_ability[current] == true {
+                if castling_ability[current] {
                     return false;
                 }
                 castling_ability[current] = true;
             }
             'q' => {
                 current = 3;
-                if castling_ability[current] == true {
+                if castling_ability[current] {
                     return false;
                 }
                 castling_ability[current] = true;
@@ -153,82 +154,37 @@ fn fen_check_en_passant(en_passant: &str) -> bool {
         return true;
     }
 
-    if !en_passant.len() == 2 {
+    if en_passant.len() != 2 {
         return false;
-    } else {
-        let mut square_iter = en_passant.chars();
-        let file_letter = square_iter.next();
-
-        match file_letter {
-            Some(c) => {
-                if c == 'a'
-                    || c == 'b'
-                    || c == 'c'
-                    || c == 'd'
-                    || c == 'e'
-                    || c == 'f'
-                    || c == 'g'
-                    || c == 'h'
-                {
-                    let eprank = square_iter.next();
-
-                    match eprank {
-                        Some(n) => {
-                            let number = n.to_digit(10).unwrap_or(1);
-                            if number != 3 || number != 6 {
-                                return false;
-                            }
-                        }
-                        None => return false,
-                    }
-                } else {
-                    return false;
-                }
-            }
-            None => return false,
-        }
     }
-    true
+
+    let mut square_iter = en_passant.chars();
+
+    match square_iter.next() {
+        Some('a'..='h') => matches!(
+            square_iter.next().and_then(|n| n.to_digit(10)),
+            Some(3) | Some(6)
+        ),
+        _ => false,
+    }
 }
 
 fn fen_check_halfmove(halfmove: &str) -> bool {
-    if halfmove.len() > 2 {
+    if halfmove.len() > 3 {
         return false;
     }
 
-    let parsed_halfmove = halfmove.parse::<u32>();
-
-    match parsed_halfmove {
-        Ok(number) => {
-            if number > 50 {
-                return false;
-            }
-        }
-        Err(_e) => return false,
-    }
-    true
+    halfmove.parse::<u32>().is_ok()
 }
 
+/* The half move clock is not compared against the full move counter, since positions set up from
+ * e.g. puzzles may use any full move number. */
 fn fen_check_fullmove(fullmove: &str, halfmove: &str) -> bool {
-    let halfmove_result = halfmove.parse::<u32>();
-    let halfmove_parsed: u32 = match halfmove_result {
-        Ok(number) => number,
-        Err(_e) => return false,
-    };
-
-    let fullmove_result = fullmove.parse::<u32>();
-    let fullmove_parsed: u32 = match fullmove_result {
-        Ok(number) => number,
-        Err(_e) => return false,
-    };
-
-    {
-        if fullmove_parsed > halfmove_parsed {
-            true
-        } else {
-            false
-        }
+    if halfmove.parse::<u32>().is_err() {
+        return false;
     }
+
+    matches!(fullmove.parse::<u32>(), Ok(number) if number > 0)
 }
 
 fn fen_check_hyphen(fen_slice: &str) -> bool {
@@ -241,6 +197,7 @@ fn fen_check_hyphen(fen_slice: &str) -> bool {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::items_after_test_module)]
 mod tests {
     use super::*;
 
@@ -264,6 +221,23 @@ mod tests {
             false
         );
     }
+
+    #[test]
+    fn test_bad_fen3() {
+        assert!(!is_fen_valid("8/8/8/8 w - - 0 1"));
+    }
+
+    #[test]
+    fn test_generate_fen() {
+        let fen = "r3k2r/pp1n1ppp/8/2pP4/8/8/PPP2PPP/R3K2R w Kq c6 0 12";
+        let chess_board = crate::array_engine::ChessBoard::new_from_fen(fen).unwrap();
+
+        assert_eq!(chess_board.to_fen(), fen);
+        assert_eq!(
+            crate::array_engine::ChessBoard::new().to_fen(),
+            FEN_START_POSITION
+        );
+    }
 }
 
 /* FEN parsing functions */
@@ -410,7 +384,7 @@ pub fn parse_fen_castling_ability(fen: &str) -> [bool; 4] {
             }
         }
     }
-    return c_ability;
+    c_ability
 }
 
 pub fn parse_fen_epawn(fen: &str) -> Option<Position> {
@@ -420,22 +394,19 @@ pub fn parse_fen_epawn(fen: &str) -> Option<Position> {
         return None;
     }
 
-    let parsed_epawn: Position;
-
     let epawn_file: usize;
-    let epawn_rank: usize;
 
     let file = fen_iter.next().unwrap();
 
     match file {
-        'a' => epawn_file = 1,
-        'b' => epawn_file = 2,
-        'c' => epawn_file = 3,
-        'd' => epawn_file = 4,
-        'e' => epawn_file = 5,
-        'f' => epawn_file = 6,
-        'g' => epawn_file = 7,
-        'h' => epawn_file = 8,
+        'a' => epawn_file = 0,
+        'b' => epawn_file = 1,
+        'c' => epawn_file = 2,
+        'd' => epawn_file = 3,
+        'e' => epawn_file = 4,
+        'f' => epawn_file = 5,
+        'g' => epawn_file = 6,
+        'h' => epawn_file = 7,
         _ => {
             panic!("unknown file character: parse_fen_e_pawn")
         }
@@ -450,13 +421,13 @@ pub fn parse_fen_epawn(fen: &str) -> Option<Position> {
         }
     };
 
-    if 0 < rank && rank <= 8 {
-        epawn_rank = rank;
+    let epawn_rank: usize = if 0 < rank && rank <= 8 {
+        rank - 1
     } else {
         panic!("rank not valid, must have value between 1 and 8");
-    }
+    };
 
-    parsed_epawn = (epawn_file, epawn_rank);
+    let parsed_epawn: Position = (epawn_file, epawn_rank);
 
     Some(parsed_epawn)
 }
@@ -474,3 +445,71 @@ pub fn parse_fen_full_move_counter(fen: &str) -> u64 {
     );
     fen.parse::<u64>().expect(error_msg.as_str())
 }
+
+/* FEN generation functions */
+pub fn generate_fen_piece_placement(board: &Board) -> String {
+    let mut ranks: Vec<String> = Vec::with_capacity(ROW_SIZE);
+
+    for rank in board.iter().rev() {
+        let mut fen_rank = String::new();
+        let mut empty_squares = 0;
+
+        for square in rank {
+            match square {
+                Some(piece) => {
+                    if empty_squares > 0 {
+                        fen_rank.push_str(&empty_squares.to_string());
+                        empty_squares = 0;
+                    }
+                    fen_rank.push(generate_fen_piece(piece));
+                }
+                None => empty_squares += 1,
+            }
+        }
+
+        if empty_squares > 0 {
+            fen_rank.push_str(&empty_squares.to_string());
+        }
+        ranks.push(fen_rank);
+    }
+
+    ranks.join("/")
+}
+
+fn generate_fen_piece(piece: &Piece) -> char {
+    let c = match piece.piece_type() {
+        King => 'k',
+        Queen => 'q',
+        Rook => 'r',
+        Bishop => 'b',
+        Knight => 'n',
+        Pawn => 'p',
+    };
+
+    match piece.color() {
+        White => c.to_ascii_uppercase(),
+        Black => c,
+    }
+}
+
+pub fn generate_fen_castling_ability(castling_ability: [bool; 4]) -> String {
+    let castling: String = "KQkq"
+        .chars()
+        .zip(castling_ability)
+        .filter(|(_, can_castle)| *can_castle)
+        .map(|(c, _)| c)
+        .collect();
+
+    if castling.is_empty() {
+        "-".to_string()
+    } else {
+        castling
+    }
+}
+
+pub fn generate_fen_epawn(en_passant_target_square: Option<Position>) -> String {
+    match en_passant_target_square {
+        Some((file, rank)) => format!("{}{}", (b'a' + file as u8) as char, rank + 1),
+        None => "-".to_string(),
+    }
+}
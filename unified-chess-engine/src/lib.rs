@@ -1,6 +1,11 @@
 pub mod array_engine;
 mod bitboard_engine;
+pub mod epd;
 mod fen;
+pub mod opening_book;
+pub mod pgn;
+pub mod search;
+pub mod syzygy;
 
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
@@ -0,0 +1,298 @@
+/* This module reads opening books in the Polyglot format. A book is a file of 16 byte entries
+ * sorted by the Polyglot hash of the position, each entry holds one move for the position and a
+ * weight that tells how good the move is. All numbers are stored as big endian. */
+pub mod book_builder;
+pub mod polyglot_hash;
+
+use crate::array_engine::chess_errors::InvalidOpeningBook;
+use crate::array_engine::{ChessBoard, Move, PieceType, Position};
+use polyglot_hash::polyglot_hash;
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::path::Path;
+
+const ENTRY_SIZE: usize = 16;
+
+/** One entry of a Polyglot book, the move is still in the Polyglot encoding. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+/** A legal move found in the book for a position. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookMove {
+    pub chess_move: Move,
+    pub weight: u16,
+}
+
+/** How a move is picked when the book has several moves for a position. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BookMoveSelection {
+    /* Always plays the move with the highest weight. */
+    BestMove,
+    /* Plays a random move, where moves with a higher weight are played more often. */
+    WeightedRandom,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OpeningBook {
+    entries: Vec<BookEntry>,
+}
+
+impl OpeningBook {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<OpeningBook, InvalidOpeningBook> {
+        let bytes = fs::read(path.as_ref()).map_err(|e| InvalidOpeningBook {
+            reason: format!("could not read {}: {e}", path.as_ref().display()),
+        })?;
+
+        OpeningBook::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<OpeningBook, InvalidOpeningBook> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(InvalidOpeningBook {
+                reason: format!("the size is not a multiple of {ENTRY_SIZE} bytes"),
+            });
+        }
+
+        let entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|entry| BookEntry {
+                key: u64::from_be_bytes(entry[0..8].try_into().unwrap()),
+                raw_move: u16::from_be_bytes(entry[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(entry[10..12].try_into().unwrap()),
+                learn: u32::from_be_bytes(entry[12..16].try_into().unwrap()),
+            })
+            .collect();
+
+        if !entries.windows(2).all(|pair| pair[0].key <= pair[1].key) {
+            return Err(InvalidOpeningBook {
+                reason: "the entries are not sorted by key".to_string(),
+            });
+        }
+
+        Ok(OpeningBook { entries })
+    }
+
+    /** Creates a book from entries in any order. */
+    pub fn from_entries(mut entries: Vec<BookEntry>) -> OpeningBook {
+        entries.sort_by_key(|entry| (entry.key, std::cmp::Reverse(entry.weight), entry.raw_move));
+
+        OpeningBook { entries }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+
+        for entry in &self.entries {
+            bytes.extend(entry.key.to_be_bytes());
+            bytes.extend(entry.raw_move.to_be_bytes());
+            bytes.extend(entry.weight.to_be_bytes());
+            bytes.extend(entry.learn.to_be_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), InvalidOpeningBook> {
+        fs::write(path.as_ref(), self.to_bytes()).map_err(|e| InvalidOpeningBook {
+            reason: format!("could not write {}: {e}", path.as_ref().display()),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /** Returns the entries of the position, found by a binary search on the sorted keys. */
+    pub fn entries(&self, chess_board: &ChessBoard) -> &[BookEntry] {
+        let key = polyglot_hash(chess_board);
+        let first = self.entries.partition_point(|entry| entry.key < key);
+        let last = self.entries.partition_point(|entry| entry.key <= key);
+
+        &self.entries[first..last]
+    }
+
+    /** Returns the legal book moves of the position sorted by weight, entries with moves that are
+    not legal on the board are skipped, which can happen on hash collisions. */
+    pub fn book_moves(&self, chess_board: &ChessBoard) -> Vec<BookMove> {
+        let mut book_moves: Vec<BookMove> = self
+            .entries(chess_board)
+            .iter()
+            .filter_map(|entry| {
+                Some(BookMove {
+                    chess_move: decode_move(chess_board, entry.raw_move)?,
+                    weight: entry.weight,
+                })
+            })
+            .collect();
+
+        book_moves.sort_by_key(|book_move| std::cmp::Reverse(book_move.weight));
+        book_moves
+    }
+
+    pub fn pick_move(
+        &self,
+        chess_board: &ChessBoard,
+        selection: BookMoveSelection,
+    ) -> Option<Move> {
+        let book_moves = self.book_moves(chess_board);
+
+        match selection {
+            BookMoveSelection::BestMove => book_moves.first().map(|book_move| book_move.chess_move),
+            BookMoveSelection::WeightedRandom => {
+                let total_weight: u64 = book_moves
+                    .iter()
+                    .map(|book_move| book_move.weight as u64)
+                    .sum();
+
+                /* Books where every weight is zero still have moves worth playing. */
+                if total_weight == 0 {
+                    return book_moves.first().map(|book_move| book_move.chess_move);
+                }
+
+                let mut remaining_weight = random_number() % total_weight;
+
+                book_moves.into_iter().find_map(|book_move| {
+                    if remaining_weight < book_move.weight as u64 {
+                        Some(book_move.chess_move)
+                    } else {
+                        remaining_weight -= book_move.weight as u64;
+                        None
+                    }
+                })
+            }
+        }
+    }
+}
+
+/** Converts a Polyglot move to a legal move on the board. Polyglot encodes castling as the king
+capturing its own rook, e.g. e1h1 for white kingside castling. */
+pub fn decode_move(chess_board: &ChessBoard, raw_move: u16) -> Option<Move> {
+    let square =
+        |bits: u16| -> Position { ((bits & 0b111) as usize, ((bits >> 3) & 0b111) as usize) };
+
+    let start_pos = square(raw_move >> 6);
+    let mut end_pos = square(raw_move);
+    let promotion_piece = match (raw_move >> 12) & 0b111 {
+        1 => Some(PieceType::Knight),
+        2 => Some(PieceType::Bishop),
+        3 => Some(PieceType::Rook),
+        4 => Some(PieceType::Queen),
+        _ => None,
+    };
+
+    let moves_king = chess_board
+        .piece_at(start_pos)
+        .is_some_and(|piece| piece.piece_type() == PieceType::King);
+
+    if moves_king && start_pos.0 == 4 && start_pos.1 == end_pos.1 {
+        match end_pos.0 {
+            7 => end_pos.0 = 6,
+            0 => end_pos.0 = 2,
+            _ => {}
+        }
+    }
+
+    chess_board.find_legal_move(start_pos, end_pos, promotion_piece)
+}
+
+/** Converts a move to the Polyglot encoding, the inverse of `decode_move`. */
+pub fn encode_move(chess_move: &Move) -> u16 {
+    let square = |position: Position| -> u16 { (position.0 + 8 * position.1) as u16 };
+
+    let mut end_pos = chess_move.end_pos;
+
+    if chess_move.meta_data.is_castling_move() {
+        end_pos.0 = if end_pos.0 == 6 { 7 } else { 0 };
+    }
+
+    let promotion: u16 = match chess_move.meta_data.promotion_piece {
+        Some(PieceType::Knight) => 1,
+        Some(PieceType::Bishop) => 2,
+        Some(PieceType::Rook) => 3,
+        Some(PieceType::Queen) => 4,
+        _ => 0,
+    };
+
+    square(end_pos) | (square(chess_move.start_pos) << 6) | (promotion << 12)
+}
+
+fn random_number() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry_bytes(key: u64, raw_move: u16, weight: u16) -> Vec<u8> {
+        let mut bytes = key.to_be_bytes().to_vec();
+        bytes.extend(raw_move.to_be_bytes());
+        bytes.extend(weight.to_be_bytes());
+        bytes.extend(0u32.to_be_bytes());
+        bytes
+    }
+
+    fn find_move(chess_board: &ChessBoard, move_string: &str) -> Move {
+        chess_board
+            .legal_moves()
+            .into_iter()
+            .find(|chess_move| chess_move.move_to_string() == move_string)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_picks_moves_from_book() {
+        let chess_board = ChessBoard::new();
+        let key = polyglot_hash(&chess_board);
+        let e2e4 = encode_move(&find_move(&chess_board, "e2e4"));
+        let d2d4 = encode_move(&find_move(&chess_board, "d2d4"));
+
+        let mut bytes = entry_bytes(key - 1, d2d4, 1);
+        bytes.extend(entry_bytes(key, d2d4, 10));
+        bytes.extend(entry_bytes(key, e2e4, 30));
+        bytes.extend(entry_bytes(key + 1, e2e4, 1));
+        let book = OpeningBook::from_bytes(&bytes).unwrap();
+
+        let book_moves = book.book_moves(&chess_board);
+        assert_eq!(book_moves.len(), 2);
+        assert_eq!(book_moves[0].chess_move.move_to_string(), "e2e4");
+        assert_eq!(book_moves[0].weight, 30);
+
+        let best_move = book.pick_move(&chess_board, BookMoveSelection::BestMove);
+        assert_eq!(best_move.unwrap().move_to_string(), "e2e4");
+        assert!(book
+            .pick_move(&chess_board, BookMoveSelection::WeightedRandom)
+            .is_some());
+    }
+
+    #[test]
+    fn test_castling_uses_polyglot_convention() {
+        let chess_board =
+            ChessBoard::new_from_fen("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1").unwrap();
+        let castling_move = find_move(&chess_board, "e1g1");
+
+        /* e1h1: from square 4, to square 7. */
+        assert_eq!(encode_move(&castling_move), (4 << 6) | 7);
+        assert_eq!(decode_move(&chess_board, (4 << 6) | 7), Some(castling_move));
+        assert_eq!(
+            decode_move(&chess_board, 4 << 6).unwrap().move_to_string(),
+            "e1c1"
+        );
+    }
+
+    #[test]
+    fn test_rejects_truncated_book() {
+        assert!(OpeningBook::from_bytes(&[0; 15]).is_err());
+    }
+}
@@ -0,0 +1,234 @@
+/* This module builds Polyglot opening books from collections of games. Every game is replayed
+ * and the statistics of each move played in each position are collected. The weight of a book
+ * move is the score the moving side achieved with it, counting two points for a win and one for
+ * a draw, like the Polyglot book builder does. */
+use crate::array_engine::{ChessBoard, Color};
+use crate::opening_book::polyglot_hash::polyglot_hash;
+use crate::opening_book::{encode_move, BookEntry, OpeningBook};
+use crate::pgn::{GameResult, PgnGame};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookBuilderOptions {
+    /* Moves after this many plies are not added to the book. */
+    pub max_ply: usize,
+    /* Moves played in fewer games are left out of the book. */
+    pub min_games: u32,
+    /* Only adds the moves of one side, e.g. for a repertoire with the white pieces. */
+    pub only_color: Option<Color>,
+}
+
+impl Default for BookBuilderOptions {
+    fn default() -> Self {
+        BookBuilderOptions {
+            max_ply: 20,
+            min_games: 1,
+            only_color: None,
+        }
+    }
+}
+
+/** The statistics of one move in one position. */
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MoveStatistics {
+    pub games: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MoveStatistics {
+    /** The score of the moving side, two points for a win and one for a draw. */
+    pub fn score(&self) -> u64 {
+        2 * self.wins as u64 + self.draws as u64
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BookBuilder {
+    options: BookBuilderOptions,
+    statistics: HashMap<(u64, u16), MoveStatistics>,
+    games: usize,
+}
+
+impl BookBuilder {
+    pub fn new(options: BookBuilderOptions) -> BookBuilder {
+        BookBuilder {
+            options,
+            statistics: HashMap::new(),
+            games: 0,
+        }
+    }
+
+    /** Adds the moves of the main line of the game to the statistics. */
+    pub fn add_game(&mut self, game: &PgnGame) {
+        for (chess_board, chess_move) in game.positions().iter().take(self.options.max_ply) {
+            let side_to_move = chess_board.side_to_move();
+
+            if self
+                .options
+                .only_color
+                .is_some_and(|color| color != side_to_move)
+            {
+                continue;
+            }
+
+            let key = (polyglot_hash(chess_board), encode_move(chess_move));
+            let statistics = self.statistics.entry(key).or_default();
+            statistics.games += 1;
+
+            match (game.result, side_to_move) {
+                (GameResult::WhiteWins, Color::White) | (GameResult::BlackWins, Color::Black) => {
+                    statistics.wins += 1
+                }
+                (GameResult::WhiteWins, Color::Black) | (GameResult::BlackWins, Color::White) => {
+                    statistics.losses += 1
+                }
+                (GameResult::Draw, _) => statistics.draws += 1,
+                (GameResult::Unknown, _) => {}
+            }
+        }
+
+        self.games += 1;
+    }
+
+    pub fn games(&self) -> usize {
+        self.games
+    }
+
+    pub fn statistics(&self, chess_board: &ChessBoard) -> Vec<(u16, MoveStatistics)> {
+        let key = polyglot_hash(chess_board);
+
+        self.statistics
+            .iter()
+            .filter(|((position_key, _), _)| *position_key == key)
+            .map(|((_, raw_move), statistics)| (*raw_move, *statistics))
+            .collect()
+    }
+
+    /** Builds the book from the moves played in at least the minimum number of games. The
+    scores are scaled down when they do not fit in the 16 bit weights. Every move gets a weight of
+    at least one, so the moves of games without a result, like a repertoire, are still played. */
+    pub fn build(&self) -> OpeningBook {
+        let selected: Vec<(&(u64, u16), &MoveStatistics)> = self
+            .statistics
+            .iter()
+            .filter(|(_, statistics)| statistics.games >= self.options.min_games)
+            .collect();
+
+        let max_score = selected
+            .iter()
+            .map(|(_, statistics)| statistics.score())
+            .max()
+            .unwrap_or(0);
+        let divisor = max_score.div_ceil(u16::MAX as u64).max(1);
+
+        OpeningBook::from_entries(
+            selected
+                .into_iter()
+                .map(|((key, raw_move), statistics)| BookEntry {
+                    key: *key,
+                    raw_move: *raw_move,
+                    weight: (statistics.score() / divisor).max(1) as u16,
+                    learn: 0,
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opening_book::BookMoveSelection;
+    use crate::pgn::parse_pgn;
+
+    #[test]
+    fn test_builds_book_from_games() {
+        let games = parse_pgn(
+            "1. e4 e5 2. Nf3 1-0\n\n1. e4 c5 1/2-1/2\n\n1. d4 d5 0-1\n\n1. e4 e5 2. Bc4 0-1\n",
+        )
+        .unwrap();
+        let mut builder = BookBuilder::new(BookBuilderOptions {
+            max_ply: 2,
+            min_games: 1,
+            only_color: None,
+        });
+
+        for game in &games {
+            builder.add_game(game);
+        }
+
+        let book = OpeningBook::from_bytes(&builder.build().to_bytes()).unwrap();
+        let chess_board = ChessBoard::new();
+        let book_moves = book.book_moves(&chess_board);
+
+        /* e4 scored a win, a draw and a loss, d4 only lost and keeps the lowest weight. */
+        assert_eq!(book_moves.len(), 2);
+        assert_eq!(book_moves[0].chess_move.move_to_string(), "e2e4");
+        assert_eq!(book_moves[0].weight, 3);
+        assert_eq!(book_moves[1].weight, 1);
+
+        let e4 = book
+            .pick_move(&chess_board, BookMoveSelection::BestMove)
+            .unwrap();
+        let mut after_e4 = chess_board;
+        after_e4.make_move(e4).unwrap();
+        assert_eq!(book.book_moves(&after_e4).len(), 2);
+
+        /* The depth cutoff leaves out the third ply. */
+        assert_eq!(book.len(), 5);
+    }
+
+    #[test]
+    fn test_builds_book_from_games_without_result() {
+        let games = parse_pgn(
+            "1. e4 e5 *
+
+1. d4 d5 *
+
+1. e4 c5 *
+",
+        )
+        .unwrap();
+        let mut builder = BookBuilder::new(BookBuilderOptions::default());
+
+        for game in &games {
+            builder.add_game(game);
+        }
+
+        let book = builder.build();
+        let book_moves = book.book_moves(&ChessBoard::new());
+
+        assert_eq!(book.len(), 5);
+        assert_eq!(book_moves.len(), 2);
+        assert!(book_moves.iter().all(|book_move| book_move.weight == 1));
+        assert!(book
+            .pick_move(&ChessBoard::new(), BookMoveSelection::BestMove)
+            .is_some());
+    }
+
+    #[test]
+    fn test_min_games_and_color_filter() {
+        let games = parse_pgn("1. e4 e5 1-0\n\n1. e4 c5 1-0\n\n1. d4 d5 1-0\n").unwrap();
+        let mut builder = BookBuilder::new(BookBuilderOptions {
+            max_ply: 10,
+            min_games: 2,
+            only_color: Some(Color::White),
+        });
+
+        for game in &games {
+            builder.add_game(game);
+        }
+
+        let book = builder.build();
+
+        assert_eq!(book.len(), 1);
+        assert_eq!(
+            book.book_moves(&ChessBoard::new())[0]
+                .chess_move
+                .move_to_string(),
+            "e2e4"
+        );
+    }
+}
@@ -0,0 +1,503 @@
+/* This module reads and writes games in Portable Game Notation (PGN). A reader streams the games
+ * of a file one at a time, so collections of any size can be processed. The moves are replayed
+ * while reading, every SAN move has to match exactly one legal move of its position, and errors
+ * report the line and column of the offending token. Comments, numeric annotation glyphs (NAGs)
+ * and recursive variations are kept with the move they follow. */
+mod lexer;
+mod writer;
+
+use crate::array_engine::chess_errors::InvalidPgn;
+use crate::array_engine::{ChessBoard, Move};
+use lexer::{Lexer, Located, Token};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/* Deeper variations are rejected, so a broken file cannot exhaust the stack. */
+const MAX_VARIATION_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /* The game is still going on, or the result is unknown. */
+    Unknown,
+}
+
+impl GameResult {
+    pub fn from_pgn(result: &str) -> Option<GameResult> {
+        match result {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unknown),
+            _ => None,
+        }
+    }
+
+    pub fn to_pgn(self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*",
+        }
+    }
+}
+
+/** A move of a game together with its annotations. The variations are alternatives to this
+move, each starting from the position before it. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnMove {
+    pub chess_move: Move,
+    pub san: String,
+    pub nags: Vec<u8>,
+    /* A comment in front of the move, only used for the first move of a line. */
+    pub comment_before: Option<String>,
+    pub comment: Option<String>,
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    pub fn new(chess_board: &ChessBoard, chess_move: Move) -> PgnMove {
+        PgnMove {
+            chess_move,
+            san: chess_board.move_to_san(&chess_move),
+            nags: Vec::new(),
+            comment_before: None,
+            comment: None,
+            variations: Vec::new(),
+        }
+    }
+
+    /** Returns the remaining clock time after the move, from a `[%clk 1:05:30]` command in its
+    comment. */
+    pub fn clock(&self) -> Option<Duration> {
+        let mut parts = self.command("clk")?.split(':').rev();
+        let seconds: f64 = parts.next()?.parse().ok()?;
+        let minutes: u64 = parts.next().map_or(Some(0), |part| part.parse().ok())?;
+        let hours: u64 = parts.next().map_or(Some(0), |part| part.parse().ok())?;
+
+        if !seconds.is_finite() || seconds < 0.0 || parts.next().is_some() {
+            return None;
+        }
+
+        Some(Duration::from_secs(hours * 3600 + minutes * 60) + Duration::from_secs_f64(seconds))
+    }
+
+    /** Sets the `[%clk]` command of the comment. Tenths of a second are only written when they
+    are not zero. */
+    pub fn set_clock(&mut self, clock: Duration) {
+        let tenths = clock.subsec_millis() / 100;
+        let seconds = clock.as_secs();
+        let mut value = format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        );
+
+        if tenths > 0 {
+            value.push_str(&format!(".{tenths}"));
+        }
+
+        self.set_command("clk", &value);
+    }
+
+    /** Returns the evaluation after the move, from an `[%eval 0.25]` or `[%eval #-3]` command in
+    its comment. */
+    pub fn evaluation(&self) -> Option<Evaluation> {
+        let value = self.command("eval")?;
+
+        match value.strip_prefix('#') {
+            Some(mate) => mate.parse().ok().map(Evaluation::Mate),
+            None => {
+                let pawns: f64 = value.parse().ok()?;
+                pawns
+                    .is_finite()
+                    .then(|| Evaluation::Centipawns((pawns * 100.0).round() as i32))
+            }
+        }
+    }
+
+    pub fn set_evaluation(&mut self, evaluation: Evaluation) {
+        let value = match evaluation {
+            Evaluation::Centipawns(centipawns) => {
+                let sign = if centipawns < 0 { "-" } else { "" };
+                let centipawns = centipawns.unsigned_abs();
+                format!("{sign}{}.{:02}", centipawns / 100, centipawns % 100)
+            }
+            Evaluation::Mate(moves) => format!("#{moves}"),
+        };
+
+        self.set_command("eval", &value);
+    }
+
+    /** Returns the value of an embedded command like `[%clk 0:05:00]` in the comment. */
+    fn command(&self, name: &str) -> Option<&str> {
+        let comment = self.comment.as_deref()?;
+        let start = comment.find(&format!("[%{name} "))? + name.len() + 3;
+        let length = comment[start..].find(']')?;
+
+        Some(comment[start..start + length].trim())
+    }
+
+    /** Replaces the command in the comment, or puts it in front of the comment text. */
+    fn set_command(&mut self, name: &str, value: &str) {
+        let command = format!("[%{name} {value}]");
+        let comment = self.comment.take().unwrap_or_default();
+        let prefix = format!("[%{name} ");
+
+        let comment = match comment.find(&prefix) {
+            Some(start) => match comment[start..].find(']') {
+                Some(length) => format!(
+                    "{}{command}{}",
+                    &comment[..start],
+                    &comment[start + length + 1..]
+                ),
+                None => format!("{command} {comment}"),
+            },
+            None if comment.is_empty() => command,
+            None => format!("{command} {comment}"),
+        };
+
+        self.comment = Some(comment);
+    }
+}
+
+/** An engine evaluation from the view of white, as written in `[%eval]` commands. A mate is
+given in moves, negative when black mates. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Evaluation {
+    Centipawns(i32),
+    Mate(i32),
+}
+
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    /* The position given by the FEN tag, or the start position. */
+    pub starting_position: ChessBoard,
+    /* The main line of the game. */
+    pub moves: Vec<PgnMove>,
+    pub result: GameResult,
+}
+
+impl Default for PgnGame {
+    fn default() -> Self {
+        PgnGame::new(ChessBoard::new())
+    }
+}
+
+impl PgnGame {
+    pub fn new(starting_position: ChessBoard) -> PgnGame {
+        PgnGame {
+            tags: Vec::new(),
+            starting_position,
+            moves: Vec::new(),
+            result: GameResult::Unknown,
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /** Sets the value of a tag, replacing an existing tag with the same name. */
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag_name, _)| tag_name == name) {
+            Some((_, tag_value)) => *tag_value = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /** Returns the moves of the main line together with the position before each move. */
+    pub fn positions(&self) -> Vec<(ChessBoard, Move)> {
+        let mut chess_board = self.starting_position;
+        let mut positions: Vec<(ChessBoard, Move)> = Vec::with_capacity(self.moves.len());
+
+        for pgn_move in &self.moves {
+            positions.push((chess_board, pgn_move.chess_move));
+            chess_board.make_move_on_board(&pgn_move.chess_move);
+            chess_board.update_meta_data(&pgn_move.chess_move);
+        }
+
+        positions
+    }
+
+    /** Returns the position after the last move of the main line. */
+    pub fn final_position(&self) -> ChessBoard {
+        match self.positions().last() {
+            Some((chess_board, chess_move)) => {
+                let mut final_position = *chess_board;
+                final_position.make_move_on_board(chess_move);
+                final_position.update_meta_data(chess_move);
+                final_position
+            }
+            None => self.starting_position,
+        }
+    }
+}
+
+/** Reads the games of a PGN text one at a time. A game with an error is returned as the error,
+and reading continues with the next game. */
+pub struct PgnReader<R: BufRead> {
+    lexer: Lexer<R>,
+}
+
+impl PgnReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<PgnReader<BufReader<File>>> {
+        Ok(PgnReader::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> PgnReader<R> {
+        PgnReader {
+            lexer: Lexer::new(reader),
+        }
+    }
+
+    fn read_game(&mut self) -> Result<Option<PgnGame>, InvalidPgn> {
+        let mut game = PgnGame::default();
+        let mut fen: Option<(String, usize, usize)> = None;
+
+        while let Some(Located {
+            token: Token::TagPair(..),
+            ..
+        }) = self.lexer.peek()?
+        {
+            let Some(Located {
+                token: Token::TagPair(name, value),
+                line,
+                column,
+            }) = self.lexer.next_token()?
+            else {
+                unreachable!("The peeked token is a tag pair");
+            };
+
+            if name == "FEN" {
+                fen = Some((value.clone(), line, column));
+            }
+            game.tags.push((name, value));
+        }
+
+        if game.tags.is_empty() && self.lexer.peek()?.is_none() {
+            return Ok(None);
+        }
+
+        if let Some((fen, line, column)) = fen {
+            game.starting_position = ChessBoard::new_from_fen(&fen)
+                .map_err(|_| self.lexer.error(line, column, format!("invalid FEN {fen}")))?;
+        }
+
+        game.moves = self.read_line(&game.starting_position, 0)?;
+
+        /* The result token ends the game, a game cut off before it keeps the result of its tag. */
+        game.result = match self.lexer.peek()? {
+            Some(Located {
+                token: Token::Result(result),
+                ..
+            }) => {
+                let result = *result;
+                self.lexer.next_token()?;
+                result
+            }
+            _ => game
+                .tag("Result")
+                .and_then(GameResult::from_pgn)
+                .unwrap_or(GameResult::Unknown),
+        };
+
+        Ok(Some(game))
+    }
+
+    /** Reads the moves of a line starting from the position. The main line ends before the
+    result or the tags of the next game, a variation ends after its closing parenthesis. */
+    fn read_line(&mut self, start: &ChessBoard, depth: usize) -> Result<Vec<PgnMove>, InvalidPgn> {
+        let mut moves: Vec<PgnMove> = Vec::new();
+        let mut chess_board = *start;
+        let mut board_before_move = *start;
+        let mut pending_comment: Option<String> = None;
+
+        loop {
+            let Some(located) = self.lexer.peek()? else {
+                if depth > 0 {
+                    let (line, column) = self.lexer.position();
+                    return Err(self.lexer.error(line, column, "unterminated variation"));
+                }
+                return Ok(moves);
+            };
+            let (line, column) = (located.line, located.column);
+
+            match located.token {
+                Token::TagPair(..) | Token::Result(_) if depth == 0 => return Ok(moves),
+                Token::TagPair(..) | Token::Result(_) => {
+                    return Err(self.lexer.error(line, column, "unterminated variation"));
+                }
+                Token::VariationEnd if depth == 0 => {
+                    return Err(self.lexer.error(line, column, "unexpected )"));
+                }
+                _ => {}
+            }
+
+            let Some(Located { token, .. }) = self.lexer.next_token()? else {
+                unreachable!("A token was peeked");
+            };
+
+            match token {
+                Token::VariationEnd => return Ok(moves),
+                Token::San(san) => {
+                    let chess_move = chess_board.move_from_san(&san).map_err(|e| {
+                        let reason = if e.is_ambiguous() {
+                            format!("ambiguous move {san}")
+                        } else {
+                            format!("illegal move {san}")
+                        };
+                        self.lexer.error(line, column, reason)
+                    })?;
+
+                    let mut pgn_move = PgnMove::new(&chess_board, chess_move);
+                    pgn_move.san = san;
+                    pgn_move.comment_before = pending_comment.take();
+                    moves.push(pgn_move);
+
+                    board_before_move = chess_board;
+                    chess_board.make_move_on_board(&chess_move);
+                    chess_board.update_meta_data(&chess_move);
+                }
+                Token::Nag(nag) => match moves.last_mut() {
+                    Some(last_move) => last_move.nags.push(nag),
+                    None => {
+                        return Err(self.lexer.error(
+                            line,
+                            column,
+                            "annotation before the first move",
+                        ))
+                    }
+                },
+                Token::Comment(comment) => match moves.last_mut() {
+                    Some(last_move) => {
+                        last_move.comment = Some(match last_move.comment.take() {
+                            Some(previous) => format!("{previous} {comment}"),
+                            None => comment,
+                        });
+                    }
+                    None => pending_comment = Some(comment),
+                },
+                Token::VariationStart => {
+                    if depth + 1 >= MAX_VARIATION_DEPTH {
+                        return Err(self.lexer.error(
+                            line,
+                            column,
+                            "variations are nested too deeply",
+                        ));
+                    }
+
+                    if moves.is_empty() {
+                        return Err(self.lexer.error(
+                            line,
+                            column,
+                            "variation before the first move",
+                        ));
+                    }
+
+                    let variation = self.read_line(&board_before_move, depth + 1)?;
+                    if let Some(last_move) = moves.last_mut() {
+                        last_move.variations.push(variation);
+                    }
+                }
+                Token::TagPair(..) | Token::Result(_) => unreachable!("Handled before"),
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, InvalidPgn>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_game() {
+            Ok(game) => game.map(Ok),
+            Err(e) => {
+                self.lexer.skip_game();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/** Parses every game in the text, failing on the first game with an error. */
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, InvalidPgn> {
+    PgnReader::new(text.as_bytes()).collect()
+}
+
+/** Writes the games in the PGN export format, separated by empty lines. */
+pub fn write_pgn<W: Write>(writer: &mut W, games: &[PgnGame]) -> io::Result<()> {
+    for game in games {
+        writeln!(writer, "{game}")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_games_with_annotations() {
+        let text = r#"[Event "Test"]
+[White "A"]
+
+1. e4 {best by test} e5 2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3) 2... Nc6 $1 3. Bb5 1-0
+
+[Event "Second"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"]
+
+1.e4 Kd7 *
+"#;
+
+        let games = parse_pgn(text).unwrap();
+
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].tag("White"), Some("A"));
+        let sans: Vec<&str> = games[0].moves.iter().map(|m| m.san.as_str()).collect();
+        assert_eq!(sans, vec!["e4", "e5", "Nf3", "Nc6", "Bb5"]);
+        assert_eq!(games[0].moves[0].comment.as_deref(), Some("best by test"));
+        assert_eq!(games[0].moves[3].nags, vec![1]);
+        assert_eq!(games[0].result, GameResult::WhiteWins);
+
+        let variation = &games[0].moves[2].variations[0];
+        assert_eq!(variation.len(), 3);
+        assert_eq!(variation[1].variations[0][0].san, "d5");
+
+        assert_eq!(games[1].moves.len(), 2);
+        assert_eq!(games[1].result, GameResult::Unknown);
+        assert_eq!(games[1].positions().len(), 2);
+    }
+
+    #[test]
+    fn test_reports_errors_and_continues() {
+        let text = "1. e4 e5 2. Nf6 Nc6 1-0\n\n[Event \"Next\"]\n1. Nc3 Nc6 2. Ne4 Nf6 3. Ng5 Ne4 4. Nf3 0-1\n\n1. d4 d5 *\n";
+        let results: Vec<Result<PgnGame, InvalidPgn>> = PgnReader::new(text.as_bytes()).collect();
+
+        assert_eq!(results.len(), 3);
+
+        let illegal = results[0].as_ref().unwrap_err();
+        assert_eq!((illegal.line(), illegal.column()), (1, 13));
+
+        /* Both knights on g1 and g5 can go to f3. */
+        let ambiguous = results[1].as_ref().unwrap_err();
+        assert_eq!((ambiguous.line(), ambiguous.column()), (4, 37));
+        assert!(ambiguous.to_string().contains("ambiguous move Nf3"));
+
+        assert_eq!(results[2].as_ref().unwrap().moves.len(), 2);
+    }
+}
@@ -0,0 +1,402 @@
+/* This submodule splits PGN text into tokens. The text is read line by line from any reader, so
+ * large files never have to be in memory at once, and every token remembers the line and column
+ * where it starts for error messages. */
+use crate::array_engine::chess_errors::InvalidPgn;
+use crate::pgn::GameResult;
+use std::io::BufRead;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    TagPair(String, String),
+    Comment(String),
+    /* A numeric annotation glyph, either written as "$n" or as a suffix like "!?". */
+    Nag(u8),
+    San(String),
+    VariationStart,
+    VariationEnd,
+    Result(GameResult),
+}
+
+/** A token with the line and column of its first character, both counted from 1. */
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Located {
+    pub token: Token,
+    pub line: usize,
+    pub column: usize,
+}
+
+pub(crate) struct Lexer<R: BufRead> {
+    reader: R,
+    current_line: Vec<char>,
+    line: usize,
+    column: usize,
+    end_of_input: bool,
+    peeked: Option<Result<Option<Located>, InvalidPgn>>,
+    /* The annotation glyph written right after a move, like "!" in "e4!". */
+    pending_annotation: Option<Located>,
+}
+
+impl<R: BufRead> Lexer<R> {
+    pub(crate) fn new(reader: R) -> Lexer<R> {
+        Lexer {
+            reader,
+            current_line: Vec::new(),
+            line: 0,
+            column: 0,
+            end_of_input: false,
+            peeked: None,
+            pending_annotation: None,
+        }
+    }
+
+    /** The line and column of the next character. */
+    pub(crate) fn position(&mut self) -> (usize, usize) {
+        self.peek_char();
+        (self.line, self.column + 1)
+    }
+
+    pub(crate) fn error(
+        &self,
+        line: usize,
+        column: usize,
+        reason: impl Into<String>,
+    ) -> InvalidPgn {
+        InvalidPgn {
+            line,
+            column,
+            reason: reason.into(),
+        }
+    }
+
+    /** Returns the next token without consuming it. */
+    pub(crate) fn peek(&mut self) -> Result<Option<&Located>, InvalidPgn> {
+        if self.peeked.is_none() {
+            let token = self.read_token();
+            self.peeked = Some(token);
+        }
+
+        match self.peeked.as_ref().expect("The token was just peeked") {
+            Ok(token) => Ok(token.as_ref()),
+            Err(e) => Err(e.clone()),
+        }
+    }
+
+    pub(crate) fn next_token(&mut self) -> Result<Option<Located>, InvalidPgn> {
+        match self.peeked.take() {
+            Some(token) => token,
+            None => self.read_token(),
+        }
+    }
+
+    /** Skips the rest of a broken game, up to its result or the tags of the next game. */
+    pub(crate) fn skip_game(&mut self) {
+        self.peeked = None;
+        self.pending_annotation = None;
+
+        loop {
+            while self.peek_char().is_some_and(|c| c.is_whitespace()) {
+                self.column += 1;
+            }
+
+            let at_line_start = self.current_line[..self.column]
+                .iter()
+                .all(|c| c.is_whitespace());
+
+            match self.peek_char() {
+                None => return,
+                Some('[') if at_line_start => return,
+                Some(_) => {}
+            }
+
+            match self.read_token() {
+                Ok(Some(Located {
+                    token: Token::Result(_),
+                    ..
+                })) => return,
+                Ok(None) => return,
+                _ => {}
+            }
+        }
+    }
+
+    /** Returns the next character, reading the next line when the current one is used up. */
+    fn peek_char(&mut self) -> Option<char> {
+        while self.column >= self.current_line.len() {
+            if self.end_of_input {
+                return None;
+            }
+
+            let mut text = String::new();
+            match self.reader.read_line(&mut text) {
+                Ok(0) | Err(_) => {
+                    self.end_of_input = true;
+                    self.current_line.clear();
+                    self.column = 0;
+                    return None;
+                }
+                Ok(_) => {
+                    self.current_line = text.chars().collect();
+                    self.line += 1;
+                    self.column = 0;
+
+                    /* A line starting with '%' is an escape for other programs. */
+                    if self.current_line.first() == Some(&'%') {
+                        self.column = self.current_line.len();
+                    }
+                }
+            }
+        }
+
+        Some(self.current_line[self.column])
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.peek_char()?;
+        self.column += 1;
+        Some(c)
+    }
+
+    fn read_token(&mut self) -> Result<Option<Located>, InvalidPgn> {
+        if let Some(annotation) = self.pending_annotation.take() {
+            return Ok(Some(annotation));
+        }
+
+        while self.peek_char().is_some_and(|c| c.is_whitespace()) {
+            self.column += 1;
+        }
+
+        let (line, column) = (self.line, self.column + 1);
+        let Some(c) = self.next_char() else {
+            return Ok(None);
+        };
+
+        let token = match c {
+            '[' => self.read_tag_pair(line, column)?,
+            '{' => {
+                let mut comment = String::new();
+
+                loop {
+                    match self.next_char() {
+                        Some('}') => break,
+                        Some(c) => comment.push(c),
+                        None => return Err(self.error(line, column, "unterminated comment")),
+                    }
+                }
+
+                Token::Comment(comment.split_whitespace().collect::<Vec<&str>>().join(" "))
+            }
+            ';' => {
+                let rest: String = self.current_line[self.column..].iter().collect();
+                self.column = self.current_line.len();
+                Token::Comment(rest.trim().to_string())
+            }
+            '(' => Token::VariationStart,
+            ')' => Token::VariationEnd,
+            '$' => {
+                let digits = self.read_while(|c| c.is_ascii_digit());
+                let nag = digits.parse::<u8>().map_err(|_| {
+                    self.error(line, column, format!("invalid annotation glyph ${digits}"))
+                })?;
+                Token::Nag(nag)
+            }
+            c => {
+                let mut symbol = c.to_string();
+                symbol
+                    .push_str(&self.read_while(|c| !c.is_whitespace() && !"[]{}();$".contains(c)));
+                return self.symbol_token(symbol, line, column);
+            }
+        };
+
+        Ok(Some(Located {
+            token,
+            line,
+            column,
+        }))
+    }
+
+    /** Splits a symbol like "12.", "e4!?", "1-0" or "O-O+" into the token it stands for. A move
+    number in front of a move, like "12.e4", is skipped. */
+    fn symbol_token(
+        &mut self,
+        symbol: String,
+        line: usize,
+        column: usize,
+    ) -> Result<Option<Located>, InvalidPgn> {
+        let located = |token: Token, column: usize| {
+            Ok(Some(Located {
+                token,
+                line,
+                column,
+            }))
+        };
+
+        if let Some(result) = GameResult::from_pgn(&symbol) {
+            return located(Token::Result(result), column);
+        }
+
+        if let Some(nag) = suffix_annotation(&symbol) {
+            return located(Token::Nag(nag), column);
+        }
+
+        let is_castling = symbol.starts_with("0-0") || symbol.starts_with("O-O");
+        let move_text = if is_castling {
+            symbol.as_str()
+        } else {
+            symbol
+                .trim_start_matches(|c: char| c.is_ascii_digit())
+                .trim_start_matches('.')
+        };
+
+        if move_text.is_empty() {
+            /* A move number without a move, the next token follows. */
+            return self.read_token();
+        }
+
+        let move_column = column + symbol.chars().count() - move_text.chars().count();
+        let san_length = move_text.trim_end_matches(['!', '?']).len();
+        let (san, suffix) = move_text.split_at(san_length);
+
+        if !san.starts_with(|c: char| c.is_ascii_alphabetic() || c == '0') {
+            return Err(self.error(line, move_column, format!("unexpected symbol {symbol}")));
+        }
+
+        if !suffix.is_empty() {
+            let nag = suffix_annotation(suffix).ok_or_else(|| {
+                self.error(
+                    line,
+                    move_column + san_length,
+                    format!("invalid annotation {suffix}"),
+                )
+            })?;
+            self.pending_annotation = Some(Located {
+                token: Token::Nag(nag),
+                line,
+                column: move_column + san_length,
+            });
+        }
+
+        located(Token::San(san.to_string()), move_column)
+    }
+
+    fn read_tag_pair(&mut self, line: usize, column: usize) -> Result<Token, InvalidPgn> {
+        self.skip_spaces();
+        let name = self.read_while(|c| c.is_ascii_alphanumeric() || c == '_');
+
+        if name.is_empty() {
+            return Err(self.error(line, column, "tag pair without a name"));
+        }
+
+        self.skip_spaces();
+        if self.next_char() != Some('"') {
+            return Err(self.error(
+                line,
+                column,
+                format!("the value of tag {name} is not quoted"),
+            ));
+        }
+
+        let mut value = String::new();
+
+        loop {
+            match self.next_char() {
+                Some('\\') => match self.next_char() {
+                    Some(c) if c != '\n' => value.push(c),
+                    _ => break,
+                },
+                Some('"') => break,
+                Some('\n') | None => {
+                    return Err(self.error(
+                        line,
+                        column,
+                        format!("unterminated value of tag {name}"),
+                    ))
+                }
+                Some(c) => value.push(c),
+            }
+        }
+
+        self.skip_spaces();
+        if self.next_char() != Some(']') {
+            return Err(self.error(line, column, format!("tag {name} is not closed with ]")));
+        }
+
+        Ok(Token::TagPair(name, value))
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek_char().is_some_and(|c| c == ' ' || c == '\t') {
+            self.column += 1;
+        }
+    }
+
+    fn read_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let mut text = String::new();
+
+        while let Some(c) = self.peek_char().filter(|c| predicate(*c)) {
+            text.push(c);
+            self.column += 1;
+
+            /* Tokens do not continue on the next line. */
+            if self.column >= self.current_line.len() {
+                break;
+            }
+        }
+
+        text
+    }
+}
+
+/** Converts the traditional annotations to their glyphs, e.g. "!?" is $5. */
+fn suffix_annotation(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(text: &str) -> Vec<Located> {
+        let mut lexer = Lexer::new(text.as_bytes());
+        let mut tokens = Vec::new();
+
+        while let Some(token) = lexer.next_token().unwrap() {
+            tokens.push(token);
+        }
+
+        tokens
+    }
+
+    #[test]
+    fn test_tokens_and_positions() {
+        let tokens = tokens(
+            "[Event \"A \\\"B\\\"\"]\n% escaped\n12.e4!? {a\n  comment} $14 (12...c5) 0-0 1/2-1/2",
+        );
+        let kinds: Vec<Token> = tokens.iter().map(|located| located.token.clone()).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                Token::TagPair("Event".to_string(), "A \"B\"".to_string()),
+                Token::San("e4".to_string()),
+                Token::Nag(5),
+                Token::Comment("a comment".to_string()),
+                Token::Nag(14),
+                Token::VariationStart,
+                Token::San("c5".to_string()),
+                Token::VariationEnd,
+                Token::San("0-0".to_string()),
+                Token::Result(GameResult::Draw),
+            ]
+        );
+        assert_eq!((tokens[1].line, tokens[1].column), (3, 4));
+        assert_eq!((tokens[4].line, tokens[4].column), (4, 12));
+    }
+}
@@ -0,0 +1,246 @@
+/* This submodule writes games in the PGN export format. The Seven Tag Roster comes first, then the
+ * FEN of a game not starting from the start position and the other tags. The moves are written
+ * in SAN generated from the position, so the output is standard even when the game was read
+ * from a sloppy file, and the movetext is wrapped at 80 columns. */
+use crate::array_engine::{ChessBoard, Color};
+use crate::fen::FEN_START_POSITION;
+use crate::pgn::{PgnGame, PgnMove};
+use std::fmt;
+
+const MAX_LINE_LENGTH: usize = 80;
+
+/* The Seven Tag Roster with the values used for unknown tags, the result is the last tag. */
+const SEVEN_TAG_ROSTER: [(&str, &str); 6] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+];
+
+impl fmt::Display for PgnGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, unknown) in SEVEN_TAG_ROSTER {
+            write_tag(f, name, self.tag(name).unwrap_or(unknown))?;
+        }
+        write_tag(f, "Result", self.result.to_pgn())?;
+
+        let fen = self.starting_position.to_fen();
+        if fen != FEN_START_POSITION {
+            write_tag(f, "SetUp", "1")?;
+            write_tag(f, "FEN", &fen)?;
+        }
+
+        for (name, value) in &self.tags {
+            let is_written = SEVEN_TAG_ROSTER.iter().any(|(tag, _)| tag == name)
+                || ["Result", "SetUp", "FEN"].contains(&name.as_str());
+
+            if !is_written {
+                write_tag(f, name, value)?;
+            }
+        }
+
+        let mut movetext = Movetext::default();
+        movetext.write_line(&self.moves, &self.starting_position);
+        movetext.push(self.result.to_pgn().to_string());
+
+        writeln!(f)?;
+        for line in movetext.lines() {
+            writeln!(f, "{line}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_tag(f: &mut fmt::Formatter, name: &str, value: &str) -> fmt::Result {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    writeln!(f, "[{name} \"{value}\"]")
+}
+
+/** The tokens of the movetext, which are wrapped into lines at the end. */
+#[derive(Default)]
+struct Movetext {
+    tokens: Vec<String>,
+    /* The next token starts a variation and gets the opening parenthesis. */
+    opens_variation: bool,
+}
+
+impl Movetext {
+    fn push(&mut self, token: String) {
+        if self.opens_variation {
+            self.opens_variation = false;
+            self.tokens.push(format!("({token}"));
+        } else {
+            self.tokens.push(token);
+        }
+    }
+
+    /** Pushes the comment word by word, so long comments are wrapped like the moves. A '}' would
+    end the comment early and is left out. A line starting with '%' is skipped by readers, so a
+    word starting with '%' is kept together with the word before it. */
+    fn push_comment(&mut self, comment: &str) {
+        let comment = comment.replace('}', "");
+        let mut words: Vec<String> = Vec::new();
+
+        for word in comment.split_whitespace() {
+            match words.last_mut() {
+                Some(previous) if word.starts_with('%') => {
+                    previous.push(' ');
+                    previous.push_str(word);
+                }
+                _ => words.push(word.to_string()),
+            }
+        }
+
+        match words.as_slice() {
+            [] => self.push("{}".to_string()),
+            [word] => self.push(format!("{{{word}}}")),
+            [first, middle @ .., last] => {
+                self.push(format!("{{{first}"));
+                for word in middle {
+                    self.push(word.clone());
+                }
+                self.push(format!("{last}}}"));
+            }
+        }
+    }
+
+    /** Writes the moves of a line with their annotations and variations. Black moves get a move
+    number after anything interrupting the moves, like "5... Nf6". */
+    fn write_line(&mut self, moves: &[PgnMove], start: &ChessBoard) {
+        let mut chess_board = *start;
+        let mut needs_move_number = true;
+
+        for pgn_move in moves {
+            if let Some(comment) = &pgn_move.comment_before {
+                self.push_comment(comment);
+            }
+
+            let move_number = chess_board.full_move_counter();
+            match chess_board.side_to_move() {
+                Color::White => self.push(format!("{move_number}.")),
+                Color::Black if needs_move_number => self.push(format!("{move_number}...")),
+                Color::Black => {}
+            }
+
+            self.push(chess_board.move_to_san(&pgn_move.chess_move));
+            needs_move_number = false;
+
+            for nag in &pgn_move.nags {
+                self.push(format!("${nag}"));
+            }
+
+            if let Some(comment) = &pgn_move.comment {
+                self.push_comment(comment);
+                needs_move_number = true;
+            }
+
+            for variation in pgn_move.variations.iter().filter(|line| !line.is_empty()) {
+                self.opens_variation = true;
+                self.write_line(variation, &chess_board);
+                if let Some(last_token) = self.tokens.last_mut() {
+                    last_token.push(')');
+                }
+                needs_move_number = true;
+            }
+
+            chess_board.make_move_on_board(&pgn_move.chess_move);
+            chess_board.update_meta_data(&pgn_move.chess_move);
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        let mut line = String::new();
+
+        for token in &self.tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > MAX_LINE_LENGTH {
+                lines.push(std::mem::take(&mut line));
+            }
+
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(token);
+        }
+
+        if !line.is_empty() {
+            lines.push(line);
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MAX_LINE_LENGTH;
+    use crate::pgn::{parse_pgn, Evaluation, GameResult};
+    use std::time::Duration;
+
+    #[test]
+    fn test_written_games_read_back() {
+        let text = r#"[White "Player \"One\""]
+[Event "Test"]
+[Annotator "Me"]
+
+{Opening comment} 1. e4 {[%clk 0:05:00] A very long comment that has to be wrapped over more than one line of the movetext}
+e5 $1 2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3 g5 4. h4) (2. Nc3) 2... Nc6 3. Bb5 a6 4. Ba4 Nf6
+5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7 1-0
+"#;
+        let mut game = parse_pgn(text).unwrap().remove(0);
+        game.moves[1].set_clock(Duration::from_millis(299_500));
+        game.moves[1].set_evaluation(Evaluation::Centipawns(-35));
+        game.moves[2].set_evaluation(Evaluation::Mate(-3));
+
+        let written = game.to_string();
+        assert!(written.starts_with(
+            "[Event \"Test\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n[White \"Player \\\"One\\\"\"]"
+        ));
+        assert!(written.contains("[Result \"1-0\"]\n[Annotator \"Me\"]\n\n{Opening comment} 1. e4"));
+        assert!(written.contains("(2. f4 exf4 (2... d5) 3. Nf3 g5 4. h4) (2. Nc3)\n2... Nc6"));
+        assert!(written.lines().all(|line| line.len() <= 80));
+
+        let read_back = parse_pgn(&written).unwrap().remove(0);
+        assert_eq!(read_back.tags.len(), 8);
+        assert_eq!(read_back.tag("White"), Some("Player \"One\""));
+        assert_eq!(read_back.moves, game.moves);
+        assert_eq!(read_back.result, GameResult::WhiteWins);
+        assert_eq!(read_back.moves[0].clock(), Some(Duration::from_secs(300)));
+        assert_eq!(
+            read_back.moves[1].clock(),
+            Some(Duration::from_millis(299_500))
+        );
+        assert_eq!(
+            read_back.moves[1].evaluation(),
+            Some(Evaluation::Centipawns(-35))
+        );
+        assert_eq!(read_back.moves[2].evaluation(), Some(Evaluation::Mate(-3)));
+    }
+
+    #[test]
+    fn test_writes_fen_of_set_up_positions() {
+        let text = "[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 7\"]\n\n7... Kd7 8. e4 *\n";
+        let game = parse_pgn(text).unwrap().remove(0);
+        let written = game.to_string();
+
+        assert!(written.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 7\"]"));
+        assert!(written.ends_with("7... Kd7 8. e4 *\n"));
+        assert_eq!(parse_pgn(&written).unwrap()[0].moves, game.moves);
+    }
+
+    #[test]
+    fn test_wrapped_comments_do_not_start_lines_with_percent() {
+        /* Every length of the first word wraps the comment at a different word. */
+        for length in 1..MAX_LINE_LENGTH {
+            let text = format!("1. e4 {{{} up 5 %}} e5 *\n", "a".repeat(length));
+            let game = parse_pgn(&text).unwrap().remove(0);
+            let written = game.to_string();
+
+            assert!(written.lines().all(|line| !line.starts_with('%')));
+            assert_eq!(parse_pgn(&written).unwrap()[0].moves, game.moves);
+        }
+    }
+}
@@ -0,0 +1,1089 @@
+/* This module implements the search of the engine: an iterative deepening principal variation
+ * search with a transposition table, a quiescence search and move ordering heuristics. The search
+ * can run on several threads using Lazy SMP: all threads search the same position and only
+ * communicate through the shared transposition table. */
+pub mod evaluation;
+pub mod mate_search;
+pub mod move_ordering;
+pub mod test_suite;
+pub mod time_management;
+pub mod transposition_table;
+
+use crate::array_engine::{ChessBoard, Move};
+use crate::syzygy::{Tablebases, Wdl, MAX_DTZ};
+use evaluation::{evaluate, PAWN_VALUE};
+use mate_search::{find_mate, mate_length, MateSearchResult};
+use move_ordering::{MoveOrderingHeuristics, MovePicker};
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use time_management::{PonderSignal, TimeAllocation, TimeControl};
+use transposition_table::{
+    Bound, TableEntry, TableMove, TranspositionTable, DEFAULT_TABLE_SIZE_MB,
+};
+
+pub const MAX_PLY: usize = 128;
+pub const INFINITY: i32 = 32000;
+pub const MATE_SCORE: i32 = 31000;
+/* Scores above this bound are mate scores. */
+pub const MATE_BOUND: i32 = MATE_SCORE - MAX_PLY as i32;
+pub const MAX_THREADS: usize = 256;
+pub const MAX_MULTI_PV: usize = 256;
+/* Scores of positions the tablebases prove to be won, they are below the mate scores. */
+pub const TABLEBASE_WIN_SCORE: i32 = MATE_BOUND - MAX_PLY as i32 - 1;
+
+const DRAW_SCORE: i32 = 0;
+/* How many nodes are searched between checks of the time and node limits. */
+const LIMIT_CHECK_INTERVAL: u64 = 1024;
+const PONDER_WAIT_INTERVAL: Duration = Duration::from_millis(1);
+
+/** Limits a search, a search without any limits runs until the maximum depth is reached. An
+infinite search ignores the clock and keeps searching after a mate is found. A ponder search
+ignores the limits until the ponder hit, and does not return before the ponder hit or a stop.
+A mate search first looks for a forced mate in the given number of moves, and falls back to a
+normal search of the same depth when there is none. */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub move_time: Option<Duration>,
+    pub nodes: Option<u64>,
+    pub time_control: Option<TimeControl>,
+    pub infinite: bool,
+    pub ponder: bool,
+    pub mate: Option<u32>,
+}
+
+/** Reported for every line after each completed iteration of the iterative deepening. The
+lines are numbered from 1, where line 1 is the best line. The node count is the sum over all
+search threads. */
+#[derive(Debug, Clone)]
+pub struct SearchInfo {
+    pub depth: u32,
+    pub multi_pv: usize,
+    pub score: i32,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    pub principal_variation: Vec<Move>,
+}
+
+/** One of the best lines found by a MultiPV search. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalysisLine {
+    pub first_move: Move,
+    pub score: i32,
+    pub principal_variation: Vec<Move>,
+}
+
+/** The result of a search, the lines are ranked from best to worst and contain as many lines as
+requested by the MultiPV setting, or fewer if the position has fewer legal moves. */
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64,
+    pub principal_variation: Vec<Move>,
+    pub lines: Vec<AnalysisLine>,
+}
+
+pub struct Searcher {
+    transposition_table: TranspositionTable,
+    /* One set of heuristics per search thread, the first belongs to the main thread. */
+    thread_heuristics: Vec<MoveOrderingHeuristics>,
+    stop_signal: Arc<AtomicBool>,
+    ponder_signal: Arc<PonderSignal>,
+    /* Hashes of the positions played in the game before the searched position. */
+    position_history: Vec<u64>,
+    multi_pv: usize,
+    tablebases: Option<Arc<Tablebases>>,
+}
+
+/** The state shared by all threads of a single search. */
+struct SharedSearchState<'a> {
+    transposition_table: &'a TranspositionTable,
+    stop_signal: &'a AtomicBool,
+    thread_nodes: &'a [AtomicU64],
+    limits: SearchLimits,
+    time_allocation: Option<TimeAllocation>,
+    start_time: Instant,
+    multi_pv: usize,
+    ponder_signal: &'a PonderSignal,
+    tablebases: Option<&'a Tablebases>,
+}
+
+/** The state of one search thread during a search. */
+struct SearchWorker<'a> {
+    id: usize,
+    shared: &'a SharedSearchState<'a>,
+    heuristics: &'a mut MoveOrderingHeuristics,
+    /* The game history followed by the positions on the current search path. */
+    position_history: Vec<u64>,
+    /* Root moves that are skipped, since they already belong to a better MultiPV line. */
+    excluded_root_moves: Vec<Move>,
+    nodes: u64,
+    stopped: bool,
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Self::new(DEFAULT_TABLE_SIZE_MB)
+    }
+}
+
+impl Searcher {
+    pub fn new(table_size_mb: usize) -> Searcher {
+        Searcher {
+            transposition_table: TranspositionTable::new(table_size_mb),
+            thread_heuristics: vec![MoveOrderingHeuristics::new()],
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            ponder_signal: Arc::new(PonderSignal::default()),
+            position_history: Vec::new(),
+            multi_pv: 1,
+            tablebases: None,
+        }
+    }
+
+    /** Forgets everything learned from previous searches, used when a new game starts. */
+    pub fn clear(&mut self) {
+        self.transposition_table.clear();
+
+        for heuristics in self.thread_heuristics.iter_mut() {
+            heuristics.clear();
+        }
+    }
+
+    pub fn set_table_size(&mut self, table_size_mb: usize) {
+        self.transposition_table = TranspositionTable::new(table_size_mb);
+    }
+
+    /** Sets the number of search threads. With a single thread the search is deterministic. */
+    pub fn set_threads(&mut self, threads: usize) {
+        self.thread_heuristics
+            .resize_with(threads.clamp(1, MAX_THREADS), MoveOrderingHeuristics::new);
+    }
+
+    pub fn threads(&self) -> usize {
+        self.thread_heuristics.len()
+    }
+
+    /** Sets the number of best lines the search reports, each line starts with a different
+    move. Searching more lines makes the search for the best move slower. */
+    pub fn set_multi_pv(&mut self, multi_pv: usize) {
+        self.multi_pv = multi_pv.clamp(1, MAX_MULTI_PV);
+    }
+
+    pub fn multi_pv(&self) -> usize {
+        self.multi_pv
+    }
+
+    /** Returns a signal that stops the running search when set, e.g. from the thread that reads
+    the UCI commands. The signal is cleared when the search returns. */
+    pub fn stop_signal(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop_signal)
+    }
+
+    /** Returns the signal that turns a running ponder search into a timed search. The signal is
+    reset when the search returns. */
+    pub fn ponder_signal(&self) -> Arc<PonderSignal> {
+        Arc::clone(&self.ponder_signal)
+    }
+
+    /** Sets the hashes of the positions that occurred in the game before the searched position,
+    which allows the search to detect draws by repetition. */
+    pub fn set_position_history(&mut self, position_history: Vec<u64>) {
+        self.position_history = position_history;
+    }
+
+    /** Sets the endgame tablebases probed by the search, None turns probing off. */
+    pub fn set_tablebases(&mut self, tablebases: Option<Arc<Tablebases>>) {
+        self.tablebases = tablebases;
+    }
+
+    pub fn tablebases(&self) -> Option<&Tablebases> {
+        self.tablebases.as_deref()
+    }
+
+    pub fn search<F: FnMut(&SearchInfo)>(
+        &mut self,
+        chess_board: &ChessBoard,
+        mut limits: SearchLimits,
+        mut report_info: F,
+    ) -> SearchResult {
+        if let Some(moves) = limits.mate {
+            let start_time = Instant::now();
+
+            if let MateSearchResult::Mate(mating_line) =
+                find_mate(chess_board, moves, &self.stop_signal)
+            {
+                let score = MATE_SCORE - mating_line.len() as i32;
+                let info = SearchInfo {
+                    depth: 2 * mate_length(&mating_line) - 1,
+                    multi_pv: 1,
+                    score,
+                    nodes: 0,
+                    elapsed: start_time.elapsed(),
+                    principal_variation: mating_line.clone(),
+                };
+                report_info(&info);
+                self.stop_signal.store(false, Ordering::Relaxed);
+
+                return SearchResult {
+                    best_move: mating_line.first().copied(),
+                    score,
+                    depth: info.depth,
+                    nodes: 0,
+                    principal_variation: mating_line.clone(),
+                    lines: vec![AnalysisLine {
+                        first_move: mating_line[0],
+                        score,
+                        principal_variation: mating_line,
+                    }],
+                };
+            }
+
+            limits.depth = Some(limits.depth.unwrap_or(2 * moves).min(2 * moves));
+        }
+
+        /* Analysis keeps searching the position, a game plays the best move of the tablebases. */
+        if !limits.infinite && !limits.ponder {
+            if let Some(result) = self.tablebase_result(chess_board, &mut report_info) {
+                self.stop_signal.store(false, Ordering::Relaxed);
+                return result;
+            }
+        }
+
+        let time_allocation = match (limits.infinite, limits.time_control) {
+            (false, Some(time_control)) => time_control.allocate(chess_board.side_to_move()),
+            _ => None,
+        };
+
+        let thread_nodes: Vec<AtomicU64> = (0..self.thread_heuristics.len())
+            .map(|_| AtomicU64::new(0))
+            .collect();
+
+        let shared = SharedSearchState {
+            transposition_table: &self.transposition_table,
+            stop_signal: &self.stop_signal,
+            thread_nodes: &thread_nodes,
+            limits,
+            time_allocation,
+            start_time: Instant::now(),
+            multi_pv: self.multi_pv,
+            ponder_signal: &self.ponder_signal,
+            tablebases: self.tablebases.as_deref(),
+        };
+
+        let position_history = &self.position_history;
+        let (main_heuristics, helper_heuristics) = self
+            .thread_heuristics
+            .split_first_mut()
+            .expect("There is always a main search thread");
+
+        let mut result = thread::scope(|scope| {
+            for (i, heuristics) in helper_heuristics.iter_mut().enumerate() {
+                let mut helper = SearchWorker::new(i + 1, &shared, heuristics, position_history);
+
+                scope.spawn(move || {
+                    helper.iterative_deepening(chess_board, |_| {});
+                });
+            }
+
+            let mut main_worker = SearchWorker::new(0, &shared, main_heuristics, position_history);
+            let result = main_worker.iterative_deepening(chess_board, report_info);
+
+            /* The best move of a ponder search may only be returned after the ponder hit. */
+            while shared.is_pondering() && !shared.stop_signal.load(Ordering::Relaxed) {
+                thread::sleep(PONDER_WAIT_INTERVAL);
+            }
+
+            /* The helper threads search until the main thread is done. */
+            shared.stop_signal.store(true, Ordering::Relaxed);
+
+            result
+        });
+
+        result.nodes = shared.total_nodes();
+        self.stop_signal.store(false, Ordering::Relaxed);
+        self.ponder_signal.reset();
+
+        result
+    }
+
+    /** Returns the root move with the best tablebase rank, which wins the fastest without a draw
+    by the fifty move rule, or None if the root position is not in the tablebases. */
+    fn tablebase_result<F: FnMut(&SearchInfo)>(
+        &self,
+        chess_board: &ChessBoard,
+        report_info: &mut F,
+    ) -> Option<SearchResult> {
+        let start_time = Instant::now();
+        let ranked_moves = self
+            .tablebases
+            .as_ref()?
+            .probe_root(chess_board, &self.position_history)?;
+        let best_move = ranked_moves.first()?;
+        let score = tablebase_rank_to_score(best_move.rank);
+
+        report_info(&SearchInfo {
+            depth: 1,
+            multi_pv: 1,
+            score,
+            nodes: 0,
+            elapsed: start_time.elapsed(),
+            principal_variation: vec![best_move.chess_move],
+        });
+
+        Some(SearchResult {
+            best_move: Some(best_move.chess_move),
+            score,
+            depth: 1,
+            nodes: 0,
+            principal_variation: vec![best_move.chess_move],
+            lines: vec![AnalysisLine {
+                first_move: best_move.chess_move,
+                score,
+                principal_variation: vec![best_move.chess_move],
+            }],
+        })
+    }
+}
+
+impl SharedSearchState<'_> {
+    fn total_nodes(&self) -> u64 {
+        self.thread_nodes
+            .iter()
+            .map(|nodes| nodes.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /** Returns when the clock of the engine started running, or None while pondering. */
+    fn clock_start_time(&self) -> Option<Instant> {
+        if self.limits.ponder {
+            self.ponder_signal.ponderhit_time()
+        } else {
+            Some(self.start_time)
+        }
+    }
+
+    fn is_pondering(&self) -> bool {
+        self.clock_start_time().is_none()
+    }
+
+    fn hard_time_limit(&self) -> Option<Duration> {
+        let allocated_time = self.time_allocation.map(|allocation| allocation.hard_limit);
+
+        match (self.limits.move_time, allocated_time) {
+            (Some(move_time), Some(allocated_time)) => Some(move_time.min(allocated_time)),
+            (move_time, allocated_time) => move_time.or(allocated_time),
+        }
+    }
+}
+
+impl<'a> SearchWorker<'a> {
+    fn new(
+        id: usize,
+        shared: &'a SharedSearchState<'a>,
+        heuristics: &'a mut MoveOrderingHeuristics,
+        position_history: &[u64],
+    ) -> SearchWorker<'a> {
+        heuristics.prepare_new_search();
+
+        SearchWorker {
+            id,
+            shared,
+            heuristics,
+            position_history: position_history.to_vec(),
+            excluded_root_moves: Vec::new(),
+            nodes: 0,
+            stopped: false,
+        }
+    }
+
+    /** Searches the position with increasing depth until a limit is reached. Every other helper
+    thread starts one ply deeper, so the threads are less likely to search the same nodes at the
+    same time. */
+    fn iterative_deepening<F: FnMut(&SearchInfo)>(
+        &mut self,
+        chess_board: &ChessBoard,
+        mut report_info: F,
+    ) -> SearchResult {
+        let limits = self.shared.limits;
+        let max_depth: u32 = limits
+            .depth
+            .unwrap_or(MAX_PLY as u32 - 1)
+            .min(MAX_PLY as u32 - 1);
+        let first_depth: u32 = (1 + self.id as u32 % 2).min(max_depth);
+        let legal_moves = chess_board.legal_moves();
+        let multi_pv = self.shared.multi_pv.min(legal_moves.len()).max(1);
+
+        let mut result = SearchResult {
+            best_move: legal_moves.first().copied(),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            principal_variation: Vec::new(),
+            lines: Vec::new(),
+        };
+
+        for depth in first_depth..=max_depth {
+            let mut lines: Vec<AnalysisLine> = Vec::with_capacity(multi_pv);
+            let mut score = DRAW_SCORE;
+            self.excluded_root_moves.clear();
+
+            for _ in 0..multi_pv {
+                let principal_variation = self.search_root(chess_board, depth, &mut score);
+
+                if self.stopped {
+                    break;
+                }
+
+                /* The root has no legal moves, the score is a mate or a stalemate. */
+                let Some(first_move) = principal_variation.first().copied() else {
+                    break;
+                };
+
+                self.excluded_root_moves.push(first_move);
+                lines.push(AnalysisLine {
+                    first_move,
+                    score,
+                    principal_variation,
+                });
+            }
+
+            if self.stopped {
+                break;
+            }
+
+            lines.sort_by_key(|line| Reverse(line.score));
+
+            if let Some(best_line) = lines.first() {
+                result.best_move = Some(best_line.first_move);
+                result.score = best_line.score;
+                result.principal_variation = best_line.principal_variation.clone();
+            } else {
+                result.score = score;
+            }
+            result.depth = depth;
+            result.lines = lines;
+
+            for (i, line) in result.lines.iter().enumerate() {
+                report_info(&SearchInfo {
+                    depth,
+                    multi_pv: i + 1,
+                    score: line.score,
+                    nodes: self.shared.total_nodes(),
+                    elapsed: self.shared.start_time.elapsed(),
+                    principal_variation: line.principal_variation.clone(),
+                });
+            }
+
+            if limits.infinite || self.shared.is_pondering() {
+                continue;
+            }
+
+            if result.score.abs() >= MATE_BOUND && limits.depth.is_none() {
+                break;
+            }
+
+            /* The time spent pondering counts towards the soft limit, the search has already
+            looked at the position for that long. */
+            if let Some(time_allocation) = self.shared.time_allocation {
+                if self.shared.start_time.elapsed() >= time_allocation.soft_limit {
+                    break;
+                }
+            }
+        }
+
+        result
+    }
+
+    /** Searches the root with a full window, skipping the excluded root moves. */
+    fn search_root(&mut self, chess_board: &ChessBoard, depth: u32, score: &mut i32) -> Vec<Move> {
+        let game_history_length = self.position_history.len();
+        let mut principal_variation: Vec<Move> = Vec::new();
+
+        *score = self.negamax(
+            chess_board,
+            depth,
+            -INFINITY,
+            INFINITY,
+            0,
+            None,
+            &mut principal_variation,
+        );
+        self.position_history.truncate(game_history_length);
+        self.publish_nodes();
+
+        principal_variation
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn negamax(
+        &mut self,
+        chess_board: &ChessBoard,
+        mut depth: u32,
+        mut alpha: i32,
+        beta: i32,
+        ply: usize,
+        previous_move: Option<Move>,
+        principal_variation: &mut Vec<Move>,
+    ) -> i32 {
+        principal_variation.clear();
+
+        if self.should_stop() {
+            return DRAW_SCORE;
+        }
+
+        let hash = chess_board.zobrist_hash();
+
+        if ply > 0 && self.is_draw(chess_board, hash) {
+            return DRAW_SCORE;
+        }
+
+        let in_check = chess_board.king_is_in_check();
+
+        if in_check {
+            depth += 1;
+        }
+
+        if depth == 0 || ply >= MAX_PLY - 1 {
+            return self.quiescence(chess_board, alpha, beta, ply);
+        }
+
+        self.nodes += 1;
+
+        let is_pv_node = beta - alpha > 1;
+        let table_entry = self.shared.transposition_table.probe(hash);
+
+        if let Some(entry) = table_entry {
+            let score = score_from_table(entry.score, ply);
+
+            if !is_pv_node
+                && entry.depth >= depth
+                && bound_allows_cutoff(&entry, score, alpha, beta)
+            {
+                return score;
+            }
+        }
+
+        if let Some(score) = self.probe_tablebases(chess_board, hash, depth, alpha, beta, ply) {
+            return score;
+        }
+
+        let side_to_move = chess_board.side_to_move();
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move: Option<Move> = None;
+        let mut searched_quiet_moves: Vec<Move> = Vec::new();
+        let mut child_variation: Vec<Move> = Vec::new();
+        let mut move_count: u32 = 0;
+
+        let hash_move = table_entry
+            .and_then(|entry| entry.best_move)
+            .and_then(|table_move| table_move.to_move(chess_board));
+
+        let mut picker = MovePicker::new(
+            hash_move,
+            self.heuristics.killer_moves(ply),
+            self.heuristics.counter_move(side_to_move, previous_move),
+        );
+
+        self.position_history.push(hash);
+
+        while let Some(chess_move) = picker.next_move(chess_board, self.heuristics) {
+            if ply == 0 && self.excluded_root_moves.contains(&chess_move) {
+                continue;
+            }
+
+            let mut child_board = *chess_board;
+            child_board.make_move_on_board(&chess_move);
+            child_board.update_meta_data(&chess_move);
+            move_count += 1;
+
+            let score = if move_count == 1 {
+                -self.negamax(
+                    &child_board,
+                    depth - 1,
+                    -beta,
+                    -alpha,
+                    ply + 1,
+                    Some(chess_move),
+                    &mut child_variation,
+                )
+            } else {
+                let null_window_score = -self.negamax(
+                    &child_board,
+                    depth - 1,
+                    -alpha - 1,
+                    -alpha,
+                    ply + 1,
+                    Some(chess_move),
+                    &mut child_variation,
+                );
+
+                if null_window_score > alpha && null_window_score < beta {
+                    -self.negamax(
+                        &child_board,
+                        depth - 1,
+                        -beta,
+                        -alpha,
+                        ply + 1,
+                        Some(chess_move),
+                        &mut child_variation,
+                    )
+                } else {
+                    null_window_score
+                }
+            };
+
+            if self.stopped {
+                self.position_history.pop();
+                return DRAW_SCORE;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(chess_move);
+
+                if score > alpha {
+                    alpha = score;
+
+                    principal_variation.clear();
+                    principal_variation.push(chess_move);
+                    principal_variation.extend_from_slice(&child_variation);
+                }
+            }
+
+            if alpha >= beta {
+                if !chess_move.is_noisy() {
+                    self.update_quiet_move_heuristics(
+                        chess_board,
+                        chess_move,
+                        &searched_quiet_moves,
+                        depth,
+                        ply,
+                        previous_move,
+                    );
+                }
+                break;
+            }
+
+            if !chess_move.is_noisy() {
+                searched_quiet_moves.push(chess_move);
+            }
+        }
+
+        self.position_history.pop();
+
+        /* Every root move can be excluded when searching more lines than there are moves. */
+        if move_count == 0 && (ply != 0 || self.excluded_root_moves.is_empty()) {
+            return if in_check {
+                -MATE_SCORE + ply as i32
+            } else {
+                DRAW_SCORE
+            };
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+
+        /* A root search with excluded moves does not give the real score of the position. */
+        if ply == 0 && !self.excluded_root_moves.is_empty() {
+            return best_score;
+        }
+
+        self.shared.transposition_table.store(TableEntry {
+            key: hash,
+            best_move: best_move.map(|chess_move| TableMove::from_move(&chess_move)),
+            score: score_to_table(best_score, ply),
+            depth,
+            bound,
+        });
+
+        best_score
+    }
+
+    /** Searches captures and promotions until the position is quiet, so the static evaluation is
+    not taken in the middle of an exchange. When in check all evasions are searched. */
+    fn quiescence(
+        &mut self,
+        chess_board: &ChessBoard,
+        mut alpha: i32,
+        beta: i32,
+        ply: usize,
+    ) -> i32 {
+        if self.should_stop() {
+            return DRAW_SCORE;
+        }
+
+        self.nodes += 1;
+
+        let in_check = chess_board.king_is_in_check();
+
+        if !in_check {
+            let stand_pat = evaluate(chess_board);
+
+            if stand_pat >= beta || ply >= MAX_PLY - 1 {
+                return stand_pat;
+            }
+
+            alpha = alpha.max(stand_pat);
+        }
+
+        let mut picker = if in_check {
+            MovePicker::new(None, [None; 2], None)
+        } else {
+            MovePicker::new_noisy(None)
+        };
+
+        let mut best_score = if in_check { -INFINITY } else { alpha };
+        let mut move_count: u32 = 0;
+
+        while let Some(chess_move) = picker.next_move(chess_board, self.heuristics) {
+            let mut child_board = *chess_board;
+            child_board.make_move_on_board(&chess_move);
+            child_board.update_meta_data(&chess_move);
+            move_count += 1;
+
+            let score = -self.quiescence(&child_board, -beta, -alpha, ply + 1);
+
+            if self.stopped {
+                return DRAW_SCORE;
+            }
+
+            if score > best_score {
+                best_score = score;
+
+                if score > alpha {
+                    alpha = score;
+                }
+            }
+
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        if in_check && move_count == 0 {
+            return -MATE_SCORE + ply as i32;
+        }
+
+        best_score
+    }
+
+    fn update_quiet_move_heuristics(
+        &mut self,
+        chess_board: &ChessBoard,
+        cutoff_move: Move,
+        searched_quiet_moves: &[Move],
+        depth: u32,
+        ply: usize,
+        previous_move: Option<Move>,
+    ) {
+        let side_to_move = chess_board.side_to_move();
+        let bonus = (depth * depth) as i32;
+
+        self.heuristics.store_killer_move(ply, cutoff_move);
+        self.heuristics
+            .store_counter_move(side_to_move, previous_move, cutoff_move);
+        self.heuristics
+            .update_history(side_to_move, &cutoff_move, bonus);
+
+        for quiet_move in searched_quiet_moves {
+            self.heuristics
+                .update_history(side_to_move, quiet_move, -bonus);
+        }
+    }
+
+    /** Probes the WDL tables right after a capture or pawn move, where the result of the table
+    does not depend on the fifty move rule counter. Returns the score if it causes a cutoff. */
+    fn probe_tablebases(
+        &mut self,
+        chess_board: &ChessBoard,
+        hash: u64,
+        depth: u32,
+        alpha: i32,
+        beta: i32,
+        ply: usize,
+    ) -> Option<i32> {
+        let tablebases = self.shared.tablebases?;
+
+        if ply == 0 || chess_board.half_move_clock() != 0 || !tablebases.can_probe(chess_board) {
+            return None;
+        }
+
+        let (score, bound) = match tablebases.probe_wdl(chess_board)? {
+            Wdl::Win => (TABLEBASE_WIN_SCORE - ply as i32, Bound::Lower),
+            Wdl::Loss => (-TABLEBASE_WIN_SCORE + ply as i32, Bound::Upper),
+            /* Cursed wins and blessed losses are draws, scored a little off zero. */
+            wdl => (DRAW_SCORE + wdl as i32, Bound::Exact),
+        };
+
+        let entry = TableEntry {
+            key: hash,
+            best_move: None,
+            score: score_to_table(score, ply),
+            depth: (depth + 6).min(MAX_PLY as u32 - 1),
+            bound,
+        };
+
+        if !bound_allows_cutoff(&entry, score, alpha, beta) {
+            return None;
+        }
+
+        self.shared.transposition_table.store(entry);
+        Some(score)
+    }
+
+    /** Checks for the fifty move rule and repetitions. A single repetition is scored as a draw,
+    since the side that can repeat can usually also repeat again. */
+    fn is_draw(&self, chess_board: &ChessBoard, hash: u64) -> bool {
+        if chess_board.is_fifty_move_draw() {
+            return true;
+        }
+
+        let reversible_moves = chess_board.half_move_clock() as usize;
+
+        self.position_history
+            .iter()
+            .rev()
+            .take(reversible_moves)
+            .skip(1)
+            .step_by(2)
+            .any(|previous_hash| *previous_hash == hash)
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+
+        if !self.nodes.is_multiple_of(LIMIT_CHECK_INTERVAL) {
+            return false;
+        }
+
+        self.publish_nodes();
+
+        if self.shared.stop_signal.load(Ordering::Relaxed) {
+            self.stopped = true;
+        }
+
+        if let Some(node_limit) = self.shared.limits.nodes {
+            if self.shared.total_nodes() >= node_limit {
+                self.stopped = true;
+            }
+        }
+
+        if let Some(clock_start_time) = self.shared.clock_start_time() {
+            if let Some(time_limit) = self.shared.hard_time_limit() {
+                if clock_start_time.elapsed() >= time_limit {
+                    self.stopped = true;
+                }
+            }
+
+            /* Pondering already used the time planned for the move before the ponder hit. */
+            if let Some(time_allocation) = self.shared.time_allocation {
+                let pondering_time = clock_start_time.duration_since(self.shared.start_time);
+
+                if pondering_time >= time_allocation.soft_limit {
+                    self.stopped = true;
+                }
+            }
+        }
+
+        self.stopped
+    }
+
+    fn publish_nodes(&self) {
+        self.shared.thread_nodes[self.id].store(self.nodes, Ordering::Relaxed);
+    }
+}
+
+/** Converts the rank of a tablebase root move to a score. Wins that the fifty move rule cannot
+draw get the tablebase win score, cursed wins get a few centipawns that grow as the fifty move
+rule gets closer to allowing the win. */
+fn tablebase_rank_to_score(rank: i32) -> i32 {
+    let certain_rank = MAX_DTZ / 2 - 100;
+
+    if rank >= certain_rank {
+        TABLEBASE_WIN_SCORE
+    } else if rank > 0 {
+        (rank - (MAX_DTZ / 2 - 200)).max(3) * PAWN_VALUE / 200
+    } else if rank == 0 {
+        DRAW_SCORE
+    } else if rank > -certain_rank {
+        (rank + (MAX_DTZ / 2 - 200)).min(-3) * PAWN_VALUE / 200
+    } else {
+        -TABLEBASE_WIN_SCORE
+    }
+}
+
+fn bound_allows_cutoff(entry: &TableEntry, score: i32, alpha: i32, beta: i32) -> bool {
+    match entry.bound {
+        Bound::Exact => true,
+        Bound::Lower => score >= beta,
+        Bound::Upper => score <= alpha,
+    }
+}
+
+/** Mate scores are stored relative to the node instead of the root, so they stay correct when
+the position is reached through a different path. */
+fn score_to_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply as i32
+    } else if score <= -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply as i32
+    } else if score <= -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search_to_depth(fen: &str, depth: u32) -> SearchResult {
+        let chess_board = ChessBoard::new_from_fen(fen).unwrap();
+        let mut searcher = Searcher::new(1);
+
+        searcher.search(
+            &chess_board,
+            SearchLimits {
+                depth: Some(depth),
+                ..SearchLimits::default()
+            },
+            |_| {},
+        )
+    }
+
+    #[test]
+    fn test_finds_mate_in_one() {
+        let result = search_to_depth("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 2);
+
+        assert_eq!(result.best_move.unwrap().move_to_string(), "a1a8");
+        assert_eq!(result.score, MATE_SCORE - 1);
+    }
+
+    #[test]
+    fn test_captures_hanging_queen() {
+        let result = search_to_depth("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 3);
+
+        assert_eq!(result.best_move.unwrap().move_to_string(), "d2d5");
+    }
+
+    #[test]
+    fn test_single_thread_search_is_deterministic() {
+        let limits = SearchLimits {
+            depth: Some(4),
+            ..SearchLimits::default()
+        };
+
+        let first = Searcher::new(1).search(&ChessBoard::new(), limits, |_| {});
+        let second = Searcher::new(1).search(&ChessBoard::new(), limits, |_| {});
+
+        assert_eq!(first.nodes, second.nodes);
+        assert_eq!(first.best_move, second.best_move);
+    }
+
+    #[test]
+    fn test_helper_threads_add_to_node_count() {
+        let chess_board = ChessBoard::new();
+        let mut searcher = Searcher::new(1);
+        searcher.set_threads(3);
+        let mut reported_nodes: Vec<u64> = Vec::new();
+
+        let result = searcher.search(
+            &chess_board,
+            SearchLimits {
+                depth: Some(4),
+                ..SearchLimits::default()
+            },
+            |info| reported_nodes.push(info.nodes),
+        );
+
+        assert!(chess_board.is_legal_move(&result.best_move.unwrap()));
+        assert!(reported_nodes.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(result.nodes >= *reported_nodes.last().unwrap());
+    }
+
+    #[test]
+    fn test_multi_pv_returns_ranked_distinct_lines() {
+        let chess_board = ChessBoard::new_from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(1);
+        searcher.set_multi_pv(3);
+        let mut reported_lines: Vec<usize> = Vec::new();
+
+        let result = searcher.search(
+            &chess_board,
+            SearchLimits {
+                depth: Some(3),
+                ..SearchLimits::default()
+            },
+            |info| reported_lines.push(info.multi_pv),
+        );
+
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(result.lines[0].first_move.move_to_string(), "d2d5");
+        assert_eq!(result.best_move, Some(result.lines[0].first_move));
+        assert!(result
+            .lines
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+        assert_ne!(result.lines[1].first_move, result.lines[2].first_move);
+        assert_ne!(result.lines[0].first_move, result.lines[1].first_move);
+        assert_eq!(&reported_lines[reported_lines.len() - 3..], &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_ponder_search_waits_for_ponderhit() {
+        let mut searcher = Searcher::new(1);
+        let ponder_signal = searcher.ponder_signal();
+        let start_time = Instant::now();
+
+        let result = thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(50));
+                ponder_signal.ponderhit();
+            });
+
+            searcher.search(
+                &ChessBoard::new(),
+                SearchLimits {
+                    depth: Some(2),
+                    ponder: true,
+                    ..SearchLimits::default()
+                },
+                |_| {},
+            )
+        });
+
+        assert!(start_time.elapsed() >= Duration::from_millis(50));
+        assert!(result.best_move.is_some());
+        assert!(ponder_signal.ponderhit_time().is_none());
+    }
+
+    #[test]
+    fn test_stalemate_is_a_draw() {
+        let result = search_to_depth("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 2);
+
+        assert!(result.best_move.is_none());
+        assert_eq!(result.score, DRAW_SCORE);
+    }
+}
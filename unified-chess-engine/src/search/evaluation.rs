@@ -0,0 +1,219 @@
+/* This module implements a static evaluation of a chessboard. The evaluation is based on material
+ * and piece-square tables, where the king uses separate tables for the middle game and the end
+ * game. Elementary endgames are recognized from the material and evaluated separately. */
+pub mod endgame;
+mod kpk;
+
+use crate::array_engine::{ChessBoard, Color, PieceType, Position, COL_SIZE, ROW_SIZE};
+use endgame::{evaluate_endgame, scale_factor, MaterialSignature, NORMAL_SCALE_FACTOR};
+
+pub const PAWN_VALUE: i32 = 100;
+pub const KNIGHT_VALUE: i32 = 320;
+pub const BISHOP_VALUE: i32 = 330;
+pub const ROOK_VALUE: i32 = 500;
+pub const QUEEN_VALUE: i32 = 900;
+pub const KING_VALUE: i32 = 20000;
+
+/* The phase weights sum up to 24 for the starting position. */
+const KNIGHT_PHASE: i32 = 1;
+const BISHOP_PHASE: i32 = 1;
+const ROOK_PHASE: i32 = 2;
+const QUEEN_PHASE: i32 = 4;
+const MAX_PHASE: i32 = 24;
+
+/* The tables are written from the perspective of white, where the first row is rank 8. */
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const KING_MIDDLE_GAME_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+#[rustfmt::skip]
+const KING_END_GAME_TABLE: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+pub fn piece_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn => PAWN_VALUE,
+        PieceType::Knight => KNIGHT_VALUE,
+        PieceType::Bishop => BISHOP_VALUE,
+        PieceType::Rook => ROOK_VALUE,
+        PieceType::Queen => QUEEN_VALUE,
+        PieceType::King => KING_VALUE,
+    }
+}
+
+/** Evaluates the board in centipawns from the perspective of the side to move. Elementary
+endgames are evaluated with special knowledge. */
+pub fn evaluate(chess_board: &ChessBoard) -> i32 {
+    let signature = MaterialSignature::new(chess_board);
+
+    if let Some(score) = evaluate_endgame(chess_board, &signature) {
+        return score;
+    }
+
+    let mut middle_game_score: i32 = 0;
+    let mut end_game_score: i32 = 0;
+    let mut phase: i32 = 0;
+
+    for rank in 0..ROW_SIZE {
+        for file in 0..COL_SIZE {
+            let Some(piece) = chess_board.piece_at((file, rank)) else {
+                continue;
+            };
+
+            let sign: i32 = if piece.color() == Color::White { 1 } else { -1 };
+            let table_index = table_index(piece.color(), (file, rank));
+
+            let (middle_game_value, end_game_value) = match piece.piece_type() {
+                PieceType::King => (
+                    KING_MIDDLE_GAME_TABLE[table_index],
+                    KING_END_GAME_TABLE[table_index],
+                ),
+                piece_type => {
+                    let value =
+                        piece_value(piece_type) + piece_square_value(piece_type, table_index);
+                    (value, value)
+                }
+            };
+
+            middle_game_score += sign * middle_game_value;
+            end_game_score += sign * end_game_value;
+            phase += piece_phase(piece.piece_type());
+        }
+    }
+
+    let phase: i32 = phase.min(MAX_PHASE);
+    let score: i32 = (middle_game_score * phase + end_game_score * (MAX_PHASE - phase)) / MAX_PHASE;
+    let score: i32 = score * scale_factor(chess_board, &signature) / NORMAL_SCALE_FACTOR;
+
+    if chess_board.side_to_move() == Color::White {
+        score
+    } else {
+        -score
+    }
+}
+
+fn piece_square_value(piece_type: PieceType, table_index: usize) -> i32 {
+    match piece_type {
+        PieceType::Pawn => PAWN_TABLE[table_index],
+        PieceType::Knight => KNIGHT_TABLE[table_index],
+        PieceType::Bishop => BISHOP_TABLE[table_index],
+        PieceType::Rook => ROOK_TABLE[table_index],
+        PieceType::Queen => QUEEN_TABLE[table_index],
+        PieceType::King => KING_MIDDLE_GAME_TABLE[table_index],
+    }
+}
+
+fn piece_phase(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Knight => KNIGHT_PHASE,
+        PieceType::Bishop => BISHOP_PHASE,
+        PieceType::Rook => ROOK_PHASE,
+        PieceType::Queen => QUEEN_PHASE,
+        PieceType::Pawn | PieceType::King => 0,
+    }
+}
+
+/** The tables are stored with rank 8 first, so the rank is mirrored for white pieces. */
+fn table_index(color: Color, position: Position) -> usize {
+    let (file, rank) = position;
+
+    match color {
+        Color::White => (ROW_SIZE - 1 - rank) * COL_SIZE + file,
+        Color::Black => rank * COL_SIZE + file,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_position_is_balanced() {
+        assert_eq!(evaluate(&ChessBoard::new()), 0);
+    }
+
+    #[test]
+    fn test_evaluation_is_symmetric() {
+        let white_to_move = ChessBoard::new_from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 0 1").unwrap();
+        let black_to_move = ChessBoard::new_from_fen("r3k3/4p3/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+
+        assert!(evaluate(&white_to_move) > ROOK_VALUE);
+        assert_eq!(evaluate(&white_to_move), evaluate(&black_to_move));
+    }
+}
@@ -0,0 +1,426 @@
+/* This submodule knows elementary endgames that the general evaluation misjudges. The endgame
+ * is recognized from the material signature of the board, e.g. KBNvK. Some endgames are
+ * evaluated by special functions, which drive the lone king to the edge or to the right corner,
+ * others only scale the general evaluation down, like endgames with opposite-colored bishops. */
+use super::kpk::probe_kpk;
+use super::{piece_value, PAWN_VALUE, ROOK_VALUE};
+use crate::array_engine::{ChessBoard, Color, PieceType, Position, COL_SIZE, ROW_SIZE};
+use std::fmt;
+
+/* Scores of endgames that are known to be won, below the scores of tablebase wins and mates. */
+pub const KNOWN_WIN_SCORE: i32 = 10000;
+
+/* Scale factors are given in 64ths of the evaluation. */
+pub const NORMAL_SCALE_FACTOR: i32 = 64;
+const OPPOSITE_BISHOPS_SCALE_FACTOR: i32 = 24;
+
+const PIECE_TYPES: [PieceType; 6] = [
+    PieceType::King,
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Pawn,
+];
+
+/** The number of pieces of every type and color on the board. */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MaterialSignature {
+    counts: [[u8; 6]; 2],
+}
+
+impl MaterialSignature {
+    pub fn new(chess_board: &ChessBoard) -> MaterialSignature {
+        let mut signature = MaterialSignature::default();
+
+        for rank in 0..ROW_SIZE {
+            for file in 0..COL_SIZE {
+                if let Some(piece) = chess_board.piece_at((file, rank)) {
+                    signature.counts[color_index(piece.color())]
+                        [piece_index(piece.piece_type())] += 1;
+                }
+            }
+        }
+
+        signature
+    }
+
+    pub fn count(&self, color: Color, piece_type: PieceType) -> u8 {
+        self.counts[color_index(color)][piece_index(piece_type)]
+    }
+
+    /** The value of the knights, bishops, rooks and queens of the color. */
+    pub fn non_pawn_material(&self, color: Color) -> i32 {
+        [
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Rook,
+            PieceType::Queen,
+        ]
+        .into_iter()
+        .map(|piece_type| self.count(color, piece_type) as i32 * piece_value(piece_type))
+        .sum()
+    }
+
+    /** Returns true if the color has the king and exactly the given pieces. */
+    fn has_exactly(&self, color: Color, pieces: &[(PieceType, u8)]) -> bool {
+        PIECE_TYPES
+            .into_iter()
+            .filter(|piece_type| *piece_type != PieceType::King)
+            .all(|piece_type| {
+                let expected = pieces
+                    .iter()
+                    .find(|(expected_type, _)| *expected_type == piece_type)
+                    .map_or(0, |(_, count)| *count);
+
+                self.count(color, piece_type) == expected
+            })
+    }
+
+    fn is_lone_king(&self, color: Color) -> bool {
+        self.has_exactly(color, &[])
+    }
+}
+
+/** Writes the signature like the names of tablebases, e.g. "KRPvKR". */
+impl fmt::Display for MaterialSignature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, color) in [Color::White, Color::Black].into_iter().enumerate() {
+            if i == 1 {
+                write!(f, "v")?;
+            }
+
+            for piece_type in PIECE_TYPES {
+                let letter = match piece_type {
+                    PieceType::King => 'K',
+                    PieceType::Queen => 'Q',
+                    PieceType::Rook => 'R',
+                    PieceType::Bishop => 'B',
+                    PieceType::Knight => 'N',
+                    PieceType::Pawn => 'P',
+                };
+
+                for _ in 0..self.count(color, piece_type) {
+                    write!(f, "{letter}")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/** Evaluates endgames with special knowledge, from the perspective of the side to move. Returns
+None if the general evaluation should be used. */
+pub fn evaluate_endgame(chess_board: &ChessBoard, signature: &MaterialSignature) -> Option<i32> {
+    if signature.count(Color::White, PieceType::King) != 1
+        || signature.count(Color::Black, PieceType::King) != 1
+    {
+        return None;
+    }
+
+    if chess_board.is_insufficient_material() {
+        return Some(0);
+    }
+
+    for strong_side in [Color::White, Color::Black] {
+        let weak_side = strong_side.opposite();
+
+        if !signature.is_lone_king(weak_side) {
+            continue;
+        }
+
+        let score = if signature.has_exactly(strong_side, &[(PieceType::Knight, 2)]) {
+            /* Mate cannot be forced. */
+            Some(0)
+        } else if signature.has_exactly(strong_side, &[(PieceType::Pawn, 1)]) {
+            Some(evaluate_kpk(chess_board, strong_side))
+        } else if signature.has_exactly(
+            strong_side,
+            &[(PieceType::Bishop, 1), (PieceType::Knight, 1)],
+        ) {
+            Some(evaluate_kbnk(chess_board, strong_side))
+        } else if is_wrong_bishop_draw(chess_board, signature, strong_side) {
+            Some(0)
+        } else if signature.count(strong_side, PieceType::Pawn) == 0
+            && signature.non_pawn_material(strong_side) >= ROOK_VALUE
+        {
+            Some(evaluate_kxk(chess_board, signature, strong_side))
+        } else {
+            None
+        };
+
+        return score.map(|score| {
+            if chess_board.side_to_move() == strong_side {
+                score
+            } else {
+                -score
+            }
+        });
+    }
+
+    None
+}
+
+/** Returns the factor in 64ths that the evaluation is scaled with, endgames with
+opposite-colored bishops and only pawns besides are often drawn even a few pawns down. */
+pub fn scale_factor(chess_board: &ChessBoard, signature: &MaterialSignature) -> i32 {
+    let only_bishops_and_pawns = [Color::White, Color::Black].into_iter().all(|color| {
+        signature.has_exactly(
+            color,
+            &[
+                (PieceType::Bishop, 1),
+                (PieceType::Pawn, signature.count(color, PieceType::Pawn)),
+            ],
+        )
+    });
+
+    if !only_bishops_and_pawns {
+        return NORMAL_SCALE_FACTOR;
+    }
+
+    let white_bishop = find_pieces(chess_board, Color::White, PieceType::Bishop)[0];
+    let black_bishop = find_pieces(chess_board, Color::Black, PieceType::Bishop)[0];
+
+    if square_color(white_bishop) != square_color(black_bishop) {
+        OPPOSITE_BISHOPS_SCALE_FACTOR
+    } else {
+        NORMAL_SCALE_FACTOR
+    }
+}
+
+/** Looks up king and pawn against king in the bitbase, a won position scores more the further
+the pawn has advanced. */
+fn evaluate_kpk(chess_board: &ChessBoard, strong_side: Color) -> i32 {
+    let normalize = |position: Position| -> usize {
+        let (mut file, mut rank) = position;
+        let pawn_file = find_pieces(chess_board, strong_side, PieceType::Pawn)[0].0;
+
+        if strong_side == Color::Black {
+            rank = ROW_SIZE - 1 - rank;
+        }
+        if pawn_file >= 4 {
+            file = COL_SIZE - 1 - file;
+        }
+
+        rank * COL_SIZE + file
+    };
+
+    let strong_king = normalize(find_pieces(chess_board, strong_side, PieceType::King)[0]);
+    let pawn = normalize(find_pieces(chess_board, strong_side, PieceType::Pawn)[0]);
+    let weak_king = normalize(find_pieces(chess_board, strong_side.opposite(), PieceType::King)[0]);
+
+    if probe_kpk(
+        strong_king,
+        pawn,
+        weak_king,
+        chess_board.side_to_move() == strong_side,
+    ) {
+        KNOWN_WIN_SCORE + PAWN_VALUE + (pawn / COL_SIZE) as i32
+    } else {
+        0
+    }
+}
+
+/** Mates with a bishop and a knight happen in the corners of the color of the bishop, so the lone
+king is driven to these corners. */
+fn evaluate_kbnk(chess_board: &ChessBoard, strong_side: Color) -> i32 {
+    let strong_king = find_pieces(chess_board, strong_side, PieceType::King)[0];
+    let weak_king = find_pieces(chess_board, strong_side.opposite(), PieceType::King)[0];
+    let bishop = find_pieces(chess_board, strong_side, PieceType::Bishop)[0];
+
+    /* a1 and h8 are dark squares, the other corners are light. */
+    let corner_king = if square_color(bishop) == square_color((0, 0)) {
+        weak_king
+    } else {
+        (COL_SIZE - 1 - weak_king.0, weak_king.1)
+    };
+
+    KNOWN_WIN_SCORE
+        + PAWN_VALUE
+        + push_close(strong_king, weak_king)
+        + 420 * push_to_dark_corner(corner_king)
+}
+
+/** Mates with a queen, a rook or other mating material against a lone king happen on the edge,
+so the lone king is driven to the edge and the strong king comes closer. */
+fn evaluate_kxk(
+    chess_board: &ChessBoard,
+    signature: &MaterialSignature,
+    strong_side: Color,
+) -> i32 {
+    let weak_side = strong_side.opposite();
+
+    /* The lone king can be stalemated. */
+    if chess_board.side_to_move() == weak_side && chess_board.legal_moves().is_empty() {
+        return 0;
+    }
+
+    let strong_king = find_pieces(chess_board, strong_side, PieceType::King)[0];
+    let weak_king = find_pieces(chess_board, weak_side, PieceType::King)[0];
+
+    KNOWN_WIN_SCORE
+        + signature.non_pawn_material(strong_side)
+        + push_to_edge(weak_king)
+        + push_close(strong_king, weak_king)
+}
+
+/** A bishop and pawns on one rook file cannot win when the bishop does not control the
+promotion square and the lone king reaches the corner. */
+fn is_wrong_bishop_draw(
+    chess_board: &ChessBoard,
+    signature: &MaterialSignature,
+    strong_side: Color,
+) -> bool {
+    let pawn_count = signature.count(strong_side, PieceType::Pawn);
+
+    if pawn_count == 0
+        || !signature.has_exactly(
+            strong_side,
+            &[(PieceType::Bishop, 1), (PieceType::Pawn, pawn_count)],
+        )
+    {
+        return false;
+    }
+
+    let pawns = find_pieces(chess_board, strong_side, PieceType::Pawn);
+    let pawn_file = pawns[0].0;
+
+    if (pawn_file != 0 && pawn_file != COL_SIZE - 1) || pawns.iter().any(|pawn| pawn.0 != pawn_file)
+    {
+        return false;
+    }
+
+    let promotion_square = match strong_side {
+        Color::White => (pawn_file, ROW_SIZE - 1),
+        Color::Black => (pawn_file, 0),
+    };
+    let bishop = find_pieces(chess_board, strong_side, PieceType::Bishop)[0];
+    let weak_king = find_pieces(chess_board, strong_side.opposite(), PieceType::King)[0];
+
+    square_color(bishop) != square_color(promotion_square)
+        && distance(promotion_square, weak_king) <= 1
+}
+
+fn find_pieces(chess_board: &ChessBoard, color: Color, piece_type: PieceType) -> Vec<Position> {
+    let mut positions: Vec<Position> = Vec::new();
+
+    for rank in 0..ROW_SIZE {
+        for file in 0..COL_SIZE {
+            if chess_board
+                .piece_at((file, rank))
+                .is_some_and(|piece| piece.color() == color && piece.piece_type() == piece_type)
+            {
+                positions.push((file, rank));
+            }
+        }
+    }
+
+    positions
+}
+
+/* Dark squares have an even sum of file and rank, like a1. */
+fn square_color(position: Position) -> usize {
+    (position.0 + position.1) % 2
+}
+
+fn distance(first: Position, second: Position) -> usize {
+    first.0.abs_diff(second.0).max(first.1.abs_diff(second.1))
+}
+
+fn edge_distance(coordinate: usize) -> i32 {
+    coordinate.min(7 - coordinate) as i32
+}
+
+fn push_to_edge(position: Position) -> i32 {
+    let file_distance = edge_distance(position.0);
+    let rank_distance = edge_distance(position.1);
+
+    90 - (7 * file_distance * file_distance / 2 + 7 * rank_distance * rank_distance / 2)
+}
+
+/* Highest in the a1 and h8 corners and zero on the a8-h1 diagonal. */
+fn push_to_dark_corner(position: Position) -> i32 {
+    (7 - position.0 as i32 - position.1 as i32).abs()
+}
+
+fn push_close(first: Position, second: Position) -> i32 {
+    140 - 20 * distance(first, second) as i32
+}
+
+fn color_index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+fn piece_index(piece_type: PieceType) -> usize {
+    match piece_type {
+        PieceType::King => 0,
+        PieceType::Queen => 1,
+        PieceType::Rook => 2,
+        PieceType::Bishop => 3,
+        PieceType::Knight => 4,
+        PieceType::Pawn => 5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate_fen(fen: &str) -> Option<i32> {
+        let chess_board = ChessBoard::new_from_fen(fen).unwrap();
+        evaluate_endgame(&chess_board, &MaterialSignature::new(&chess_board))
+    }
+
+    #[test]
+    fn test_material_signature() {
+        let chess_board = ChessBoard::new_from_fen("8/8/4k3/8/2B5/3NK3/8/8 w - - 0 1").unwrap();
+        let signature = MaterialSignature::new(&chess_board);
+
+        assert_eq!(signature.to_string(), "KBNvK");
+        assert_eq!(signature.count(Color::White, PieceType::Knight), 1);
+        assert_eq!(
+            MaterialSignature::new(&ChessBoard::new()).to_string(),
+            "KQRRBBNNPPPPPPPPvKQRRBBNNPPPPPPPP"
+        );
+    }
+
+    #[test]
+    fn test_known_endgames() {
+        /* KQK and KRK are wins, better with the lone king on the edge. */
+        let centre = evaluate_fen("8/8/8/3k4/8/4K3/8/Q7 w - - 0 1").unwrap();
+        let edge = evaluate_fen("3k4/8/4K3/8/8/8/8/Q7 w - - 0 1").unwrap();
+        assert!(centre > KNOWN_WIN_SCORE && edge > centre);
+        assert!(evaluate_fen("8/8/8/3k4/8/8/8/r3K3 w - - 0 1").unwrap() < -KNOWN_WIN_SCORE);
+
+        /* KBNK with a light bishop mates in the a8 and h1 corners. */
+        let right_corner = evaluate_fen("k7/8/1K6/8/8/8/8/3B1N2 w - - 0 1").unwrap();
+        let wrong_corner = evaluate_fen("8/8/8/8/8/1K6/8/k2B1N2 w - - 0 1").unwrap();
+        assert!(right_corner > wrong_corner);
+
+        /* Insufficient material, KPK from the bitbase and the wrong bishop. */
+        assert_eq!(evaluate_fen("8/8/4k3/8/8/3KN3/8/8 w - - 0 1"), Some(0));
+        assert!(evaluate_fen("3k4/8/3K4/3P4/8/8/8/8 b - - 0 1").unwrap() < -KNOWN_WIN_SCORE);
+        assert_eq!(evaluate_fen("8/3k4/8/3K4/3P4/8/8/8 w - - 0 1"), Some(0));
+        assert_eq!(evaluate_fen("k7/8/8/P7/P7/8/8/2B1K3 w - - 0 1"), Some(0));
+        assert_eq!(evaluate_fen("k7/8/8/P7/P7/8/8/3BK3 w - - 0 1"), None);
+    }
+
+    #[test]
+    fn test_opposite_colored_bishops_scale_down() {
+        let opposite = ChessBoard::new_from_fen("4k1b1/8/8/8/8/8/PPP5/2B1K3 w - - 0 1").unwrap();
+        let same = ChessBoard::new_from_fen("4kb2/8/8/8/8/8/PPP5/2B1K3 w - - 0 1").unwrap();
+
+        assert_eq!(
+            scale_factor(&opposite, &MaterialSignature::new(&opposite)),
+            OPPOSITE_BISHOPS_SCALE_FACTOR
+        );
+        assert_eq!(
+            scale_factor(&same, &MaterialSignature::new(&same)),
+            NORMAL_SCALE_FACTOR
+        );
+    }
+}
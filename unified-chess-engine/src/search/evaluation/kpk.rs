@@ -0,0 +1,223 @@
+/* This submodule generates a bitbase for king and pawn against king. The bitbase stores for every
+ * position with white to move or black to move whether white wins, which is computed once by
+ * repeatedly classifying the positions from the results of their successors until nothing
+ * changes. Positions are normalized so the pawn is white and on the files a to d, squares are
+ * numbered from a1 = 0 to h8 = 63. */
+use std::sync::OnceLock;
+
+/* 24 pawn squares, 64 squares for each king and two sides to move. */
+const POSITION_COUNT: usize = 2 * 24 * 64 * 64;
+
+/* The results are bit flags, so the results of the successors can be combined with `|`. */
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+const WHITE_TO_MOVE: usize = 0;
+const BLACK_TO_MOVE: usize = 1;
+
+static BITBASE: OnceLock<Vec<u64>> = OnceLock::new();
+
+/** Returns true if white wins the position with a king, a pawn on the files a to d and black
+only having a king. */
+pub fn probe_kpk(white_king: usize, pawn: usize, black_king: usize, white_to_move: bool) -> bool {
+    let side_to_move = if white_to_move {
+        WHITE_TO_MOVE
+    } else {
+        BLACK_TO_MOVE
+    };
+    let index = index(side_to_move, black_king, white_king, pawn);
+
+    BITBASE.get_or_init(generate)[index / 64] & (1 << (index % 64)) != 0
+}
+
+fn index(side_to_move: usize, black_king: usize, white_king: usize, pawn: usize) -> usize {
+    white_king
+        | (black_king << 6)
+        | (side_to_move << 12)
+        | (file(pawn) << 13)
+        | ((6 - rank(pawn)) << 15)
+}
+
+fn file(square: usize) -> usize {
+    square % 8
+}
+
+fn rank(square: usize) -> usize {
+    square / 8
+}
+
+fn distance(first: usize, second: usize) -> usize {
+    file(first)
+        .abs_diff(file(second))
+        .max(rank(first).abs_diff(rank(second)))
+}
+
+fn king_moves(square: usize) -> impl Iterator<Item = usize> {
+    (0..64).filter(move |target| distance(square, *target) == 1)
+}
+
+fn pawn_attacks(pawn: usize, square: usize) -> bool {
+    rank(square) == rank(pawn) + 1 && file(square).abs_diff(file(pawn)) == 1
+}
+
+struct KpkPosition {
+    side_to_move: usize,
+    white_king: usize,
+    black_king: usize,
+    pawn: usize,
+    result: u8,
+}
+
+impl KpkPosition {
+    fn new(index: usize) -> KpkPosition {
+        let white_king = index & 0x3f;
+        let black_king = (index >> 6) & 0x3f;
+        let side_to_move = (index >> 12) & 1;
+        let pawn = 8 * (6 - ((index >> 15) & 7)) + ((index >> 13) & 3);
+        let promotion_square = pawn + 8;
+
+        let result = if distance(white_king, black_king) <= 1
+            || white_king == pawn
+            || black_king == pawn
+            || (side_to_move == WHITE_TO_MOVE && pawn_attacks(pawn, black_king))
+        {
+            INVALID
+        } else if side_to_move == WHITE_TO_MOVE
+            && rank(pawn) == 6
+            && white_king != promotion_square
+            && (distance(black_king, promotion_square) > 1
+                || distance(white_king, promotion_square) == 1)
+        {
+            /* The pawn promotes and the new queen cannot be captured. */
+            WIN
+        } else if side_to_move == BLACK_TO_MOVE
+            && (king_moves(black_king)
+                .all(|square| distance(white_king, square) <= 1 || pawn_attacks(pawn, square))
+                || (distance(black_king, pawn) == 1 && distance(white_king, pawn) > 1))
+        {
+            /* Black is stalemated or captures the pawn. */
+            DRAW
+        } else {
+            UNKNOWN
+        };
+
+        KpkPosition {
+            side_to_move,
+            white_king,
+            black_king,
+            pawn,
+            result,
+        }
+    }
+
+    /** White wins if one move wins and black draws if one move draws, the position stays
+    unknown while a successor is unknown. */
+    fn classify(&self, results: &[u8]) -> u8 {
+        let (good, bad) = if self.side_to_move == WHITE_TO_MOVE {
+            (WIN, DRAW)
+        } else {
+            (DRAW, WIN)
+        };
+
+        let mut successors = INVALID;
+
+        if self.side_to_move == WHITE_TO_MOVE {
+            for square in king_moves(self.white_king) {
+                successors |= results[index(BLACK_TO_MOVE, self.black_king, square, self.pawn)];
+            }
+
+            if rank(self.pawn) < 6 {
+                successors |= results[index(
+                    BLACK_TO_MOVE,
+                    self.black_king,
+                    self.white_king,
+                    self.pawn + 8,
+                )];
+            }
+
+            let pushed_pawn = self.pawn + 8;
+            if rank(self.pawn) == 1
+                && pushed_pawn != self.white_king
+                && pushed_pawn != self.black_king
+            {
+                successors |= results[index(
+                    BLACK_TO_MOVE,
+                    self.black_king,
+                    self.white_king,
+                    self.pawn + 16,
+                )];
+            }
+        } else {
+            for square in king_moves(self.black_king) {
+                successors |= results[index(WHITE_TO_MOVE, square, self.white_king, self.pawn)];
+            }
+        }
+
+        if successors & good != 0 {
+            good
+        } else if successors & UNKNOWN != 0 {
+            UNKNOWN
+        } else {
+            bad
+        }
+    }
+}
+
+fn generate() -> Vec<u64> {
+    let mut positions: Vec<KpkPosition> = (0..POSITION_COUNT).map(KpkPosition::new).collect();
+    let mut results: Vec<u8> = positions.iter().map(|position| position.result).collect();
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+
+        for (i, position) in positions.iter_mut().enumerate() {
+            if position.result == UNKNOWN {
+                position.result = position.classify(&results);
+
+                if position.result != UNKNOWN {
+                    results[i] = position.result;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    let mut bitbase = vec![0u64; POSITION_COUNT / 64];
+
+    for (i, result) in results.iter().enumerate() {
+        if *result == WIN {
+            bitbase[i / 64] |= 1 << (i % 64);
+        }
+    }
+
+    bitbase
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(name: &str) -> usize {
+        let bytes = name.as_bytes();
+        (bytes[1] - b'1') as usize * 8 + (bytes[0] - b'a') as usize
+    }
+
+    #[test]
+    fn test_kpk_results() {
+        /* The king on the sixth rank in front of its pawn always wins. */
+        assert!(probe_kpk(square("d6"), square("d5"), square("d8"), false));
+        assert!(probe_kpk(square("d6"), square("d5"), square("d8"), true));
+        /* Further back the side with the opposition decides. */
+        assert!(probe_kpk(square("d5"), square("d4"), square("d7"), false));
+        assert!(!probe_kpk(square("d5"), square("d4"), square("d7"), true));
+        /* A rook pawn is a draw when the defending king reaches the corner. */
+        assert!(!probe_kpk(square("b6"), square("a5"), square("a8"), true));
+        /* The pawn runs away from a distant king. */
+        assert!(probe_kpk(square("h1"), square("c4"), square("h8"), true));
+        /* Black captures the undefended pawn. */
+        assert!(!probe_kpk(square("h1"), square("c4"), square("d5"), false));
+    }
+}
@@ -0,0 +1,252 @@
+/* This module implements a mate finder, which proves forced mates with a depth-first search.
+ * Unlike the main search it does not evaluate positions: the attacker looks for a move after
+ * which every defence is mated in time, and the defender looks for a single reply that escapes.
+ * Checking moves are tried first, and on the last move only checks are considered, since only a
+ * check can mate. Positions that are proven not to be mates are remembered, so they are not
+ * searched again through transpositions. */
+use crate::array_engine::{ChessBoard, Move};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/* How many nodes are searched between checks of the stop signal. */
+const STOP_CHECK_INTERVAL: u64 = 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MateSearchResult {
+    /** The mating line, starting and ending with a move of the attacking side. The line has the
+    fewest possible moves, and the defender plays the replies that delay the mate the longest. */
+    Mate(Vec<Move>),
+    /** It is proven that the side to move can not force mate within the given number of moves. */
+    NoMate,
+    Stopped,
+}
+
+struct MateSearch<'a> {
+    stop_signal: &'a AtomicBool,
+    /* The highest number of moves each attacking position is proven not to mate in. */
+    proven_no_mate: HashMap<u64, u32>,
+    nodes: u64,
+    stopped: bool,
+}
+
+/** Searches for a forced mate in at most `max_moves` moves of the side to move, mates in fewer
+moves are found first. The search can be interrupted by setting the stop signal. */
+pub fn find_mate(
+    chess_board: &ChessBoard,
+    max_moves: u32,
+    stop_signal: &AtomicBool,
+) -> MateSearchResult {
+    let mut mate_search = MateSearch {
+        stop_signal,
+        proven_no_mate: HashMap::new(),
+        nodes: 0,
+        stopped: false,
+    };
+
+    match mate_search.attack(chess_board, max_moves) {
+        _ if mate_search.stopped => MateSearchResult::Stopped,
+        Some(mating_line) => MateSearchResult::Mate(mating_line),
+        None => MateSearchResult::NoMate,
+    }
+}
+
+/** Returns the number of moves of the mate of the given mating line. */
+pub fn mate_length(mating_line: &[Move]) -> u32 {
+    mating_line.len().div_ceil(2) as u32
+}
+
+impl MateSearch<'_> {
+    /** Returns the shortest line that mates in at most `moves` moves of the side to move. Shorter
+    mates are tried first, so the length of the lines tells `defend` which defence lasts longest. */
+    fn attack(&mut self, chess_board: &ChessBoard, moves: u32) -> Option<Vec<Move>> {
+        for moves in 1..=moves {
+            let mating_line = self.attack_within(chess_board, moves);
+
+            if mating_line.is_some() || self.stopped {
+                return mating_line;
+            }
+        }
+
+        None
+    }
+
+    /** Returns a line that mates in at most `moves` moves of the side to move, which is not
+    necessarily the shortest one. */
+    fn attack_within(&mut self, chess_board: &ChessBoard, moves: u32) -> Option<Vec<Move>> {
+        if self.should_stop() {
+            return None;
+        }
+
+        let hash = chess_board.zobrist_hash();
+
+        if self
+            .proven_no_mate
+            .get(&hash)
+            .is_some_and(|proven_moves| *proven_moves >= moves)
+        {
+            return None;
+        }
+
+        let mut checking_moves: Vec<(Move, ChessBoard)> = Vec::new();
+        let mut quiet_moves: Vec<(Move, ChessBoard)> = Vec::new();
+
+        for chess_move in chess_board.legal_moves() {
+            let mut child_board = *chess_board;
+            child_board.make_move_on_board(&chess_move);
+            child_board.update_meta_data(&chess_move);
+
+            if child_board.king_is_in_check() {
+                checking_moves.push((chess_move, child_board));
+            } else if moves > 1 {
+                quiet_moves.push((chess_move, child_board));
+            }
+        }
+
+        for (chess_move, child_board) in checking_moves.into_iter().chain(quiet_moves) {
+            if let Some(defence) = self.defend(&child_board, moves - 1) {
+                let mut mating_line = vec![chess_move];
+                mating_line.extend(defence);
+
+                return Some(mating_line);
+            }
+
+            if self.stopped {
+                return None;
+            }
+        }
+
+        if !self.stopped {
+            self.proven_no_mate.insert(hash, moves);
+        }
+
+        None
+    }
+
+    /** Returns the longest defence if every reply is mated within `moves` further moves, or None
+    if the defender escapes. An empty line means that the defender is already mated. */
+    fn defend(&mut self, chess_board: &ChessBoard, moves: u32) -> Option<Vec<Move>> {
+        self.nodes += 1;
+        let replies = chess_board.legal_moves();
+
+        if replies.is_empty() {
+            return chess_board.king_is_in_check().then(Vec::new);
+        }
+
+        if moves == 0 {
+            return None;
+        }
+
+        let mut longest_defence: Vec<Move> = Vec::new();
+
+        for reply in replies {
+            let mut child_board = *chess_board;
+            child_board.make_move_on_board(&reply);
+            child_board.update_meta_data(&reply);
+
+            let mating_line = self.attack(&child_board, moves)?;
+
+            if mating_line.len() + 1 > longest_defence.len() {
+                longest_defence = vec![reply];
+                longest_defence.extend(mating_line);
+            }
+        }
+
+        Some(longest_defence)
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.nodes.is_multiple_of(STOP_CHECK_INTERVAL)
+            && self.stop_signal.load(Ordering::Relaxed)
+        {
+            self.stopped = true;
+        }
+
+        self.stopped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mating_line_to_string(fen: &str, max_moves: u32) -> Option<String> {
+        let chess_board = ChessBoard::new_from_fen(fen).unwrap();
+
+        match find_mate(&chess_board, max_moves, &AtomicBool::new(false)) {
+            MateSearchResult::Mate(mating_line) => Some(
+                mating_line
+                    .iter()
+                    .map(|chess_move| chess_move.move_to_string())
+                    .collect::<Vec<String>>()
+                    .join(" "),
+            ),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_finds_back_rank_mate() {
+        let mating_line = mating_line_to_string("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+
+        assert_eq!(mating_line.as_deref(), Some("a1a8"));
+    }
+
+    #[test]
+    fn test_finds_mate_in_two_with_a_quiet_move() {
+        /* 1. Kb6 Kb8 2. Rd8# */
+        let chess_board = ChessBoard::new_from_fen("k7/8/2K5/8/8/8/8/3R4 w - - 0 1").unwrap();
+
+        let MateSearchResult::Mate(mating_line) =
+            find_mate(&chess_board, 2, &AtomicBool::new(false))
+        else {
+            panic!("Expected a mate in two");
+        };
+
+        assert_eq!(mate_length(&mating_line), 2);
+        assert_eq!(mating_line.len(), 3);
+    }
+
+    #[test]
+    fn test_mating_line_is_shortest_after_every_reply() {
+        for (fen, max_moves) in [
+            ("k7/8/2K5/8/8/8/8/3R4 w - - 0 1", 3),
+            ("8/8/8/8/8/2K5/7R/k7 w - - 0 1", 4),
+            ("8/8/8/8/8/8/5KQ1/3k4 w - - 0 1", 3),
+            ("8/8/8/8/4Q3/4K3/7k/8 w - - 0 1", 3),
+        ] {
+            let mut chess_board = ChessBoard::new_from_fen(fen).unwrap();
+            let MateSearchResult::Mate(mating_line) =
+                find_mate(&chess_board, max_moves, &AtomicBool::new(false))
+            else {
+                panic!("Expected a mate in {fen}");
+            };
+
+            /* Every position of the attacker in the line is mated as fast as possible. */
+            for (i, chess_move) in mating_line.iter().enumerate() {
+                if i % 2 == 0 {
+                    let remaining_line = &mating_line[i..];
+                    let shortest_mate = (1..=mate_length(remaining_line)).find(|moves| {
+                        matches!(
+                            find_mate(&chess_board, *moves, &AtomicBool::new(false)),
+                            MateSearchResult::Mate(_)
+                        )
+                    });
+                    assert_eq!(shortest_mate, Some(mate_length(remaining_line)), "{fen}");
+                }
+
+                chess_board.make_move_on_board(chess_move);
+                chess_board.update_meta_data(chess_move);
+            }
+        }
+    }
+
+    #[test]
+    fn test_proves_that_no_mate_exists() {
+        let chess_board = ChessBoard::new_from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+
+        assert_eq!(
+            find_mate(&chess_board, 2, &AtomicBool::new(false)),
+            MateSearchResult::NoMate
+        );
+    }
+}
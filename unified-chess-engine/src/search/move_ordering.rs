@@ -0,0 +1,433 @@
+/* This module orders moves for the alpha-beta search. The `MovePicker` hands out moves in stages,
+ * starting with the hash move, followed by the captures ordered by MVV-LVA, the killer moves, the
+ * counter move and lastly the quiet moves ordered by the history heuristic. The pseudo legal
+ * moves are generated once, and a move is only checked for legality when it is handed out, so
+ * the moves after a cutoff are never checked. */
+use crate::array_engine::{ChessBoard, Color, Move, PieceType, Position, COL_SIZE};
+use crate::search::evaluation::piece_value;
+use crate::search::MAX_PLY;
+
+const SQUARE_COUNT: usize = 64;
+const MAX_HISTORY_SCORE: i32 = 16384;
+
+/** The heuristics are learned during a search and are shared by all nodes of the search. */
+pub struct MoveOrderingHeuristics {
+    killer_moves: Vec<[Option<Move>; 2]>,
+    /* Indexed by side to move, start square and end square. */
+    history: Vec<[[i32; SQUARE_COUNT]; SQUARE_COUNT]>,
+    /* Indexed by the piece and end square of the previous move. */
+    counter_moves: Vec<[Option<Move>; SQUARE_COUNT]>,
+}
+
+impl Default for MoveOrderingHeuristics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MoveOrderingHeuristics {
+    pub fn new() -> MoveOrderingHeuristics {
+        MoveOrderingHeuristics {
+            killer_moves: vec![[None; 2]; MAX_PLY],
+            history: vec![[[0; SQUARE_COUNT]; SQUARE_COUNT]; 2],
+            counter_moves: vec![[None; SQUARE_COUNT]; 12],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /** Killer moves are only valid for the ply they were found at, they are cleared between
+    searches while the history is kept and aged. */
+    pub fn prepare_new_search(&mut self) {
+        self.killer_moves.fill([None; 2]);
+
+        for color_history in self.history.iter_mut() {
+            for from_square in color_history.iter_mut() {
+                for score in from_square.iter_mut() {
+                    *score /= 2;
+                }
+            }
+        }
+    }
+
+    pub fn killer_moves(&self, ply: usize) -> [Option<Move>; 2] {
+        self.killer_moves.get(ply).copied().unwrap_or([None; 2])
+    }
+
+    pub fn store_killer_move(&mut self, ply: usize, killer_move: Move) {
+        let Some(killers) = self.killer_moves.get_mut(ply) else {
+            return;
+        };
+
+        if killers[0] != Some(killer_move) {
+            killers[1] = killers[0];
+            killers[0] = Some(killer_move);
+        }
+    }
+
+    pub fn history_score(&self, color: Color, chess_move: &Move) -> i32 {
+        self.history[color_index(color)][square_index(chess_move.start_pos)]
+            [square_index(chess_move.end_pos)]
+    }
+
+    /** Updates the history score of a quiet move, a positive bonus is given to the move that
+    caused a cutoff and a negative one to the quiet moves searched before it. The score is kept
+    within bounds by scaling the bonus with the distance to the maximum. */
+    pub fn update_history(&mut self, color: Color, chess_move: &Move, bonus: i32) {
+        let bonus = bonus.clamp(-MAX_HISTORY_SCORE, MAX_HISTORY_SCORE);
+        let score = &mut self.history[color_index(color)][square_index(chess_move.start_pos)]
+            [square_index(chess_move.end_pos)];
+
+        *score += bonus - *score * bonus.abs() / MAX_HISTORY_SCORE;
+    }
+
+    pub fn counter_move(&self, color: Color, previous_move: Option<Move>) -> Option<Move> {
+        let previous_move = previous_move?;
+
+        self.counter_moves[piece_index(color.opposite(), &previous_move)]
+            [square_index(previous_move.end_pos)]
+    }
+
+    pub fn store_counter_move(
+        &mut self,
+        color: Color,
+        previous_move: Option<Move>,
+        counter_move: Move,
+    ) {
+        if let Some(previous_move) = previous_move {
+            self.counter_moves[piece_index(color.opposite(), &previous_move)]
+                [square_index(previous_move.end_pos)] = Some(counter_move);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    HashMove,
+    GenerateNoisyMoves,
+    NoisyMoves,
+    FirstKiller,
+    SecondKiller,
+    CounterMove,
+    GenerateQuietMoves,
+    QuietMoves,
+    Done,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ScoredMove {
+    chess_move: Move,
+    score: i32,
+}
+
+pub struct MovePicker {
+    stage: Stage,
+    hash_move: Option<Move>,
+    killer_moves: [Option<Move>; 2],
+    counter_move: Option<Move>,
+    moves: Vec<ScoredMove>,
+    /* The pseudo legal quiet moves, kept from the generation of the noisy moves. */
+    quiet_moves: Vec<Move>,
+    index: usize,
+    skip_quiet_moves: bool,
+}
+
+impl MovePicker {
+    pub fn new(
+        hash_move: Option<Move>,
+        killer_moves: [Option<Move>; 2],
+        counter_move: Option<Move>,
+    ) -> MovePicker {
+        MovePicker {
+            stage: Stage::HashMove,
+            hash_move,
+            killer_moves,
+            counter_move,
+            moves: Vec::new(),
+            quiet_moves: Vec::new(),
+            index: 0,
+            skip_quiet_moves: false,
+        }
+    }
+
+    /** A move picker for the quiescence search, which only hands out captures and promotions. */
+    pub fn new_noisy(hash_move: Option<Move>) -> MovePicker {
+        MovePicker {
+            stage: Stage::HashMove,
+            hash_move: hash_move.filter(|chess_move| chess_move.is_noisy()),
+            killer_moves: [None; 2],
+            counter_move: None,
+            moves: Vec::new(),
+            quiet_moves: Vec::new(),
+            index: 0,
+            skip_quiet_moves: true,
+        }
+    }
+
+    pub fn next_move(
+        &mut self,
+        chess_board: &ChessBoard,
+        heuristics: &MoveOrderingHeuristics,
+    ) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = Stage::GenerateNoisyMoves;
+
+                    if let Some(hash_move) = self.hash_move {
+                        if chess_board.is_legal_move(&hash_move) {
+                            return Some(hash_move);
+                        }
+                        self.hash_move = None;
+                    }
+                }
+
+                Stage::GenerateNoisyMoves => {
+                    let (noisy_moves, quiet_moves) =
+                        chess_board.pseudo_legal_noisy_and_quiet_moves();
+
+                    if !self.skip_quiet_moves {
+                        self.quiet_moves = quiet_moves;
+                    }
+                    self.moves = noisy_moves
+                        .into_iter()
+                        .filter(|chess_move| Some(*chess_move) != self.hash_move)
+                        .map(|chess_move| ScoredMove {
+                            chess_move,
+                            score: mvv_lva_score(&chess_move),
+                        })
+                        .collect();
+                    self.index = 0;
+                    self.stage = Stage::NoisyMoves;
+                }
+
+                Stage::NoisyMoves => {
+                    if let Some(chess_move) = self.pick_best_remaining_legal_move(chess_board) {
+                        return Some(chess_move);
+                    }
+
+                    self.stage = if self.skip_quiet_moves {
+                        Stage::Done
+                    } else {
+                        Stage::FirstKiller
+                    };
+                }
+
+                Stage::FirstKiller => {
+                    self.stage = Stage::SecondKiller;
+
+                    if let Some(killer_move) = self.killer_moves[0] {
+                        if self.is_valid_quiet_move(chess_board, &killer_move) {
+                            return Some(killer_move);
+                        }
+                    }
+                    self.killer_moves[0] = None;
+                }
+
+                Stage::SecondKiller => {
+                    self.stage = Stage::CounterMove;
+
+                    if let Some(killer_move) = self.killer_moves[1] {
+                        if self.killer_moves[0] != Some(killer_move)
+                            && self.is_valid_quiet_move(chess_board, &killer_move)
+                        {
+                            return Some(killer_move);
+                        }
+                    }
+                    self.killer_moves[1] = None;
+                }
+
+                Stage::CounterMove => {
+                    self.stage = Stage::GenerateQuietMoves;
+
+                    if let Some(counter_move) = self.counter_move {
+                        if !self.killer_moves.contains(&Some(counter_move))
+                            && self.is_valid_quiet_move(chess_board, &counter_move)
+                        {
+                            return Some(counter_move);
+                        }
+                    }
+                    self.counter_move = None;
+                }
+
+                Stage::GenerateQuietMoves => {
+                    let color = chess_board.side_to_move();
+
+                    self.moves = std::mem::take(&mut self.quiet_moves)
+                        .into_iter()
+                        .filter(|chess_move| !self.was_handed_out_early(chess_move))
+                        .map(|chess_move| ScoredMove {
+                            chess_move,
+                            score: heuristics.history_score(color, &chess_move),
+                        })
+                        .collect();
+                    self.index = 0;
+                    self.stage = Stage::QuietMoves;
+                }
+
+                Stage::QuietMoves => {
+                    if let Some(chess_move) = self.pick_best_remaining_legal_move(chess_board) {
+                        return Some(chess_move);
+                    }
+                    self.stage = Stage::Done;
+                }
+
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /** Selection sort step: swaps the best scored remaining move to the front, skipping the
+    illegal ones. Doing this lazily is cheaper than sorting and checking every move, since most
+    nodes only look at a few moves before a cutoff. */
+    fn pick_best_remaining_legal_move(&mut self, chess_board: &ChessBoard) -> Option<Move> {
+        while self.index < self.moves.len() {
+            let mut best_index = self.index;
+
+            for i in (self.index + 1)..self.moves.len() {
+                if self.moves[i].score > self.moves[best_index].score {
+                    best_index = i;
+                }
+            }
+
+            self.moves.swap(self.index, best_index);
+            self.index += 1;
+
+            let chess_move = self.moves[self.index - 1].chess_move;
+            if chess_board.keeps_king_safe(&chess_move) {
+                return Some(chess_move);
+            }
+        }
+
+        None
+    }
+
+    fn is_valid_quiet_move(&self, chess_board: &ChessBoard, chess_move: &Move) -> bool {
+        !chess_move.is_noisy()
+            && Some(*chess_move) != self.hash_move
+            && chess_board.is_legal_move(chess_move)
+    }
+
+    fn was_handed_out_early(&self, chess_move: &Move) -> bool {
+        Some(*chess_move) == self.hash_move
+            || self.killer_moves.contains(&Some(*chess_move))
+            || Some(*chess_move) == self.counter_move
+    }
+}
+
+/** Most valuable victim, least valuable attacker. Promotions are scored by the value gained from
+the promotion piece. */
+pub fn mvv_lva_score(chess_move: &Move) -> i32 {
+    let mut score: i32 = 0;
+
+    if let Some(captured_piece) = chess_move.meta_data.piece_to_capture() {
+        score += 10 * piece_value(captured_piece)
+            - piece_value(chess_move.meta_data.piece_to_move()) / 10;
+    }
+
+    if let Some(promotion_piece) = chess_move.meta_data.promotion_piece {
+        score += piece_value(promotion_piece);
+    }
+
+    score
+}
+
+fn color_index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+fn piece_index(color: Color, chess_move: &Move) -> usize {
+    let piece_type_index: usize = match chess_move.meta_data.piece_to_move() {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    };
+
+    color_index(color) * 6 + piece_type_index
+}
+
+fn square_index(position: Position) -> usize {
+    position.1 * COL_SIZE + position.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array_engine::perft::KIWIPETE_FEN_POSITION;
+
+    fn picked_moves(chess_board: &ChessBoard, mut picker: MovePicker) -> Vec<Move> {
+        let heuristics = MoveOrderingHeuristics::new();
+        let mut moves = Vec::new();
+
+        while let Some(chess_move) = picker.next_move(chess_board, &heuristics) {
+            moves.push(chess_move);
+        }
+        moves
+    }
+
+    #[test]
+    fn test_picker_hands_out_every_legal_move_once() {
+        let chess_board = ChessBoard::new_from_fen(KIWIPETE_FEN_POSITION).unwrap();
+        let legal_moves = chess_board.legal_moves();
+        let quiet_move = *legal_moves
+            .iter()
+            .find(|chess_move| !chess_move.is_noisy())
+            .unwrap();
+
+        let picker = MovePicker::new(Some(legal_moves[5]), [Some(quiet_move), None], None);
+        let moves = picked_moves(&chess_board, picker);
+
+        assert_eq!(moves.len(), legal_moves.len());
+        assert_eq!(moves[0], legal_moves[5]);
+        assert!(legal_moves
+            .iter()
+            .all(|chess_move| moves.contains(chess_move)));
+    }
+
+    #[test]
+    fn test_picker_skips_illegal_moves() {
+        /* The knight is pinned and the king is in check by the bishop. */
+        let chess_board = ChessBoard::new_from_fen("4r1k1/8/8/8/1b6/8/4N3/4K3 w - - 0 1").unwrap();
+        let moves = picked_moves(&chess_board, MovePicker::new(None, [None; 2], None));
+
+        assert_eq!(moves.len(), chess_board.legal_moves().len());
+        assert!(moves
+            .iter()
+            .all(|chess_move| chess_board.is_legal_move(chess_move)));
+    }
+
+    #[test]
+    fn test_captures_are_ordered_by_mvv_lva() {
+        let chess_board = ChessBoard::new_from_fen(KIWIPETE_FEN_POSITION).unwrap();
+        let moves = picked_moves(&chess_board, MovePicker::new_noisy(None));
+
+        let scores: Vec<i32> = moves.iter().map(mvv_lva_score).collect();
+
+        assert!(!moves.is_empty());
+        assert!(moves.iter().all(|chess_move| chess_move.is_noisy()));
+        assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn test_illegal_killer_is_skipped() {
+        let chess_board = ChessBoard::new();
+        let other_board = ChessBoard::new_from_fen(KIWIPETE_FEN_POSITION).unwrap();
+        let illegal_killer = other_board
+            .legal_moves()
+            .into_iter()
+            .find(|chess_move| !chess_move.is_noisy() && !chess_board.is_legal_move(chess_move))
+            .unwrap();
+
+        let picker = MovePicker::new(None, [Some(illegal_killer), None], None);
+
+        assert_eq!(picked_moves(&chess_board, picker).len(), 20);
+    }
+}
@@ -0,0 +1,234 @@
+/* This module runs test suites like WAC, ECM or STS, which are EPD files where every position has
+ * best moves (bm) to find or moves to avoid (am). Each position is searched with the same limits
+ * and a fresh transposition table, and the time to solution is the time of the iteration from
+ * which on the engine kept choosing a solving move. Comparing the solved positions of two builds
+ * shows changes in tactical strength much faster than playing matches. */
+use crate::array_engine::chess_errors::InvalidEpd;
+use crate::array_engine::Move;
+use crate::epd::Epd;
+use crate::search::{SearchLimits, Searcher};
+use std::fmt;
+use std::time::Duration;
+
+/** A position of the suite with its solving moves resolved against the position. */
+struct TestPosition<'a> {
+    epd: &'a Epd,
+    best_moves: Vec<Move>,
+    avoid_moves: Vec<Move>,
+}
+
+impl TestPosition<'_> {
+    fn is_solution(&self, chess_move: &Move) -> bool {
+        if self.best_moves.is_empty() {
+            !self.avoid_moves.contains(chess_move)
+        } else {
+            self.best_moves.contains(chess_move)
+        }
+    }
+
+    /** The expected moves as written in the suite, e.g. "bm Qg6 Qh5" or "am Bxh7". */
+    fn expected(&self) -> String {
+        let opcode = if self.best_moves.is_empty() {
+            "am"
+        } else {
+            "bm"
+        };
+        let moves = self.epd.operation(opcode).unwrap_or_default();
+        format!("{opcode} {}", moves.join(" "))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    /* The id of the position, or its number in the suite if it has none. */
+    pub id: String,
+    pub expected: String,
+    /* The move the engine chose, in SAN. */
+    pub found_move: Option<String>,
+    pub solved: bool,
+    /* When the engine started choosing a solution for good, only set for solved positions. */
+    pub solution_time: Option<Duration>,
+    pub solution_depth: Option<u32>,
+    pub nodes: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TestSuiteReport {
+    pub results: Vec<TestResult>,
+}
+
+impl TestSuiteReport {
+    pub fn solved(&self) -> usize {
+        self.results.iter().filter(|result| result.solved).count()
+    }
+
+    /** The average time to solution over the solved positions. */
+    pub fn average_solution_time(&self) -> Option<Duration> {
+        let times: Vec<Duration> = self
+            .results
+            .iter()
+            .filter_map(|result| result.solution_time)
+            .collect();
+
+        (!times.is_empty()).then(|| times.iter().sum::<Duration>() / times.len() as u32)
+    }
+}
+
+/** Searches every position of the suite with the limits. Each result is passed to `on_result`
+as soon as it is known, so progress can be shown during long runs. Fails before searching if a
+position has no bm or am operation, or one of their moves is not legal. */
+pub fn run_test_suite<F: FnMut(&TestResult)>(
+    searcher: &mut Searcher,
+    suite: &[Epd],
+    limits: SearchLimits,
+    mut on_result: F,
+) -> Result<TestSuiteReport, InvalidEpd> {
+    let mut positions: Vec<TestPosition> = Vec::with_capacity(suite.len());
+
+    for (i, epd) in suite.iter().enumerate() {
+        let position = TestPosition {
+            epd,
+            best_moves: epd.best_moves()?,
+            avoid_moves: epd.avoid_moves()?,
+        };
+
+        if position.best_moves.is_empty() && position.avoid_moves.is_empty() {
+            return Err(InvalidEpd {
+                reason: format!("position {} has no bm or am operation", i + 1),
+            });
+        }
+
+        positions.push(position);
+    }
+
+    let mut report = TestSuiteReport::default();
+
+    for (i, position) in positions.iter().enumerate() {
+        searcher.clear();
+        searcher.set_position_history(Vec::new());
+
+        let mut solution_found: Option<(Duration, u32)> = None;
+        let search_result = searcher.search(&position.epd.chess_board, limits, |info| {
+            if info.multi_pv != 1 {
+                return;
+            }
+
+            match info.principal_variation.first() {
+                Some(chess_move) if position.is_solution(chess_move) => {
+                    solution_found.get_or_insert((info.elapsed, info.depth));
+                }
+                _ => solution_found = None,
+            }
+        });
+
+        let chess_board = &position.epd.chess_board;
+        let solved = search_result
+            .best_move
+            .is_some_and(|chess_move| position.is_solution(&chess_move));
+        let solution_found = solution_found.filter(|_| solved);
+
+        let result = TestResult {
+            id: position
+                .epd
+                .id()
+                .map_or_else(|| (i + 1).to_string(), str::to_string),
+            expected: position.expected(),
+            found_move: search_result
+                .best_move
+                .map(|chess_move| chess_board.move_to_san(&chess_move)),
+            solved,
+            solution_time: solution_found.map(|(time, _)| time),
+            solution_depth: solution_found.map(|(_, depth)| depth),
+            nodes: search_result.nodes,
+        };
+
+        on_result(&result);
+        report.results.push(result);
+    }
+
+    Ok(report)
+}
+
+impl fmt::Display for TestResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let time = self.solution_time.map_or("-".to_string(), |time| {
+            format!("{:.2}s", time.as_secs_f64())
+        });
+        let depth = self
+            .solution_depth
+            .map_or("-".to_string(), |depth| depth.to_string());
+
+        write!(
+            f,
+            "{:<20} {:<20} {:<8} {:<8} {:>8} {:>5} {:>12}",
+            self.id,
+            self.expected,
+            self.found_move.as_deref().unwrap_or("-"),
+            if self.solved { "solved" } else { "failed" },
+            time,
+            depth,
+            self.nodes
+        )
+    }
+}
+
+impl fmt::Display for TestSuiteReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<20} {:<20} {:<8} {:<8} {:>8} {:>5} {:>12}",
+            "id", "expected", "found", "result", "time", "depth", "nodes"
+        )?;
+
+        for result in &self.results {
+            writeln!(f, "{result}")?;
+        }
+
+        write!(f, "Solved {} of {}", self.solved(), self.results.len())?;
+
+        if let Some(average) = self.average_solution_time() {
+            write!(
+                f,
+                ", average time to solution {:.2}s",
+                average.as_secs_f64()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd::parse_epd;
+
+    #[test]
+    fn test_runs_suite() {
+        let suite = parse_epd(
+            "6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8; id \"back rank\";\n\
+             4k3/8/8/8/8/8/3q4/4K3 w - - am Kf1;\n",
+        )
+        .unwrap();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+
+        let mut reported = 0;
+        let report =
+            run_test_suite(&mut Searcher::new(1), &suite, limits, |_| reported += 1).unwrap();
+
+        assert_eq!(reported, 2);
+        assert_eq!(report.results[0].id, "back rank");
+        assert_eq!(report.results[0].found_move.as_deref(), Some("Ra8#"));
+        assert_eq!(report.results[0].solution_depth, Some(1));
+        /* Taking the queen with Kxd2 is not the move to avoid. */
+        assert_eq!(report.results[1].id, "2");
+        assert_eq!(report.results[1].expected, "am Kf1");
+        assert_eq!(report.solved(), 2);
+
+        let missing = parse_epd("4k3/8/8/8/8/8/8/4K3 w - - id \"none\";").unwrap();
+        assert!(run_test_suite(&mut Searcher::new(1), &missing, limits, |_| {}).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+/* This module decides how much of the remaining clock time a search may use. The search gets a
+ * soft limit, after which no new iteration is started, and a hard limit, after which the search
+ * is stopped in the middle of an iteration. A ponder search runs without limits until the
+ * opponent plays the expected move, from then on the clock of the engine is running. */
+use crate::array_engine::Color;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/* Used when the number of moves until the next time control is unknown. */
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+/* Time kept back to cover the communication with the GUI. */
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+
+/** The clock state as sent by the UCI go command. */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeControl {
+    pub white_time: Option<Duration>,
+    pub black_time: Option<Duration>,
+    pub white_increment: Duration,
+    pub black_increment: Duration,
+    pub moves_to_go: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeAllocation {
+    pub soft_limit: Duration,
+    pub hard_limit: Duration,
+}
+
+impl TimeControl {
+    /** Returns None when the clock of the given side is not known. */
+    pub fn allocate(&self, side_to_move: Color) -> Option<TimeAllocation> {
+        let (remaining_time, increment) = match side_to_move {
+            Color::White => (self.white_time?, self.white_increment),
+            Color::Black => (self.black_time?, self.black_increment),
+        };
+
+        let usable_time = remaining_time.saturating_sub(MOVE_OVERHEAD);
+        let moves_to_go = self.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+
+        let soft_limit = (usable_time / moves_to_go + increment * 3 / 4).min(usable_time / 2);
+        let hard_limit = (soft_limit * 4).min(usable_time * 3 / 4);
+
+        Some(TimeAllocation {
+            soft_limit,
+            hard_limit: hard_limit.max(soft_limit),
+        })
+    }
+}
+
+/** Tells a running ponder search that the opponent played the expected move, which turns the
+search into a normal timed search without restarting it. */
+#[derive(Debug, Default)]
+pub struct PonderSignal {
+    ponderhit_time: Mutex<Option<Instant>>,
+}
+
+impl PonderSignal {
+    pub fn ponderhit(&self) {
+        self.lock().get_or_insert_with(Instant::now);
+    }
+
+    /** Returns the moment the clock of the engine started running, or None while pondering. */
+    pub fn ponderhit_time(&self) -> Option<Instant> {
+        *self.lock()
+    }
+
+    pub fn reset(&self) {
+        *self.lock() = None;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Instant>> {
+        self.ponderhit_time
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocation_stays_within_remaining_time() {
+        let time_control = TimeControl {
+            white_time: Some(Duration::from_secs(60)),
+            black_time: Some(Duration::from_millis(100)),
+            white_increment: Duration::from_secs(1),
+            ..TimeControl::default()
+        };
+
+        let white = time_control.allocate(Color::White).unwrap();
+        let black = time_control.allocate(Color::Black).unwrap();
+
+        assert!(white.soft_limit > Duration::from_secs(1));
+        assert!(white.hard_limit < Duration::from_secs(45));
+        assert!(black.hard_limit < Duration::from_millis(100));
+        assert!(TimeControl::default().allocate(Color::White).is_none());
+    }
+}
@@ -0,0 +1,246 @@
+/* This module implements the transposition table, a hash table that stores the results of
+ * previously searched positions indexed by their Zobrist hash. The table is shared between the
+ * search threads without locking: every slot stores the key xor'ed with the data, so an entry
+ * that was torn by two threads writing at the same time is detected as a key mismatch. */
+use crate::array_engine::{ChessBoard, Move, PieceType, Position, COL_SIZE};
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub const DEFAULT_TABLE_SIZE_MB: usize = 16;
+
+/** Describes how the stored score relates to the real score of the position. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound {
+    Exact,
+    /* The score failed high, the real score is at least the stored score. */
+    Lower,
+    /* The score failed low, the real score is at most the stored score. */
+    Upper,
+}
+
+/** A move stored in the table, the full move is restored by looking it up on the board. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TableMove {
+    pub start_pos: Position,
+    pub end_pos: Position,
+    pub promotion_piece: Option<PieceType>,
+}
+
+impl TableMove {
+    pub fn from_move(chess_move: &Move) -> TableMove {
+        TableMove {
+            start_pos: chess_move.start_pos,
+            end_pos: chess_move.end_pos,
+            promotion_piece: chess_move.meta_data.promotion_piece,
+        }
+    }
+
+    /** Returns the legal move on the board, or None if the move is not legal, which can happen
+    on hash collisions. */
+    pub fn to_move(&self, chess_board: &ChessBoard) -> Option<Move> {
+        chess_board.find_legal_move(self.start_pos, self.end_pos, self.promotion_piece)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TableEntry {
+    pub key: u64,
+    pub best_move: Option<TableMove>,
+    pub score: i32,
+    pub depth: u32,
+    pub bound: Bound,
+}
+
+struct TableSlot {
+    checked_key: AtomicU64,
+    data: AtomicU64,
+}
+
+pub struct TranspositionTable {
+    slots: Vec<TableSlot>,
+}
+
+impl TranspositionTable {
+    pub fn new(size_in_mb: usize) -> TranspositionTable {
+        let slot_count: usize = (size_in_mb.max(1) * 1024 * 1024) / size_of::<TableSlot>();
+
+        TranspositionTable {
+            slots: (0..slot_count.max(1))
+                .map(|_| TableSlot {
+                    checked_key: AtomicU64::new(0),
+                    data: AtomicU64::new(0),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TableEntry> {
+        let slot = &self.slots[self.index(key)];
+        let data = slot.data.load(Ordering::Relaxed);
+
+        if data == 0 || slot.checked_key.load(Ordering::Relaxed) ^ data != key {
+            return None;
+        }
+
+        Some(unpack_entry(key, data))
+    }
+
+    /** Stores an entry, an entry of the same position is only replaced by a shallower search if
+    the new result is exact. */
+    pub fn store(&self, entry: TableEntry) {
+        let slot = &self.slots[self.index(entry.key)];
+
+        let replace: bool = match self.probe(entry.key) {
+            None => true,
+            Some(existing) => entry.depth >= existing.depth || entry.bound == Bound::Exact,
+        };
+
+        if replace {
+            let data = pack_entry(&entry);
+
+            slot.checked_key.store(entry.key ^ data, Ordering::Relaxed);
+            slot.data.store(data, Ordering::Relaxed);
+        }
+    }
+
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.checked_key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key % self.slots.len() as u64) as usize
+    }
+}
+
+/* Layout of the packed data, from the least significant bit:
+ * 6 bits start square, 6 bits end square, 3 bits promotion piece, 1 bit move flag,
+ * 16 bits score, 8 bits depth, 2 bits bound and 1 bit that marks the slot as used. */
+const MOVE_FLAG: u64 = 1 << 15;
+const USED_FLAG: u64 = 1 << 42;
+
+fn pack_entry(entry: &TableEntry) -> u64 {
+    let packed_move: u64 = match entry.best_move {
+        Some(table_move) => {
+            let promotion: u64 = match table_move.promotion_piece {
+                None => 0,
+                Some(PieceType::Knight) => 1,
+                Some(PieceType::Bishop) => 2,
+                Some(PieceType::Rook) => 3,
+                Some(PieceType::Queen) => 4,
+                Some(_) => 0,
+            };
+
+            square_index(table_move.start_pos)
+                | (square_index(table_move.end_pos) << 6)
+                | (promotion << 12)
+                | MOVE_FLAG
+        }
+        None => 0,
+    };
+
+    let bound: u64 = match entry.bound {
+        Bound::Exact => 0,
+        Bound::Lower => 1,
+        Bound::Upper => 2,
+    };
+
+    packed_move
+        | ((entry.score as i16 as u16 as u64) << 16)
+        | ((entry.depth.min(u8::MAX as u32) as u64) << 32)
+        | (bound << 40)
+        | USED_FLAG
+}
+
+fn unpack_entry(key: u64, data: u64) -> TableEntry {
+    let best_move = if data & MOVE_FLAG != 0 {
+        let promotion_piece = match (data >> 12) & 0b111 {
+            1 => Some(PieceType::Knight),
+            2 => Some(PieceType::Bishop),
+            3 => Some(PieceType::Rook),
+            4 => Some(PieceType::Queen),
+            _ => None,
+        };
+
+        Some(TableMove {
+            start_pos: square_position(data & 0b11_1111),
+            end_pos: square_position((data >> 6) & 0b11_1111),
+            promotion_piece,
+        })
+    } else {
+        None
+    };
+
+    let bound = match (data >> 40) & 0b11 {
+        0 => Bound::Exact,
+        1 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+
+    TableEntry {
+        key,
+        best_move,
+        score: ((data >> 16) & 0xffff) as u16 as i16 as i32,
+        depth: ((data >> 32) & 0xff) as u32,
+        bound,
+    }
+}
+
+fn square_index(position: Position) -> u64 {
+    (position.1 * COL_SIZE + position.0) as u64
+}
+
+fn square_position(square: u64) -> Position {
+    (square as usize % COL_SIZE, square as usize / COL_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_probe() {
+        let table = TranspositionTable::new(1);
+        let table_move = TableMove {
+            start_pos: (6, 6),
+            end_pos: (6, 7),
+            promotion_piece: Some(PieceType::Knight),
+        };
+
+        table.store(TableEntry {
+            key: 42,
+            best_move: Some(table_move),
+            score: -17,
+            depth: 3,
+            bound: Bound::Lower,
+        });
+
+        let entry = table.probe(42).unwrap();
+
+        assert_eq!(entry.best_move, Some(table_move));
+        assert_eq!(entry.score, -17);
+        assert_eq!(entry.depth, 3);
+        assert_eq!(entry.bound, Bound::Lower);
+        assert!(table.probe(43).is_none());
+
+        table.clear();
+        assert!(table.probe(42).is_none());
+    }
+
+    #[test]
+    fn test_table_move_is_resolved_on_the_board() {
+        let chess_board = ChessBoard::new();
+        let table_move = TableMove {
+            start_pos: (4, 1),
+            end_pos: (4, 3),
+            promotion_piece: None,
+        };
+
+        let chess_move = table_move.to_move(&chess_board).unwrap();
+
+        assert_eq!(chess_move.move_to_string(), "e2e4");
+        assert_eq!(TableMove::from_move(&chess_move), table_move);
+    }
+}
@@ -0,0 +1,566 @@
+/* This module probes Syzygy endgame tablebases. WDL tables (.rtbw) tell whether a position with
+ * few pieces is won, drawn or lost, DTZ tables (.rtbz) tell how many plies it takes until the
+ * next capture or pawn move of a perfect game, which is what matters for the fifty move rule.
+ * Tables are read from disk the first time they are probed. Positions with castling rights are
+ * not stored in the tables, positions with an en passant square are handled by searching the
+ * captures before probing. */
+mod table;
+
+use crate::array_engine::chess_errors::InvalidTablebase;
+use crate::array_engine::{ChessBoard, Color, Move, PieceType};
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Neg;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use table::{Table, TableKind, TablePiece, TableValue, BLACK, MAX_PIECES};
+
+/* Ranks of root moves, winning moves are ranked near MAX_DTZ and losing moves near -MAX_DTZ.
+Moves ranked above MAX_DTZ / 2 - 100 win before the fifty move rule can draw the game. */
+pub const MAX_DTZ: i32 = 1 << 18;
+
+/** The result of a position with perfect play. Cursed wins and blessed losses are wins and
+losses that are drawn by the fifty move rule. */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Option<Wdl> {
+        match value {
+            -2 => Some(Wdl::Loss),
+            -1 => Some(Wdl::BlessedLoss),
+            0 => Some(Wdl::Draw),
+            1 => Some(Wdl::CursedWin),
+            2 => Some(Wdl::Win),
+            _ => None,
+        }
+    }
+
+    /** Returns the DTZ of a position right before a capture or pawn move with the result. */
+    fn dtz_before_zeroing(self) -> i32 {
+        match self {
+            Wdl::Loss => -1,
+            Wdl::BlessedLoss => -101,
+            Wdl::Draw => 0,
+            Wdl::CursedWin => 101,
+            Wdl::Win => 1,
+        }
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        Wdl::from_value(-(self as i32)).expect("The negated result is in range")
+    }
+}
+
+/** A legal root move ranked by the tablebases. Moves with a higher rank are better, moves that
+win or lose with the same margin to the fifty move rule have the same rank. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TablebaseMove {
+    pub chess_move: Move,
+    /* The distance to zeroing in plies from the root position, positive if the move wins. */
+    pub dtz: i32,
+    pub rank: i32,
+}
+
+/** Whether a probe found the best move among the captures and pawn moves, in which case the
+DTZ table does not have to be probed. */
+#[derive(PartialEq)]
+enum ProbeState {
+    Ok,
+    ZeroingBestMove,
+}
+
+/** A table file that is loaded the first time it is probed. */
+struct TableSlot {
+    path: PathBuf,
+    table: OnceLock<Option<Table>>,
+}
+
+impl TableSlot {
+    fn table(&self, kind: TableKind, name: &str) -> Option<&Table> {
+        self.table
+            .get_or_init(|| Table::parse(kind, name, fs::read(&self.path).ok()?))
+            .as_ref()
+    }
+}
+
+#[derive(Default)]
+pub struct Tablebases {
+    wdl_tables: HashMap<String, TableSlot>,
+    dtz_tables: HashMap<String, TableSlot>,
+    max_pieces: usize,
+}
+
+impl Tablebases {
+    /** Finds the tables in the directories of the path, which are separated by ':' or ';' like
+    the SyzygyPath option of other engines. */
+    pub fn new(path: &str) -> Result<Tablebases, InvalidTablebase> {
+        let mut tablebases = Tablebases::default();
+
+        for directory in path
+            .split([':', ';'])
+            .filter(|directory| !directory.is_empty())
+        {
+            tablebases.add_directory(Path::new(directory))?;
+        }
+
+        Ok(tablebases)
+    }
+
+    fn add_directory(&mut self, directory: &Path) -> Result<(), InvalidTablebase> {
+        let entries = fs::read_dir(directory).map_err(|e| InvalidTablebase {
+            reason: format!("could not read {}: {e}", directory.display()),
+        })?;
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let (Some(name), Some(extension)) = (
+                path.file_stem().and_then(|name| name.to_str()),
+                path.extension().and_then(|extension| extension.to_str()),
+            ) else {
+                continue;
+            };
+
+            let piece_count = name.chars().filter(|c| *c != 'v').count();
+            if !is_table_name(name) || piece_count > MAX_PIECES {
+                continue;
+            }
+
+            let tables = match extension {
+                "rtbw" => &mut self.wdl_tables,
+                "rtbz" => &mut self.dtz_tables,
+                _ => continue,
+            };
+
+            if extension == "rtbw" {
+                self.max_pieces = self.max_pieces.max(piece_count);
+            }
+
+            tables.insert(
+                name.to_string(),
+                TableSlot {
+                    path: path.clone(),
+                    table: OnceLock::new(),
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    /** The largest number of pieces, kings included, of the WDL tables found. */
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    pub fn table_count(&self) -> usize {
+        self.wdl_tables.len() + self.dtz_tables.len()
+    }
+
+    /** Returns true if the tables can have the position, which needs few enough pieces and no
+    castling rights. */
+    pub fn can_probe(&self, chess_board: &ChessBoard) -> bool {
+        !chess_board.castling_ability().contains(&true)
+            && pieces_of(chess_board).len() <= self.max_pieces
+    }
+
+    /** Returns the result of the position for the side to move, or None if the table of the
+    position is missing. The result assumes the half move clock is zero. */
+    pub fn probe_wdl(&self, chess_board: &ChessBoard) -> Option<Wdl> {
+        if !self.can_probe(chess_board) {
+            return None;
+        }
+
+        self.search(chess_board, false).map(|(wdl, _)| wdl)
+    }
+
+    /** Returns the distance to the next capture or pawn move in plies, positive if the side to
+    move wins and negative if it loses. Draws have a DTZ of 0, and cursed wins and blessed losses
+    have a DTZ of more than 100 plies. The DTZ of wins and losses can be off by one ply. */
+    pub fn probe_dtz(&self, chess_board: &ChessBoard) -> Option<i32> {
+        if !self.can_probe(chess_board) {
+            return None;
+        }
+
+        self.dtz(chess_board)
+    }
+
+    /** Ranks the legal moves of the root position. Moves that win without allowing a draw by
+    the fifty move rule are ranked by their DTZ, so the engine makes progress. `position_history`
+    holds the hashes of the earlier positions of the game, used to detect repetitions. */
+    pub fn probe_root(
+        &self,
+        chess_board: &ChessBoard,
+        position_history: &[u64],
+    ) -> Option<Vec<TablebaseMove>> {
+        if !self.can_probe(chess_board) {
+            return None;
+        }
+
+        let half_move_clock = chess_board.half_move_clock() as i32;
+        let reversible_history: Vec<u64> = position_history
+            .iter()
+            .rev()
+            .take(chess_board.half_move_clock() as usize)
+            .copied()
+            .collect();
+        let repeated = reversible_history.contains(&chess_board.zobrist_hash());
+        let mut ranked_moves: Vec<TablebaseMove> = Vec::new();
+
+        for chess_move in chess_board.legal_moves() {
+            let child_board = board_after_move(chess_board, &chess_move);
+
+            let mut dtz = if child_board.half_move_clock() == 0 {
+                (-self.search(&child_board, false)?.0).dtz_before_zeroing()
+            } else if reversible_history.contains(&child_board.zobrist_hash())
+                || child_board.half_move_clock() >= 100
+            {
+                0
+            } else {
+                let dtz = -self.dtz(&child_board)?;
+                dtz + dtz.signum()
+            };
+
+            /* A mating move has a DTZ of one ply. */
+            if dtz == 2 && child_board.king_is_in_check() && child_board.legal_moves().is_empty() {
+                dtz = 1;
+            }
+
+            let rank = if dtz > 0 {
+                if dtz + half_move_clock <= 99 && !repeated {
+                    MAX_DTZ - dtz
+                } else {
+                    MAX_DTZ / 2 - (dtz + half_move_clock)
+                }
+            } else if dtz < 0 {
+                if -dtz * 2 + half_move_clock < 100 {
+                    -MAX_DTZ - dtz
+                } else {
+                    -MAX_DTZ / 2 + (-dtz + half_move_clock)
+                }
+            } else {
+                0
+            };
+
+            ranked_moves.push(TablebaseMove {
+                chess_move,
+                dtz,
+                rank,
+            });
+        }
+
+        ranked_moves.sort_by_key(|ranked_move| std::cmp::Reverse(ranked_move.rank));
+        Some(ranked_moves)
+    }
+
+    /** Searches the captures, and the pawn moves if `check_zeroing_moves` is set, before
+    probing the WDL table. The tables store wrong values for positions with an en passant capture
+    and for positions where a capture is the only good move. */
+    fn search(
+        &self,
+        chess_board: &ChessBoard,
+        check_zeroing_moves: bool,
+    ) -> Option<(Wdl, ProbeState)> {
+        let legal_moves = chess_board.legal_moves();
+        let mut best = Wdl::Loss;
+        let mut searched_moves = 0;
+
+        for chess_move in &legal_moves {
+            let is_capture = chess_move.meta_data.piece_to_capture().is_some()
+                || chess_move.meta_data.is_en_passant_move();
+            let is_pawn_move = chess_move.meta_data.piece_to_move() == PieceType::Pawn;
+
+            if !(is_capture || check_zeroing_moves && is_pawn_move) {
+                continue;
+            }
+
+            searched_moves += 1;
+            let wdl = -self
+                .search(&board_after_move(chess_board, chess_move), false)?
+                .0;
+
+            if wdl > best {
+                best = wdl;
+
+                if wdl == Wdl::Win {
+                    return Some((wdl, ProbeState::ZeroingBestMove));
+                }
+            }
+        }
+
+        let no_more_moves = searched_moves > 0 && searched_moves == legal_moves.len();
+        let wdl = if no_more_moves {
+            best
+        } else {
+            match self.probe_table(chess_board, TableKind::Wdl, Wdl::Draw)? {
+                TableValue::Value(value) => Wdl::from_value(value)?,
+                TableValue::ChangeSideToMove => return None,
+            }
+        };
+
+        /* The DTZ table stores a value that does not matter when a capture wins. */
+        if best >= wdl {
+            let state = if best > Wdl::Draw || no_more_moves {
+                ProbeState::ZeroingBestMove
+            } else {
+                ProbeState::Ok
+            };
+            return Some((best, state));
+        }
+
+        Some((wdl, ProbeState::Ok))
+    }
+
+    fn dtz(&self, chess_board: &ChessBoard) -> Option<i32> {
+        let (wdl, state) = self.search(chess_board, true)?;
+
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+
+        if state == ProbeState::ZeroingBestMove {
+            return Some(wdl.dtz_before_zeroing());
+        }
+
+        match self.probe_table(chess_board, TableKind::Dtz, wdl)? {
+            TableValue::Value(dtz) => {
+                let cursed = (wdl == Wdl::BlessedLoss || wdl == Wdl::CursedWin) as i32;
+                Some((dtz + 100 * cursed) * (wdl as i32).signum())
+            }
+            TableValue::ChangeSideToMove => self.dtz_from_children(chess_board, wdl),
+        }
+    }
+
+    /** The DTZ table only stores the other side to move, so the DTZ is found with a search of
+    one ply that looks for the move with the lowest DTZ of the right sign. */
+    fn dtz_from_children(&self, chess_board: &ChessBoard, wdl: Wdl) -> Option<i32> {
+        let mut min_dtz = i32::MAX;
+
+        for chess_move in chess_board.legal_moves() {
+            let is_zeroing = chess_move.meta_data.piece_to_capture().is_some()
+                || chess_move.meta_data.is_en_passant_move()
+                || chess_move.meta_data.piece_to_move() == PieceType::Pawn;
+            let child_board = board_after_move(chess_board, &chess_move);
+
+            /* For zeroing moves the DTZ before the move is needed, while the search of the
+            position after the move gives its result. */
+            let mut dtz = if is_zeroing {
+                -(-self.search(&child_board, false)?.0).dtz_before_zeroing()
+            } else {
+                -self.dtz(&child_board)?
+            };
+
+            if dtz == 1 && child_board.king_is_in_check() && child_board.legal_moves().is_empty() {
+                min_dtz = 1;
+            }
+
+            if !is_zeroing {
+                dtz += dtz.signum();
+            }
+
+            if dtz < min_dtz && dtz.signum() == (wdl as i32).signum() {
+                min_dtz = dtz;
+            }
+        }
+
+        /* Without legal moves the position is a mate. */
+        Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+    }
+
+    fn probe_table(
+        &self,
+        chess_board: &ChessBoard,
+        kind: TableKind,
+        wdl: Wdl,
+    ) -> Option<TableValue> {
+        let pieces = pieces_of(chess_board);
+
+        /* KvK is a draw and has no table. */
+        if pieces.len() == 2 {
+            return Some(TableValue::Value(0));
+        }
+
+        let material = |color: u8| -> String {
+            let mut codes: Vec<u8> = pieces
+                .iter()
+                .filter(|piece| piece.code & BLACK == color)
+                .map(|piece| piece.code & !BLACK)
+                .collect();
+            codes.sort_by_key(|code| std::cmp::Reverse(*code));
+            codes
+                .into_iter()
+                .map(|code| b"?PNBRQK"[code as usize] as char)
+                .collect()
+        };
+        let (white, black) = (material(0), material(BLACK));
+
+        let tables = match kind {
+            TableKind::Wdl => &self.wdl_tables,
+            TableKind::Dtz => &self.dtz_tables,
+        };
+
+        /* Tables are named with the stronger side first. */
+        let (name, black_stronger) = if tables.contains_key(&format!("{white}v{black}")) {
+            (format!("{white}v{black}"), false)
+        } else {
+            (format!("{black}v{white}"), true)
+        };
+
+        let table = tables.get(&name)?.table(kind, &name)?;
+        let white_to_move = chess_board.side_to_move() == Color::White;
+
+        table.probe(&pieces, white_to_move, black_stronger, wdl)
+    }
+}
+
+/** Returns the pieces of the board with the piece codes of the tables. */
+fn pieces_of(chess_board: &ChessBoard) -> Vec<TablePiece> {
+    let mut pieces: Vec<TablePiece> = Vec::with_capacity(MAX_PIECES);
+
+    for square in 0..64 {
+        if let Some(piece) = chess_board.piece_at((square % 8, square / 8)) {
+            let code = match piece.piece_type() {
+                PieceType::Pawn => 1,
+                PieceType::Knight => 2,
+                PieceType::Bishop => 3,
+                PieceType::Rook => 4,
+                PieceType::Queen => 5,
+                PieceType::King => 6,
+            };
+
+            pieces.push(TablePiece {
+                code: if piece.color() == Color::Black {
+                    code | BLACK
+                } else {
+                    code
+                },
+                square,
+            });
+        }
+    }
+
+    pieces
+}
+
+fn board_after_move(chess_board: &ChessBoard, chess_move: &Move) -> ChessBoard {
+    let mut child_board = *chess_board;
+    child_board.make_move_on_board(chess_move);
+    child_board.update_meta_data(chess_move);
+    child_board
+}
+
+/** Returns true for names like "KRPvKR", with both kings at the start of their side. */
+fn is_table_name(name: &str) -> bool {
+    match name.split_once('v') {
+        Some((white, black)) => [white, black]
+            .iter()
+            .all(|side| side.starts_with('K') && side[1..].chars().all(|c| "QRBNP".contains(c))),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kings_only_is_a_draw() {
+        let tablebases = Tablebases {
+            max_pieces: 3,
+            ..Tablebases::default()
+        };
+        let chess_board = ChessBoard::new_from_fen("8/8/4k3/8/8/3K4/8/8 w - - 0 1").unwrap();
+
+        assert_eq!(tablebases.probe_wdl(&chess_board), Some(Wdl::Draw));
+        assert_eq!(tablebases.probe_dtz(&chess_board), Some(0));
+    }
+
+    #[test]
+    fn test_missing_tables_and_castling_rights() {
+        let tablebases = Tablebases {
+            max_pieces: 5,
+            ..Tablebases::default()
+        };
+        let rook_ending = ChessBoard::new_from_fen("8/8/4k3/8/8/3K4/8/R7 w - - 0 1").unwrap();
+        let castling = ChessBoard::new_from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+
+        assert_eq!(tablebases.probe_wdl(&rook_ending), None);
+        assert!(!tablebases.can_probe(&castling));
+        assert!(is_table_name("KRPvKR"));
+        assert!(!is_table_name("KRvR"));
+        assert!(Tablebases::new("/nonexistent/syzygy").is_err());
+    }
+
+    /** The tests with real tables need SYZYGY_PATH to point to the 3 and 4 piece tables, which
+    are too large to keep in the repository, so they are ignored by default. Run them with
+    `SYZYGY_PATH=/path/to/syzygy cargo test -- --ignored`. */
+    fn real_tablebases() -> Tablebases {
+        let path = std::env::var("SYZYGY_PATH").expect("SYZYGY_PATH is set");
+
+        Tablebases::new(&path).expect("SYZYGY_PATH is a readable directory")
+    }
+
+    fn probe_root_moves(tablebases: &Tablebases, fen: &str) -> Vec<(String, i32)> {
+        let chess_board = ChessBoard::new_from_fen(fen).unwrap();
+
+        tablebases
+            .probe_root(&chess_board, &[])
+            .unwrap()
+            .iter()
+            .map(|ranked_move| (ranked_move.chess_move.move_to_string(), ranked_move.dtz))
+            .collect()
+    }
+
+    #[test]
+    #[ignore = "needs SYZYGY_PATH"]
+    fn test_real_queen_ending() {
+        let tablebases = real_tablebases();
+        let mate_in_one = ChessBoard::new_from_fen("k7/8/1K6/8/8/8/7Q/8 w - - 0 1").unwrap();
+        let stalemate = ChessBoard::new_from_fen("k7/8/1K6/8/8/8/7Q/8 b - - 0 1").unwrap();
+
+        assert_eq!(tablebases.probe_wdl(&mate_in_one), Some(Wdl::Win));
+        assert_eq!(tablebases.probe_wdl(&stalemate), Some(Wdl::Draw));
+        assert_eq!(tablebases.probe_dtz(&stalemate), Some(0));
+
+        /* Qh8 is the only mate, Qc7 stalemates and Qb8 gives the queen away. */
+        let root_moves = probe_root_moves(&tablebases, "k7/8/1K6/8/8/8/7Q/8 w - - 0 1");
+        assert_eq!(root_moves[0], ("h2h8".to_string(), 1));
+        assert!(root_moves[1..].iter().all(|(_, dtz)| *dtz != 1));
+        assert!(root_moves.contains(&("h2c7".to_string(), 0)));
+        assert!(root_moves.contains(&("h2b8".to_string(), 0)));
+    }
+
+    #[test]
+    #[ignore = "needs SYZYGY_PATH"]
+    fn test_real_rook_ending() {
+        let tablebases = real_tablebases();
+        let white_to_move = ChessBoard::new_from_fen("8/8/8/4k3/8/8/8/R3K3 w - - 0 1").unwrap();
+        let black_to_move = ChessBoard::new_from_fen("8/8/8/4k3/8/8/8/R3K3 b - - 0 1").unwrap();
+        let hanging_rook = ChessBoard::new_from_fen("8/8/8/8/8/8/1k6/R3K3 b - - 0 1").unwrap();
+
+        assert_eq!(tablebases.probe_wdl(&white_to_move), Some(Wdl::Win));
+        assert_eq!(tablebases.probe_wdl(&black_to_move), Some(Wdl::Loss));
+        assert_eq!(tablebases.probe_wdl(&hanging_rook), Some(Wdl::Draw));
+
+        /* A rook ending is mated in at most 16 moves, so the DTZ stays within 32 plies. */
+        let dtz = tablebases.probe_dtz(&white_to_move).unwrap();
+        assert!((1..=32).contains(&dtz));
+        let dtz = tablebases.probe_dtz(&black_to_move).unwrap();
+        assert!((-32..=-1).contains(&dtz));
+
+        /* Taking the rook draws, every other king move loses. */
+        let root_moves = probe_root_moves(&tablebases, "8/8/8/8/8/8/1k6/R3K3 b - - 0 1");
+        assert_eq!(root_moves[0], ("b2a1".to_string(), 0));
+        assert!(root_moves[1..].iter().all(|(_, dtz)| *dtz < 0));
+    }
+}
@@ -0,0 +1,1005 @@
+/* This module decodes a single Syzygy table file. A table stores one value for every position of
+ * a material combination, e.g. KRvK. The position is first mapped to an index, using the
+ * symmetries of the board to keep the index small, and the value at the index is then
+ * decompressed from blocks of canonical Huffman codes of "recursive pairing" symbols.
+ * The layout follows the table format of the Syzygy generator and the probing code of
+ * Stockfish, all squares are numbered from a1 = 0 to h8 = 63. */
+use crate::syzygy::Wdl;
+use std::sync::OnceLock;
+
+pub(crate) const MAX_PIECES: usize = 7;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+/* Flags of the compressed data of one table. */
+const FLAG_SIDE_TO_MOVE: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+/* Piece codes used by the tables, black pieces have the fourth bit set. */
+pub(crate) const PAWN: u8 = 1;
+pub(crate) const KING: u8 = 6;
+pub(crate) const BLACK: u8 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+/** A piece of the probed position, with the piece code used by the tables. */
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TablePiece {
+    pub code: u8,
+    pub square: usize,
+}
+
+/** The result of probing a table, a DTZ table only stores one side to move. */
+pub(crate) enum TableValue {
+    Value(i32),
+    ChangeSideToMove,
+}
+
+/** Decoding data of one side and leading file of a table. The offsets point into the file. */
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    flags: u8,
+    max_symbol_length: usize,
+    min_symbol_length: usize,
+    block_count: usize,
+    block_size: usize,
+    span: usize,
+    lowest_symbol: usize,
+    binary_tree: usize,
+    block_length: usize,
+    block_length_size: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    data: usize,
+    base64: Vec<u64>,
+    symbol_length: Vec<u8>,
+    pieces: [u8; MAX_PIECES],
+    group_index: [u64; MAX_PIECES + 1],
+    group_length: [usize; MAX_PIECES + 1],
+    map_index: [usize; 4],
+}
+
+pub(crate) struct Table {
+    kind: TableKind,
+    bytes: Vec<u8>,
+    piece_count: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    /* Pawns of the leading color first, the leading color is the one with fewer pawns. */
+    pawn_count: [usize; 2],
+    /* True if both sides have the same material, e.g. KRvKR. */
+    symmetric: bool,
+    /* Indexed by side and by the file of the leading pawn. */
+    pairs: Vec<Vec<PairsData>>,
+    dtz_map: usize,
+}
+
+/** Lookup tables of the index encoding, they are the same for every table. */
+struct Encoding {
+    map_pawns: [usize; 64],
+    map_b1h1h7: [usize; 64],
+    map_a1d1d4: [usize; 64],
+    map_kk: [[usize; 64]; 10],
+    binomial: [[u64; 64]; 6],
+    lead_pawn_index: [[u64; 64]; 6],
+    lead_pawns_size: [[u64; 4]; 6],
+}
+
+static ENCODING: OnceLock<Encoding> = OnceLock::new();
+
+fn encoding() -> &'static Encoding {
+    ENCODING.get_or_init(Encoding::new)
+}
+
+fn rank_of(square: usize) -> usize {
+    square >> 3
+}
+
+fn file_of(square: usize) -> usize {
+    square & 7
+}
+
+/* Positive above the a1-h8 diagonal, zero on it and negative below it. */
+fn off_diagonal(square: usize) -> i32 {
+    rank_of(square) as i32 - file_of(square) as i32
+}
+
+fn flip_file(square: usize) -> usize {
+    square ^ 7
+}
+
+fn flip_rank(square: usize) -> usize {
+    square ^ 56
+}
+
+fn kings_are_adjacent_or_equal(first: usize, second: usize) -> bool {
+    file_of(first).abs_diff(file_of(second)) <= 1 && rank_of(first).abs_diff(rank_of(second)) <= 1
+}
+
+impl Encoding {
+    fn new() -> Encoding {
+        let mut encoding = Encoding {
+            map_pawns: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; 6],
+            lead_pawn_index: [[0; 64]; 6],
+            lead_pawns_size: [[0; 4]; 6],
+        };
+
+        /* Squares below the a1-h8 diagonal are mapped to 0..27. */
+        let mut code = 0;
+        for square in 0..64 {
+            if off_diagonal(square) < 0 {
+                encoding.map_b1h1h7[square] = code;
+                code += 1;
+            }
+        }
+
+        /* Squares of the a1-d1-d4 triangle are mapped to 0..9, the diagonal comes last. */
+        let mut diagonal: Vec<usize> = Vec::new();
+        code = 0;
+        for square in 0..=27 {
+            if off_diagonal(square) < 0 && file_of(square) <= 3 {
+                encoding.map_a1d1d4[square] = code;
+                code += 1;
+            } else if off_diagonal(square) == 0 && file_of(square) <= 3 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            encoding.map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        /* The 462 legal placements of two kings, where the first is in the a1-d1-d4 triangle.
+        Placements with both kings on the diagonal are encoded last. */
+        let mut both_on_diagonal: Vec<(usize, usize)> = Vec::new();
+        code = 0;
+        for index in 0..10 {
+            for first in 0..=27 {
+                /* b1 is mapped to 0, like the squares outside the triangle. */
+                if encoding.map_a1d1d4[first] != index || (index == 0 && first != 1) {
+                    continue;
+                }
+
+                for second in 0..64 {
+                    if kings_are_adjacent_or_equal(first, second)
+                        || (off_diagonal(first) == 0 && off_diagonal(second) > 0)
+                    {
+                        continue;
+                    }
+
+                    if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                        both_on_diagonal.push((index, second));
+                    } else {
+                        encoding.map_kk[index][second] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (index, second) in both_on_diagonal {
+            encoding.map_kk[index][second] = code;
+            code += 1;
+        }
+
+        /* binomial[k][n] is the number of ways to choose k out of n elements. */
+        encoding.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..6.min(n + 1) {
+                encoding.binomial[k][n] = if k > 0 {
+                    encoding.binomial[k - 1][n - 1]
+                } else {
+                    0
+                } + if k < n {
+                    encoding.binomial[k][n - 1]
+                } else {
+                    0
+                };
+            }
+        }
+
+        /* map_pawns gives the number of squares left for the other pawns when the leading pawn
+        is on the square, the leading pawn is the one closest to the edge with the lowest rank. */
+        let mut available_squares = 47;
+        for lead_pawn_count in 1..=5 {
+            for file in 0..4 {
+                let mut index: u64 = 0;
+
+                for rank in 1..7 {
+                    let square = 8 * rank + file;
+
+                    if lead_pawn_count == 1 {
+                        encoding.map_pawns[square] = available_squares;
+                        available_squares -= 1;
+                        encoding.map_pawns[flip_file(square)] = available_squares;
+                        available_squares = available_squares.saturating_sub(1);
+                    }
+
+                    encoding.lead_pawn_index[lead_pawn_count][square] = index;
+                    index += encoding.binomial[lead_pawn_count - 1][encoding.map_pawns[square]];
+                }
+
+                encoding.lead_pawns_size[lead_pawn_count][file] = index;
+            }
+        }
+
+        encoding
+    }
+}
+
+/** Reads numbers from the table file, reading past the end returns zeros. */
+fn read_u8(bytes: &[u8], offset: usize) -> u8 {
+    bytes.get(offset).copied().unwrap_or(0)
+}
+
+fn read_u16_le(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([read_u8(bytes, offset), read_u8(bytes, offset + 1)])
+}
+
+fn read_u32_le(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        read_u8(bytes, offset),
+        read_u8(bytes, offset + 1),
+        read_u8(bytes, offset + 2),
+        read_u8(bytes, offset + 3),
+    ])
+}
+
+fn read_u32_be(bytes: &[u8], offset: usize) -> u32 {
+    read_u32_le(bytes, offset).swap_bytes()
+}
+
+fn read_u64_be(bytes: &[u8], offset: usize) -> u64 {
+    ((read_u32_be(bytes, offset) as u64) << 32) | read_u32_be(bytes, offset + 4) as u64
+}
+
+impl Table {
+    /** Parses a table file of the material given by its name, e.g. "KRvK". Returns None if the
+    file does not match the material or is not a table of the given kind. */
+    pub(crate) fn parse(kind: TableKind, name: &str, bytes: Vec<u8>) -> Option<Table> {
+        let magic = match kind {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        };
+
+        if bytes.len() < 5 || bytes[0..4] != magic {
+            return None;
+        }
+
+        let (white, black) = name.split_once('v')?;
+        let white_codes = piece_codes(white, 0)?;
+        let black_codes = piece_codes(black, BLACK)?;
+        let piece_count = white_codes.len() + black_codes.len();
+
+        if piece_count > MAX_PIECES {
+            return None;
+        }
+
+        let count = |codes: &[u8], code: u8| codes.iter().filter(|c| **c == code).count();
+        let white_pawns = count(&white_codes, PAWN);
+        let black_pawns = count(&black_codes, PAWN | BLACK);
+        let has_unique_pieces = (PAWN..KING)
+            .any(|code| count(&white_codes, code) == 1 || count(&black_codes, code | BLACK) == 1);
+
+        /* The leading color is the side with fewer pawns, or white if only white has pawns. */
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        let pawn_count = if white_leads {
+            [white_pawns, black_pawns]
+        } else {
+            [black_pawns, white_pawns]
+        };
+
+        let mut table = Table {
+            kind,
+            bytes,
+            piece_count,
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces,
+            pawn_count,
+            symmetric: white == black,
+            pairs: Vec::new(),
+            dtz_map: 0,
+        };
+
+        /* The first byte after the magic stores whether the table is split by side to move and
+        whether it has pawns. */
+        let header = read_u8(&table.bytes, 4);
+        let split = header & 1 != 0;
+
+        if table.has_pawns != (header & 2 != 0)
+            || (kind == TableKind::Wdl && split == table.symmetric)
+        {
+            return None;
+        }
+
+        table.read_layout(5)?;
+        Some(table)
+    }
+
+    fn sides(&self) -> usize {
+        if self.kind == TableKind::Wdl && !self.symmetric {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn files(&self) -> usize {
+        if self.has_pawns {
+            4
+        } else {
+            1
+        }
+    }
+
+    fn pairs(&self, side: usize, file: usize) -> &PairsData {
+        &self.pairs[side % self.sides()][if self.has_pawns { file } else { 0 }]
+    }
+
+    fn read_layout(&mut self, mut offset: usize) -> Option<()> {
+        let sides = self.sides();
+        let files = self.files();
+        let pawns_on_both_sides = self.has_pawns && self.pawn_count[1] > 0;
+        self.pairs = vec![vec![PairsData::default(); files]; sides];
+
+        for file in 0..files {
+            let first = read_u8(&self.bytes, offset);
+            let second = if pawns_on_both_sides {
+                read_u8(&self.bytes, offset + 1)
+            } else {
+                0xff
+            };
+            let order = [[first & 0xf, second & 0xf], [first >> 4, second >> 4]];
+            offset += 1 + pawns_on_both_sides as usize;
+
+            for k in 0..self.piece_count {
+                let byte = read_u8(&self.bytes, offset);
+                for side in 0..sides {
+                    self.pairs[side][file].pieces[k] =
+                        if side == 0 { byte & 0xf } else { byte >> 4 };
+                }
+                offset += 1;
+            }
+
+            for (side, side_order) in order.into_iter().enumerate().take(sides) {
+                self.set_groups(side, file, side_order);
+            }
+        }
+
+        offset += offset & 1;
+
+        for file in 0..files {
+            for side in 0..sides {
+                offset = self.set_sizes(side, file, offset)?;
+            }
+        }
+
+        if self.kind == TableKind::Dtz {
+            offset = self.set_dtz_map(offset, files);
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &mut self.pairs[side][file];
+                pairs.sparse_index = offset;
+                offset += pairs.sparse_index_size * 6;
+            }
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &mut self.pairs[side][file];
+                pairs.block_length = offset;
+                offset += pairs.block_length_size * 2;
+            }
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                offset = (offset + 0x3f) & !0x3f;
+                let pairs = &mut self.pairs[side][file];
+                pairs.data = offset;
+                offset += pairs.block_count * pairs.block_size;
+            }
+        }
+
+        (offset <= self.bytes.len()).then_some(())
+    }
+
+    /** Splits the pieces into groups of equal pieces, where the first group holds the leading
+    pieces, and computes the factor of every group in the index. */
+    fn set_groups(&mut self, side: usize, file: usize, order: [u8; 2]) {
+        let encoding = encoding();
+        let has_pawns = self.has_pawns;
+        let has_unique_pieces = self.has_unique_pieces;
+        let pawns_on_both_sides = has_pawns && self.pawn_count[1] > 0;
+        let piece_count = self.piece_count;
+        let pairs = &mut self.pairs[side][file];
+
+        let mut first_length: i32 = if has_pawns {
+            0
+        } else if has_unique_pieces {
+            3
+        } else {
+            2
+        };
+        let mut n = 0;
+        pairs.group_length[0] = 1;
+
+        for i in 1..piece_count {
+            first_length -= 1;
+
+            if first_length > 0 || pairs.pieces[i] == pairs.pieces[i - 1] {
+                pairs.group_length[n] += 1;
+            } else {
+                n += 1;
+                pairs.group_length[n] = 1;
+            }
+        }
+
+        n += 1;
+        pairs.group_length[n] = 0;
+
+        let mut next = if pawns_on_both_sides { 2 } else { 1 };
+        let mut free_squares = 64
+            - pairs.group_length[0]
+            - if pawns_on_both_sides {
+                pairs.group_length[1]
+            } else {
+                0
+            };
+        let mut index: u64 = 1;
+        let mut k: u8 = 0;
+
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                pairs.group_index[0] = index;
+                index *= if has_pawns {
+                    encoding.lead_pawns_size[pairs.group_length[0]][file]
+                } else if has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] {
+                pairs.group_index[1] = index;
+                index *= encoding.binomial[pairs.group_length[1]][48 - pairs.group_length[0]];
+            } else {
+                pairs.group_index[next] = index;
+                index *= encoding.binomial[pairs.group_length[next]][free_squares];
+                free_squares -= pairs.group_length[next];
+                next += 1;
+            }
+
+            k += 1;
+        }
+
+        pairs.group_index[n] = index;
+    }
+
+    /** Reads the header of the compressed data and builds the Huffman decoding tables. */
+    fn set_sizes(&mut self, side: usize, file: usize, mut offset: usize) -> Option<usize> {
+        let bytes = &self.bytes;
+        let pairs = &mut self.pairs[side][file];
+
+        pairs.flags = read_u8(bytes, offset);
+        offset += 1;
+
+        if pairs.flags & FLAG_SINGLE_VALUE != 0 {
+            /* The single value is stored as the minimum symbol length. */
+            pairs.min_symbol_length = read_u8(bytes, offset) as usize;
+            return Some(offset + 1);
+        }
+
+        let group_count = pairs.group_length.iter().position(|length| *length == 0)?;
+        let table_size = pairs.group_index[group_count];
+
+        pairs.block_size = 1 << read_u8(bytes, offset);
+        pairs.span = 1 << read_u8(bytes, offset + 1);
+        pairs.sparse_index_size = table_size.div_ceil(pairs.span as u64) as usize;
+        let padding = read_u8(bytes, offset + 2) as usize;
+        pairs.block_count = read_u32_le(bytes, offset + 3) as usize;
+        pairs.block_length_size = pairs.block_count + padding;
+        pairs.max_symbol_length = read_u8(bytes, offset + 7) as usize;
+        pairs.min_symbol_length = read_u8(bytes, offset + 8) as usize;
+        pairs.lowest_symbol = offset + 9;
+        offset += 9;
+
+        if pairs.max_symbol_length < pairs.min_symbol_length || pairs.max_symbol_length > 64 {
+            return None;
+        }
+
+        /* Longer canonical Huffman codes have lower values. base64[i] is the lowest code of
+        length i + min_symbol_length, left aligned in 64 bits. */
+        let lengths = pairs.max_symbol_length - pairs.min_symbol_length + 1;
+        let lowest_symbol = |i: usize| read_u16_le(bytes, pairs.lowest_symbol + 2 * i) as u64;
+        let mut base64: Vec<u64> = vec![0; lengths];
+
+        for i in (0..lengths - 1).rev() {
+            base64[i] = (base64[i + 1] + lowest_symbol(i)).wrapping_sub(lowest_symbol(i + 1)) / 2;
+        }
+
+        for (i, base) in base64.iter_mut().enumerate() {
+            let shift = 64 - i - pairs.min_symbol_length;
+            *base = if shift >= 64 { 0 } else { *base << shift };
+        }
+
+        pairs.base64 = base64;
+        offset += lengths * 2;
+
+        let symbol_count = read_u16_le(bytes, offset) as usize;
+        offset += 2;
+        pairs.binary_tree = offset;
+        pairs.symbol_length = vec![0; symbol_count];
+
+        let mut visited = vec![false; symbol_count];
+        for symbol in 0..symbol_count {
+            if !visited[symbol] {
+                let length = symbol_length(bytes, pairs, symbol, &mut visited)?;
+                pairs.symbol_length[symbol] = length;
+            }
+        }
+
+        Some(offset + symbol_count * 3 + (symbol_count & 1))
+    }
+
+    /** Reads the maps from stored DTZ values to real DTZ values, one map per WDL result. */
+    fn set_dtz_map(&mut self, mut offset: usize, files: usize) -> usize {
+        self.dtz_map = offset;
+
+        for file in 0..files {
+            let flags = self.pairs[0][file].flags;
+
+            if flags & FLAG_MAPPED == 0 {
+                continue;
+            }
+
+            if flags & FLAG_WIDE != 0 {
+                offset += offset & 1;
+
+                for i in 0..4 {
+                    self.pairs[0][file].map_index[i] = (offset - self.dtz_map) / 2 + 1;
+                    offset += 2 * read_u16_le(&self.bytes, offset) as usize + 2;
+                }
+            } else {
+                for i in 0..4 {
+                    self.pairs[0][file].map_index[i] = offset - self.dtz_map + 1;
+                    offset += read_u8(&self.bytes, offset) as usize + 1;
+                }
+            }
+        }
+
+        offset + (offset & 1)
+    }
+
+    /** Probes the position given by its pieces. The pieces must match the material of the
+    table, and `black_stronger` tells whether the colors have to be swapped to match it. */
+    pub(crate) fn probe(
+        &self,
+        pieces: &[TablePiece],
+        white_to_move: bool,
+        black_stronger: bool,
+        wdl: Wdl,
+    ) -> Option<TableValue> {
+        let encoding = encoding();
+
+        /* Symmetric tables only store white to move, and tables are stored with white as the
+        stronger side, so the colors and the board are flipped when needed. */
+        let black_symmetric = !white_to_move && self.symmetric;
+        let flip = black_symmetric || black_stronger;
+        let flip_color: u8 = if flip { BLACK } else { 0 };
+        let flip_squares: usize = if flip { 56 } else { 0 };
+        let side_to_move: usize = (flip as usize) ^ (!white_to_move as usize);
+
+        let mut squares: Vec<usize> = Vec::with_capacity(pieces.len());
+        let mut codes: Vec<u8> = Vec::with_capacity(pieces.len());
+        let mut lead_pawn_count = 0;
+        let mut file = 0;
+
+        if self.has_pawns {
+            let lead_pawn = self.pairs(0, 0).pieces[0] ^ flip_color;
+
+            for piece in pieces.iter().filter(|piece| piece.code == lead_pawn) {
+                squares.push(piece.square ^ flip_squares);
+                codes.push(piece.code ^ flip_color);
+            }
+
+            lead_pawn_count = squares.len();
+            let lead_index =
+                (0..lead_pawn_count).max_by_key(|i| encoding.map_pawns[squares[*i]])?;
+            squares.swap(0, lead_index);
+            file = file_of(squares[0]).min(7 - file_of(squares[0]));
+        }
+
+        /* Symmetric tables without pawns store both sides to move in the same values. */
+        let stores_side_to_move = (self.pairs(0, file).flags & FLAG_SIDE_TO_MOVE) as usize
+            == side_to_move
+            || (self.symmetric && !self.has_pawns);
+
+        if self.kind == TableKind::Dtz && !stores_side_to_move {
+            return Some(TableValue::ChangeSideToMove);
+        }
+
+        let lead_pawn = if self.has_pawns {
+            Some(self.pairs(0, 0).pieces[0] ^ flip_color)
+        } else {
+            None
+        };
+
+        for piece in pieces.iter().filter(|piece| Some(piece.code) != lead_pawn) {
+            squares.push(piece.square ^ flip_squares);
+            codes.push(piece.code ^ flip_color);
+        }
+
+        let pairs = self.pairs(side_to_move, file);
+        let size = squares.len();
+
+        if size != self.piece_count {
+            return None;
+        }
+
+        /* Orders the pieces like the table, which is the order that compresses best. */
+        for i in lead_pawn_count..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|j| pairs.pieces[i] == codes[*j]) {
+                codes.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        /* Mirrors the board so the leading piece is on the files a to d. */
+        if file_of(squares[0]) > 3 {
+            for square in squares.iter_mut() {
+                *square = flip_file(*square);
+            }
+        }
+
+        let mut index: u64;
+
+        if self.has_pawns {
+            index = encoding.lead_pawn_index[lead_pawn_count][squares[0]];
+            squares[1..lead_pawn_count].sort_by_key(|square| encoding.map_pawns[*square]);
+
+            for (i, square) in squares.iter().enumerate().take(lead_pawn_count).skip(1) {
+                index += encoding.binomial[i][encoding.map_pawns[*square]];
+            }
+        } else {
+            /* Mirrors the board so the leading piece is on the ranks 1 to 4. */
+            if rank_of(squares[0]) > 3 {
+                for square in squares.iter_mut() {
+                    *square = flip_rank(*square);
+                }
+            }
+
+            /* Mirrors along the a1-h8 diagonal so the first leading piece that is not on the
+            diagonal is below it. */
+            for i in 0..pairs.group_length[0] {
+                if off_diagonal(squares[i]) == 0 {
+                    continue;
+                }
+
+                if off_diagonal(squares[i]) > 0 {
+                    for square in squares[i..].iter_mut() {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+
+                break;
+            }
+
+            index = if self.has_unique_pieces {
+                unique_pieces_index(encoding, &squares)
+            } else {
+                encoding.map_kk[encoding.map_a1d1d4[squares[0]]][squares[1]] as u64
+            };
+        }
+
+        index *= pairs.group_index[0];
+
+        let mut group_start = pairs.group_length[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+
+        while pairs.group_length[next] != 0 {
+            let group_end = group_start + pairs.group_length[next];
+            squares[group_start..group_end].sort_unstable();
+            let mut n: u64 = 0;
+
+            for i in 0..pairs.group_length[next] {
+                let square = squares[group_start + i];
+                let adjust = squares[..group_start]
+                    .iter()
+                    .filter(|previous| square > **previous)
+                    .count();
+
+                n +=
+                    encoding.binomial[i + 1][square - adjust - if remaining_pawns { 8 } else { 0 }];
+            }
+
+            remaining_pawns = false;
+            index += n * pairs.group_index[next];
+            group_start = group_end;
+            next += 1;
+        }
+
+        let value = self.decompress(pairs, index)?;
+
+        Some(TableValue::Value(match self.kind {
+            TableKind::Wdl => value - 2,
+            TableKind::Dtz => self.map_dtz(file, value, wdl),
+        }))
+    }
+
+    fn map_dtz(&self, file: usize, mut value: i32, wdl: Wdl) -> i32 {
+        let pairs = self.pairs(0, file);
+        let flags = pairs.flags;
+
+        if flags & FLAG_MAPPED != 0 {
+            let map_index = pairs.map_index[match wdl {
+                Wdl::Win => 0,
+                Wdl::Loss => 1,
+                Wdl::CursedWin => 2,
+                Wdl::BlessedLoss => 3,
+                Wdl::Draw => 0,
+            }] + value as usize;
+
+            value = if flags & FLAG_WIDE != 0 {
+                read_u16_le(&self.bytes, self.dtz_map + 2 * map_index) as i32
+            } else {
+                read_u8(&self.bytes, self.dtz_map + map_index) as i32
+            };
+        }
+
+        /* DTZ is stored in moves unless the flags say it is stored in plies. */
+        if (wdl == Wdl::Win && flags & FLAG_WIN_PLIES == 0)
+            || (wdl == Wdl::Loss && flags & FLAG_LOSS_PLIES == 0)
+            || wdl == Wdl::CursedWin
+            || wdl == Wdl::BlessedLoss
+        {
+            value *= 2;
+        }
+
+        value + 1
+    }
+
+    /** Returns the value stored at the index. */
+    fn decompress(&self, pairs: &PairsData, index: u64) -> Option<i32> {
+        if pairs.flags & FLAG_SINGLE_VALUE != 0 {
+            return Some(pairs.min_symbol_length as i32);
+        }
+
+        let bytes = &self.bytes;
+        let block_length = |block: usize| read_u16_le(bytes, pairs.block_length + 2 * block) as i64;
+
+        /* The sparse index points to the block of every span-th value, from there the block
+        lengths are walked to the block holding the value. */
+        let k = (index / pairs.span as u64) as usize;
+        if k >= pairs.sparse_index_size {
+            return None;
+        }
+
+        let mut block = read_u32_le(bytes, pairs.sparse_index + 6 * k) as usize;
+        let mut offset = read_u16_le(bytes, pairs.sparse_index + 6 * k + 4) as i64;
+        offset += (index % pairs.span as u64) as i64 - (pairs.span / 2) as i64;
+
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block) + 1;
+        }
+
+        while offset > block_length(block) {
+            offset -= block_length(block) + 1;
+            block += 1;
+
+            if block >= pairs.block_length_size {
+                return None;
+            }
+        }
+
+        /* Reads canonical Huffman symbols from the block until the symbol holding the value. */
+        let mut pointer = pairs.data + block * pairs.block_size;
+        let mut buffer = read_u64_be(bytes, pointer);
+        pointer += 8;
+        let mut buffer_size = 64;
+        let mut symbol: usize;
+
+        loop {
+            let mut length = 0;
+
+            while length + 1 < pairs.base64.len() && buffer < pairs.base64[length] {
+                length += 1;
+            }
+
+            let shift = 64 - length - pairs.min_symbol_length;
+            symbol = ((buffer - pairs.base64[length]) >> shift) as usize;
+            symbol += read_u16_le(bytes, pairs.lowest_symbol + 2 * length) as usize;
+
+            let expanded_length = *pairs.symbol_length.get(symbol)? as i64 + 1;
+
+            if offset < expanded_length {
+                break;
+            }
+
+            offset -= expanded_length;
+            length += pairs.min_symbol_length;
+            buffer = if length >= 64 { 0 } else { buffer << length };
+            buffer_size -= length as i32;
+
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= (read_u32_be(bytes, pointer) as u64) << (64 - buffer_size);
+                pointer += 4;
+            }
+        }
+
+        /* Expands the symbol into its pair of symbols until a single value is left. */
+        while pairs.symbol_length[symbol] != 0 {
+            let (left, right) = tree_node(bytes, pairs, symbol);
+            let left_length = *pairs.symbol_length.get(left)? as i64 + 1;
+
+            if offset < left_length {
+                symbol = left;
+            } else {
+                offset -= left_length;
+                symbol = right;
+            }
+        }
+
+        Some(tree_node(bytes, pairs, symbol).0 as i32)
+    }
+}
+
+/** Encodes a leading group of three unique pieces, like the two kings and the rook in KRvK. */
+fn unique_pieces_index(encoding: &Encoding, squares: &[usize]) -> u64 {
+    let adjust1 = (squares[1] > squares[0]) as usize;
+    let adjust2 = (squares[2] > squares[0]) as usize + (squares[2] > squares[1]) as usize;
+
+    let index = if off_diagonal(squares[0]) != 0 {
+        (encoding.map_a1d1d4[squares[0]] * 63 + (squares[1] - adjust1)) * 62 + squares[2] - adjust2
+    } else if off_diagonal(squares[1]) != 0 {
+        (6 * 63 + rank_of(squares[0]) * 28 + encoding.map_b1h1h7[squares[1]]) * 62 + squares[2]
+            - adjust2
+    } else if off_diagonal(squares[2]) != 0 {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + rank_of(squares[0]) * 7 * 28
+            + (rank_of(squares[1]) - adjust1) * 28
+            + encoding.map_b1h1h7[squares[2]]
+    } else {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + 4 * 7 * 28
+            + rank_of(squares[0]) * 7 * 6
+            + (rank_of(squares[1]) - adjust1) * 6
+            + (rank_of(squares[2]) - adjust2)
+    };
+
+    index as u64
+}
+
+/** Returns the left and right symbol a symbol expands to, the right symbol of a leaf is 0xfff
+and its left symbol is the stored value. */
+fn tree_node(bytes: &[u8], pairs: &PairsData, symbol: usize) -> (usize, usize) {
+    let node = pairs.binary_tree + 3 * symbol;
+    let (first, second, third) = (
+        read_u8(bytes, node) as usize,
+        read_u8(bytes, node + 1) as usize,
+        read_u8(bytes, node + 2) as usize,
+    );
+
+    (((second & 0xf) << 8) | first, (third << 4) | (second >> 4))
+}
+
+/** Returns the number of values minus one that a symbol expands to. */
+fn symbol_length(
+    bytes: &[u8],
+    pairs: &mut PairsData,
+    symbol: usize,
+    visited: &mut [bool],
+) -> Option<u8> {
+    visited[symbol] = true;
+    let (left, right) = tree_node(bytes, pairs, symbol);
+
+    if right == 0xfff {
+        return Some(0);
+    }
+
+    for child in [left, right] {
+        if !*visited.get(child)? {
+            let length = symbol_length(bytes, pairs, child, visited)?;
+            pairs.symbol_length[child] = length;
+        }
+    }
+
+    Some(
+        pairs.symbol_length[left]
+            .wrapping_add(pairs.symbol_length[right])
+            .wrapping_add(1),
+    )
+}
+
+/** Converts the pieces of one side in a table name, e.g. "KRP", to piece codes. */
+fn piece_codes(pieces: &str, color: u8) -> Option<Vec<u8>> {
+    pieces
+        .chars()
+        .map(|c| {
+            let code = match c {
+                'P' => PAWN,
+                'N' => 2,
+                'B' => 3,
+                'R' => 4,
+                'Q' => 5,
+                'K' => KING,
+                _ => return None,
+            };
+            Some(code | color)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoding_tables() {
+        let encoding = encoding();
+
+        /* There are 462 ways to place two kings up to symmetry. */
+        let king_placements = (0..10)
+            .flat_map(|index| encoding.map_kk[index].iter())
+            .max()
+            .unwrap()
+            + 1;
+        assert_eq!(king_placements, 462);
+
+        assert_eq!(encoding.binomial[2][5], 10);
+        assert_eq!(encoding.binomial[5][63], 7028847);
+        assert_eq!(encoding.map_pawns[8], 47);
+        assert_eq!(encoding.map_pawns[15], 46);
+        assert_eq!(encoding.map_a1d1d4[1], 0);
+        assert_eq!(encoding.map_a1d1d4[27], 9);
+    }
+
+    #[test]
+    fn test_single_value_table() {
+        /* A KQvK table where every position is a win, stored as a single value. */
+        let mut bytes = WDL_MAGIC.to_vec();
+        bytes.extend([0b01, 0x00, 0x66, 0x55, 0xee, 0]);
+        bytes.extend([FLAG_SINGLE_VALUE, 4, FLAG_SINGLE_VALUE, 0]);
+        bytes.resize(64, 0);
+
+        let table = Table::parse(TableKind::Wdl, "KQvK", bytes).unwrap();
+        let pieces = [
+            TablePiece {
+                code: KING,
+                square: 4,
+            },
+            TablePiece { code: 5, square: 3 },
+            TablePiece {
+                code: KING | BLACK,
+                square: 60,
+            },
+        ];
+
+        match table.probe(&pieces, true, false, Wdl::Draw) {
+            Some(TableValue::Value(value)) => assert_eq!(value, 2),
+            _ => panic!("Expected a value"),
+        }
+        match table.probe(&pieces, false, false, Wdl::Draw) {
+            Some(TableValue::Value(value)) => assert_eq!(value, -2),
+            _ => panic!("Expected a value"),
+        }
+    }
+}
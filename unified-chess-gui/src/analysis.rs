@@ -0,0 +1,193 @@
+/* This module shows the engine analysis of the position on the board: an evaluation bar next to
+ * the board, the depth and speed of the search, and the best lines in SAN. The analysis runs until
+ * the position changes, and clicking a move of a line plays the line up to that move on the board
+ * as a variation. */
+use crate::board::SQUARE_SIZE;
+use crate::Message;
+use iced::widget::{button, column, container, pick_list, row, text, Column, Row};
+use iced::{Background, Element, Length};
+use unified_chess_engine::array_engine::{ChessBoard, Color, ROW_SIZE};
+use unified_chess_engine::search::{SearchInfo, MATE_BOUND, MATE_SCORE};
+
+pub(crate) const DEFAULT_ANALYSIS_LINES: usize = 3;
+const ANALYSIS_LINE_CHOICES: [usize; 5] = [1, 2, 3, 4, 5];
+const EVALUATION_BAR_WIDTH: f32 = 24.0;
+const ANALYSIS_WIDTH: f32 = 380.0;
+const SCORE_WIDTH: f32 = 56.0;
+const WHITE_BAR: iced::Color = iced::Color::from_rgb(0.95, 0.95, 0.95);
+const BLACK_BAR: iced::Color = iced::Color::from_rgb(0.2, 0.2, 0.2);
+
+pub(crate) struct Analysis {
+    /* The number of best lines searched. */
+    pub(crate) lines: usize,
+    /* The id of the running search, None when there is nothing to search. */
+    pub(crate) search: Option<u64>,
+    /* The analysed position. */
+    pub(crate) chess_board: ChessBoard,
+    /* The latest report of each line, the best line first. */
+    infos: Vec<SearchInfo>,
+}
+
+impl Analysis {
+    pub(crate) fn new(lines: usize, chess_board: ChessBoard) -> Analysis {
+        Analysis {
+            lines,
+            search: None,
+            chess_board,
+            infos: Vec::new(),
+        }
+    }
+
+    /** Keeps the report of a line, the lines are reported in order after every iteration. */
+    pub(crate) fn update(&mut self, info: SearchInfo) {
+        let index = info.multi_pv - 1;
+
+        match index.cmp(&self.infos.len()) {
+            std::cmp::Ordering::Less => self.infos[index] = info,
+            std::cmp::Ordering::Equal => self.infos.push(info),
+            std::cmp::Ordering::Greater => {}
+        }
+    }
+
+    /** The score of the best line from the view of white. */
+    fn white_score(&self) -> Option<i32> {
+        let info = self.infos.first()?;
+
+        Some(match self.chess_board.side_to_move() {
+            Color::White => info.score,
+            Color::Black => -info.score,
+        })
+    }
+}
+
+/** Writes a score from the view of white in pawns, or the moves to mate like #3 and #-2. */
+pub(crate) fn format_score(white_score: i32) -> String {
+    if white_score.abs() >= MATE_BOUND {
+        let moves_to_mate = (MATE_SCORE - white_score.abs() + 1) / 2;
+        format!("#{}", moves_to_mate * white_score.signum())
+    } else {
+        format!("{:+.2}", white_score as f64 / 100.0)
+    }
+}
+
+/** The part of the evaluation bar filled by white, from the expected score of white. */
+fn white_share(white_score: i32) -> f32 {
+    if white_score.abs() >= MATE_BOUND {
+        return if white_score > 0 { 1.0 } else { 0.0 };
+    }
+
+    1.0 / (1.0 + 10f32.powf(-white_score as f32 / 400.0))
+}
+
+/** A bar as high as the board, white's part grows from white's side of the board. */
+pub(crate) fn evaluation_bar<'a>(analysis: &Analysis, flipped: bool) -> Element<'a, Message> {
+    let share = analysis.white_score().map_or(0.5, white_share);
+    let white_portion = ((share * 1000.0).round() as u16).clamp(1, 999);
+
+    let part = |color: iced::Color, portion: u16| {
+        container(row![])
+            .width(Length::Fill)
+            .height(Length::FillPortion(portion))
+            .style(move |_theme| container::Style {
+                background: Some(Background::Color(color)),
+                ..container::Style::default()
+            })
+    };
+    let white = part(WHITE_BAR, white_portion);
+    let black = part(BLACK_BAR, 1000 - white_portion);
+
+    let bar = match flipped {
+        false => column![black, white],
+        true => column![white, black],
+    };
+
+    bar.width(EVALUATION_BAR_WIDTH)
+        .height(SQUARE_SIZE * ROW_SIZE as f32)
+        .into()
+}
+
+/** The depth and speed of the search and the best lines. */
+pub(crate) fn analysis_view(analysis: &Analysis) -> Element<'_, Message> {
+    let summary = match analysis.infos.first() {
+        Some(info) => {
+            let milliseconds = info.elapsed.as_millis().max(1);
+            let nodes_per_second = info.nodes as u128 * 1000 / milliseconds;
+            format!("Depth {}, {} kN/s", info.depth, nodes_per_second / 1000)
+        }
+        None if analysis.search.is_none() => "No moves to analyse".to_string(),
+        None => "Analysing".to_string(),
+    };
+
+    let header = row![
+        text(summary).width(Length::Fill),
+        text("Lines"),
+        pick_list(
+            ANALYSIS_LINE_CHOICES,
+            Some(analysis.lines),
+            Message::SetAnalysisLines
+        ),
+    ]
+    .spacing(10);
+
+    let lines = analysis
+        .infos
+        .iter()
+        .map(|info| line_view(analysis.chess_board, info));
+
+    column![header, Column::with_children(lines).spacing(8)]
+        .spacing(10)
+        .width(ANALYSIS_WIDTH)
+        .into()
+}
+
+/** The score and the moves of a line, numbered like in the move list. */
+fn line_view<'a>(chess_board: ChessBoard, info: &SearchInfo) -> Element<'a, Message> {
+    let white_score = match chess_board.side_to_move() {
+        Color::White => info.score,
+        Color::Black => -info.score,
+    };
+
+    let mut chess_board = chess_board;
+    let mut items: Vec<Element<'a, Message>> =
+        vec![text(format_score(white_score)).width(SCORE_WIDTH).into()];
+
+    for (i, chess_move) in info.principal_variation.iter().enumerate() {
+        let san = chess_board.move_to_san(chess_move);
+        let move_number = chess_board.full_move_counter();
+        let label = match (chess_board.side_to_move(), i) {
+            (Color::White, _) => format!("{move_number}. {san}"),
+            (Color::Black, 0) => format!("{move_number}... {san}"),
+            (Color::Black, _) => san,
+        };
+
+        items.push(
+            button(text(label).size(14))
+                .padding([2, 3])
+                .style(button::text)
+                .on_press(Message::PlayAnalysisMoves(
+                    info.principal_variation[..=i].to_vec(),
+                ))
+                .into(),
+        );
+
+        chess_board.make_move_on_board(chess_move);
+        chess_board.update_meta_data(chess_move);
+    }
+
+    Row::with_children(items).wrap().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scores() {
+        assert_eq!(format_score(35), "+0.35");
+        assert_eq!(format_score(-120), "-1.20");
+        assert_eq!(format_score(MATE_SCORE - 5), "#3");
+        assert_eq!(format_score(-MATE_SCORE + 2), "#-1");
+        assert!((white_share(0) - 0.5).abs() < 1e-6);
+        assert!(white_share(400) > 0.9 && white_share(-MATE_SCORE + 2) == 0.0);
+    }
+}
@@ -0,0 +1,412 @@
+/* This module draws the chessboard of the GUI: 8x8 squares with the cburnett pieces, seen from
+ * white with rank 8 at the top, or from black when it is flipped. Pressing a square sends
+ * `Message::ClickSquare`, so a piece can be moved by clicking it and its destination, or by
+ * dragging it there. The selected piece shows where it can move to, and the squares of a hint
+ * are highlighted. */
+use crate::board_editor::EditorMessage;
+use crate::{Coordinate, GameState, Message};
+use iced::widget::{
+    button, column, container, mouse_area, opaque, row, stack, svg, text, Row, Stack,
+};
+use iced::{Alignment, Background, Border, Color, Element, Length, Padding, Point};
+use unified_chess_engine::array_engine::{self, Piece, PieceType, COL_SIZE, ROW_SIZE};
+
+pub(crate) const SQUARE_SIZE: f32 = 72.0;
+
+const LIGHT_SQUARE: Color = Color::from_rgb(0.93, 0.85, 0.71);
+const DARK_SQUARE: Color = Color::from_rgb(0.71, 0.53, 0.39);
+const SELECTED_SQUARE: Color = Color::from_rgb(0.80, 0.82, 0.42);
+const HINT_SQUARE: Color = Color::from_rgb(0.49, 0.69, 0.84);
+const TARGET_MARKER: Color = Color::from_rgba(0.08, 0.33, 0.08, 0.45);
+pub(crate) const BACKDROP: Color = Color::from_rgba(0.0, 0.0, 0.0, 0.6);
+const PROMOTION_PIECES: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
+
+/* The SVGs in the order of `piece_image_index`, embedded so the binary runs from any directory. */
+const PIECES: [&[u8]; 12] = [
+    include_bytes!("../../pieces/cburnett/bB.svg"),
+    include_bytes!("../../pieces/cburnett/bK.svg"),
+    include_bytes!("../../pieces/cburnett/bN.svg"),
+    include_bytes!("../../pieces/cburnett/bP.svg"),
+    include_bytes!("../../pieces/cburnett/bQ.svg"),
+    include_bytes!("../../pieces/cburnett/bR.svg"),
+    include_bytes!("../../pieces/cburnett/wB.svg"),
+    include_bytes!("../../pieces/cburnett/wK.svg"),
+    include_bytes!("../../pieces/cburnett/wN.svg"),
+    include_bytes!("../../pieces/cburnett/wP.svg"),
+    include_bytes!("../../pieces/cburnett/wQ.svg"),
+    include_bytes!("../../pieces/cburnett/wR.svg"),
+];
+
+/** The piece images, loaded once so iced can cache the rendered SVGs. */
+pub(crate) struct PieceImages {
+    handles: Vec<svg::Handle>,
+}
+
+impl Default for PieceImages {
+    fn default() -> Self {
+        PieceImages {
+            handles: PIECES
+                .iter()
+                .map(|bytes| svg::Handle::from_memory(*bytes))
+                .collect(),
+        }
+    }
+}
+
+impl PieceImages {
+    pub(crate) fn handle(&self, piece: Piece) -> svg::Handle {
+        self.handles[piece_image_index(piece)].clone()
+    }
+}
+
+fn piece_image_index(piece: Piece) -> usize {
+    let color_offset = match piece.color() {
+        array_engine::Color::Black => 0,
+        array_engine::Color::White => 6,
+    };
+
+    color_offset
+        + match piece.piece_type() {
+            PieceType::Bishop => 0,
+            PieceType::King => 1,
+            PieceType::Knight => 2,
+            PieceType::Pawn => 3,
+            PieceType::Queen => 4,
+            PieceType::Rook => 5,
+        }
+}
+
+/** Where the selected piece can move to, captures are marked differently from quiet moves. */
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum MoveTarget {
+    Quiet,
+    Capture,
+}
+
+/** Draws the board with white at the bottom, or black when the board is flipped. */
+pub(crate) fn board_view<'a>(
+    game_state: &'a GameState,
+    piece_images: &'a PieceImages,
+    flipped: bool,
+) -> Element<'a, Message> {
+    let move_targets = &game_state.move_targets();
+
+    let ranks = (0..ROW_SIZE).map(move |row| {
+        let squares = (0..COL_SIZE).map(move |column| {
+            let coordinate = coordinate_of(column, row, flipped);
+            let move_target = move_targets
+                .iter()
+                .find(|(target, _)| *target == coordinate)
+                .map(|(_, move_target)| *move_target);
+
+            let mut square = square_view(game_state, piece_images, coordinate, move_target);
+            /* The ranks are labeled along the left edge and the files along the bottom edge. */
+            if column == 0 {
+                square = square.push(coordinate_label(coordinate, (coordinate.y + 1).to_string()));
+            }
+            if row == ROW_SIZE - 1 {
+                let file = (b'a' + coordinate.x as u8) as char;
+                square = square.push(coordinate_label(coordinate, file.to_string()));
+            }
+
+            square_area(game_state, square, coordinate)
+        });
+
+        Row::with_children(squares).into()
+    });
+
+    let board = stack![column(ranks)]
+        .push_maybe(dragged_piece_view(game_state, piece_images))
+        .push_maybe(promotion_view(game_state, piece_images));
+
+    /* The squares take the presses, the board tracks the cursor and the drops. */
+    mouse_area(board)
+        .on_move(Message::MoveCursor)
+        .on_release(Message::DropPiece)
+        .on_exit(Message::CancelDrag)
+        .into()
+}
+
+/** Draws the board of the position setup, without moves. Pressing a square places the piece
+picked in the palette, a right click empties it. */
+pub(crate) fn setup_board_view<'a>(
+    piece_at: impl Fn(Coordinate) -> Option<Piece>,
+    piece_images: &'a PieceImages,
+    flipped: bool,
+) -> Element<'a, Message> {
+    let ranks = (0..ROW_SIZE).map(|row| {
+        let squares = (0..COL_SIZE).map(|column| {
+            let coordinate = coordinate_of(column, row, flipped);
+            let mut square = Stack::new().width(Length::Fill).height(Length::Fill);
+
+            if let Some(piece) = piece_at(coordinate) {
+                square = square.push(
+                    svg(piece_images.handle(piece))
+                        .width(Length::Fill)
+                        .height(Length::Fill),
+                );
+            }
+            if column == 0 {
+                square = square.push(coordinate_label(coordinate, (coordinate.y + 1).to_string()));
+            }
+            if row == ROW_SIZE - 1 {
+                let file = (b'a' + coordinate.x as u8) as char;
+                square = square.push(coordinate_label(coordinate, file.to_string()));
+            }
+
+            let background = match is_dark_square(coordinate) {
+                true => DARK_SQUARE,
+                false => LIGHT_SQUARE,
+            };
+            let square = container(square)
+                .width(SQUARE_SIZE)
+                .height(SQUARE_SIZE)
+                .padding(2)
+                .style(move |_theme| container::Style {
+                    background: Some(Background::Color(background)),
+                    ..container::Style::default()
+                });
+
+            mouse_area(square)
+                .on_press(Message::Editor(EditorMessage::PlaceAt(coordinate)))
+                .on_right_press(Message::Editor(EditorMessage::RemoveAt(coordinate)))
+                .into()
+        });
+
+        Row::with_children(squares).into()
+    });
+
+    column(ranks).into()
+}
+
+/** The square under a point relative to the top left corner of the board. */
+pub(crate) fn coordinate_at(point: Point, flipped: bool) -> Option<Coordinate> {
+    let board_size = SQUARE_SIZE * ROW_SIZE as f32;
+
+    if !(0.0..board_size).contains(&point.x) || !(0.0..board_size).contains(&point.y) {
+        return None;
+    }
+
+    let column = (point.x / SQUARE_SIZE) as usize;
+    let row = (point.y / SQUARE_SIZE) as usize;
+    Some(coordinate_of(column, row, flipped))
+}
+
+/** The square drawn in the column and row counted from the top left corner of the board. */
+fn coordinate_of(column: usize, row: usize, flipped: bool) -> Coordinate {
+    match flipped {
+        false => Coordinate {
+            x: column,
+            y: ROW_SIZE - 1 - row,
+        },
+        true => Coordinate {
+            x: COL_SIZE - 1 - column,
+            y: row,
+        },
+    }
+}
+
+fn is_dark_square(coordinate: Coordinate) -> bool {
+    (coordinate.x + coordinate.y).is_multiple_of(2)
+}
+
+/** A rank number in the top left corner or a file letter in the bottom right corner of the
+square, in the color of the other squares. */
+fn coordinate_label<'a>(coordinate: Coordinate, label: String) -> Element<'a, Message> {
+    let is_rank = label.chars().all(|c| c.is_ascii_digit());
+    let color = match is_dark_square(coordinate) {
+        true => LIGHT_SQUARE,
+        false => DARK_SQUARE,
+    };
+
+    let label = container(text(label).size(13).color(color))
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .padding([0, 2]);
+
+    match is_rank {
+        true => label.align_left(Length::Fill).align_top(Length::Fill),
+        false => label.align_right(Length::Fill).align_bottom(Length::Fill),
+    }
+    .into()
+}
+
+/** The piece on the square, or its faint image while it is dragged, and the move target. */
+fn square_view<'a>(
+    game_state: &'a GameState,
+    piece_images: &'a PieceImages,
+    coordinate: Coordinate,
+    move_target: Option<MoveTarget>,
+) -> Stack<'a, Message> {
+    let piece = game_state
+        .chess_board
+        .piece_at((coordinate.x, coordinate.y));
+    /* The piece being dragged is only shown faintly on its own square. */
+    let is_dragged = game_state
+        .drag
+        .as_ref()
+        .is_some_and(|drag| drag.from == coordinate);
+
+    let mut content = Stack::new().width(Length::Fill).height(Length::Fill);
+
+    if let Some(piece) = piece {
+        content = content.push(
+            svg(piece_images.handle(piece))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .opacity(if is_dragged { 0.3 } else { 1.0 }),
+        );
+    }
+
+    content.push_maybe(move_target.map(move_target_view))
+}
+
+/** The background of the square under its content, pressing it sends `Message::ClickSquare`. */
+fn square_area<'a>(
+    game_state: &GameState,
+    content: Stack<'a, Message>,
+    coordinate: Coordinate,
+) -> Element<'a, Message> {
+    let is_hint = game_state.is_at_last_move()
+        && game_state.hint.is_some_and(|hint| {
+            hint.start_position == coordinate || hint.end_position == coordinate
+        });
+
+    let background = if game_state.selected_square == Some(coordinate) {
+        SELECTED_SQUARE
+    } else if is_hint {
+        HINT_SQUARE
+    } else if is_dark_square(coordinate) {
+        DARK_SQUARE
+    } else {
+        LIGHT_SQUARE
+    };
+
+    let square = container(content)
+        .width(SQUARE_SIZE)
+        .height(SQUARE_SIZE)
+        .padding(2)
+        .style(move |_theme| container::Style {
+            background: Some(Background::Color(background)),
+            ..container::Style::default()
+        });
+
+    mouse_area(square)
+        .on_press(Message::ClickSquare(coordinate))
+        .into()
+}
+
+/** A dot on empty squares and a ring around pieces that can be captured. */
+fn move_target_view<'a>(move_target: MoveTarget) -> Element<'a, Message> {
+    let (size, background, border) = match move_target {
+        MoveTarget::Quiet => (
+            SQUARE_SIZE / 3.0,
+            Some(Background::Color(TARGET_MARKER)),
+            Border::default().rounded(SQUARE_SIZE / 6.0),
+        ),
+        MoveTarget::Capture => (
+            SQUARE_SIZE - 4.0,
+            None,
+            Border::default()
+                .rounded(SQUARE_SIZE / 2.0)
+                .width(SQUARE_SIZE / 12.0)
+                .color(TARGET_MARKER),
+        ),
+    };
+
+    let marker = container(row![])
+        .width(size)
+        .height(size)
+        .style(move |_theme| container::Style {
+            background,
+            border,
+            ..container::Style::default()
+        });
+
+    container(marker).center(Length::Fill).into()
+}
+
+/** The piece being dragged, centered on the cursor. */
+fn dragged_piece_view<'a>(
+    game_state: &'a GameState,
+    piece_images: &'a PieceImages,
+) -> Option<Element<'a, Message>> {
+    let drag = game_state.drag.as_ref()?;
+    let cursor = game_state.cursor_position?;
+    let piece = game_state
+        .chess_board
+        .piece_at((drag.from.x, drag.from.y))?;
+
+    let board_size = SQUARE_SIZE * ROW_SIZE as f32;
+    let offset =
+        |position: f32| (position - SQUARE_SIZE / 2.0).clamp(0.0, board_size - SQUARE_SIZE);
+
+    let piece_view = svg(piece_images.handle(piece))
+        .width(SQUARE_SIZE)
+        .height(SQUARE_SIZE);
+
+    Some(
+        container(piece_view)
+            .padding(Padding {
+                top: offset(cursor.y),
+                left: offset(cursor.x),
+                ..Padding::ZERO
+            })
+            .width(board_size)
+            .height(board_size)
+            .into(),
+    )
+}
+
+/** The promotion picker over a dimmed board. Pressing the board outside the picker cancels the
+promotion and the pawn stays where it was. */
+fn promotion_view<'a>(
+    game_state: &'a GameState,
+    piece_images: &'a PieceImages,
+) -> Option<Element<'a, Message>> {
+    game_state.pending_promotion?;
+
+    let side_to_move = game_state.chess_board.side_to_move();
+    let choices = PROMOTION_PIECES.iter().map(|&piece_type| {
+        let piece_view = svg(piece_images.handle(Piece::new(side_to_move, piece_type)))
+            .width(SQUARE_SIZE)
+            .height(SQUARE_SIZE);
+
+        button(piece_view)
+            .padding(4)
+            .style(button::secondary)
+            .on_press(Message::PromotePawn(piece_type))
+            .into()
+    });
+
+    let picker = container(
+        column![
+            Row::with_children(choices).spacing(8),
+            button("Cancel")
+                .style(button::secondary)
+                .on_press(Message::CancelPromotion),
+        ]
+        .spacing(8)
+        .align_x(Alignment::Center),
+    )
+    .padding(12)
+    .style(container::rounded_box);
+
+    let backdrop = container(opaque(picker))
+        .center(Length::Fill)
+        .style(|_theme| container::Style {
+            background: Some(Background::Color(BACKDROP)),
+            ..container::Style::default()
+        });
+
+    Some(
+        mouse_area(backdrop)
+            .on_press(Message::CancelPromotion)
+            .into(),
+    )
+}
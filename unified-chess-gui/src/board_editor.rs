@@ -0,0 +1,470 @@
+/* This module is the position setup of the GUI. Pieces are placed from a palette and removed with
+ * a right click, and the side to move, the castling rights and the en passant square are set next
+ * to the board. The position is written as a FEN and read back with `ChessBoard::new_from_fen`, so
+ * the game starts from exactly the position a FEN would give, after it is validated. */
+use crate::analysis::{Analysis, DEFAULT_ANALYSIS_LINES};
+use crate::board::{setup_board_view, PieceImages};
+use crate::new_game::NewGameSettings;
+use crate::{ChessApplication, Coordinate, GameState, Message};
+use iced::widget::{button, checkbox, column, pick_list, row, svg, text, Row};
+use iced::{Element, Length, Task};
+use std::fmt;
+use unified_chess_engine::array_engine::{
+    ChessBoard, Color, Piece, PieceType, Position, COL_SIZE, ROW_SIZE,
+};
+use unified_chess_engine::pgn::PgnGame;
+
+const PALETTE_SQUARE_SIZE: f32 = 44.0;
+const EDITOR_WIDTH: f32 = 380.0;
+const PALETTE_PIECES: [PieceType; 6] = [
+    PieceType::King,
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Pawn,
+];
+/* In the order of `ChessBoard::castling_ability`. */
+const CASTLING_LABELS: [&str; 4] = ["White O-O", "White O-O-O", "Black O-O", "Black O-O-O"];
+
+#[derive(Debug, Clone)]
+pub enum EditorMessage {
+    /* Opens the setup with the shown position. */
+    Open,
+    Close,
+    SelectTool(Tool),
+    PlaceAt(Coordinate),
+    RemoveAt(Coordinate),
+    SetSideToMove(Color),
+    SetCastling(usize, bool),
+    SetEnPassant(EnPassantSquare),
+    Clear,
+    StartPosition,
+    /* Opens the new game dialog, the game starts from the position. */
+    Play,
+    Analyse,
+}
+
+/** What pressing a square does: place a piece or empty the square. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tool {
+    Place(Piece),
+    Erase,
+}
+
+/** A choice of the en passant square, the square behind a pawn that just moved two squares. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnPassantSquare(Option<Position>);
+
+impl fmt::Display for EnPassantSquare {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some((file, rank)) => write!(f, "{}{}", (b'a' + file as u8) as char, rank + 1),
+            None => write!(f, "None"),
+        }
+    }
+}
+
+pub(crate) struct BoardEditor {
+    /* The pieces by rank and file, like the board of the engine. */
+    pieces: [[Option<Piece>; COL_SIZE]; ROW_SIZE],
+    side_to_move: Color,
+    castling_ability: [bool; 4],
+    /* The file of the en passant square, its rank follows from the side to move. */
+    en_passant_file: Option<usize>,
+    full_move_counter: u64,
+    tool: Tool,
+}
+
+impl BoardEditor {
+    fn new(chess_board: &ChessBoard) -> BoardEditor {
+        let mut pieces = [[None; COL_SIZE]; ROW_SIZE];
+        for (rank, squares) in pieces.iter_mut().enumerate() {
+            for (file, square) in squares.iter_mut().enumerate() {
+                *square = chess_board.piece_at((file, rank));
+            }
+        }
+
+        BoardEditor {
+            pieces,
+            side_to_move: chess_board.side_to_move(),
+            castling_ability: chess_board.castling_ability(),
+            en_passant_file: chess_board.en_passant_target_square().map(|(file, _)| file),
+            full_move_counter: chess_board.full_move_counter().max(1),
+            tool: Tool::Place(Piece::new(Color::White, PieceType::King)),
+        }
+    }
+
+    fn en_passant_square(&self, file: usize) -> Position {
+        match self.side_to_move {
+            Color::White => (file, 5),
+            Color::Black => (file, 2),
+        }
+    }
+
+    /** The position as a FEN, starting the count of half moves for the fifty move rule anew. */
+    pub(crate) fn to_fen(&self) -> String {
+        let ranks: Vec<String> = self
+            .pieces
+            .iter()
+            .rev()
+            .map(|squares| {
+                let mut rank = String::new();
+                let mut empty_squares = 0;
+                for square in squares {
+                    match square {
+                        Some(piece) => {
+                            if empty_squares > 0 {
+                                rank.push_str(&empty_squares.to_string());
+                                empty_squares = 0;
+                            }
+                            rank.push(piece_char(*piece));
+                        }
+                        None => empty_squares += 1,
+                    }
+                }
+                if empty_squares > 0 {
+                    rank.push_str(&empty_squares.to_string());
+                }
+                rank
+            })
+            .collect();
+
+        let side_to_move = match self.side_to_move {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+        let castling: String = "KQkq"
+            .chars()
+            .zip(self.castling_ability)
+            .filter_map(|(c, can_castle)| can_castle.then_some(c))
+            .collect();
+        let castling = if castling.is_empty() {
+            "-".to_string()
+        } else {
+            castling
+        };
+        let en_passant = match self.en_passant_file {
+            Some(file) => EnPassantSquare(Some(self.en_passant_square(file))).to_string(),
+            None => "-".to_string(),
+        };
+
+        format!(
+            "{} {side_to_move} {castling} {en_passant} 0 {}",
+            ranks.join("/"),
+            self.full_move_counter
+        )
+    }
+
+    /** The position read from its FEN, if it is valid. */
+    pub(crate) fn chess_board(&self) -> Result<ChessBoard, String> {
+        let chess_board = ChessBoard::new_from_fen(&self.to_fen()).map_err(|e| e.to_string())?;
+        chess_board.validate().map_err(|e| e.to_string())?;
+        Ok(chess_board)
+    }
+
+    fn set_square(&mut self, coordinate: Coordinate, piece: Option<Piece>) {
+        self.pieces[coordinate.y][coordinate.x] = piece;
+    }
+}
+
+fn piece_char(piece: Piece) -> char {
+    let c = match piece.piece_type() {
+        PieceType::King => 'k',
+        PieceType::Queen => 'q',
+        PieceType::Rook => 'r',
+        PieceType::Bishop => 'b',
+        PieceType::Knight => 'n',
+        PieceType::Pawn => 'p',
+    };
+
+    match piece.color() {
+        Color::White => c.to_ascii_uppercase(),
+        Color::Black => c,
+    }
+}
+
+impl ChessApplication {
+    pub(crate) fn update_editor(&mut self, message: EditorMessage) -> Task<Message> {
+        if let EditorMessage::Open = message {
+            let chess_board = match &self.game_instance {
+                Some(game_state) => game_state.chess_board,
+                None => ChessBoard::new(),
+            };
+            self.board_editor = Some(BoardEditor::new(&chess_board));
+            return Task::none();
+        }
+
+        let Some(editor) = &mut self.board_editor else {
+            return Task::none();
+        };
+
+        match message {
+            EditorMessage::Open => {}
+            EditorMessage::Close => self.board_editor = None,
+            EditorMessage::SelectTool(tool) => editor.tool = tool,
+            EditorMessage::PlaceAt(coordinate) => match editor.tool {
+                Tool::Place(piece) => editor.set_square(coordinate, Some(piece)),
+                Tool::Erase => editor.set_square(coordinate, None),
+            },
+            EditorMessage::RemoveAt(coordinate) => editor.set_square(coordinate, None),
+            EditorMessage::SetSideToMove(color) => editor.side_to_move = color,
+            EditorMessage::SetCastling(index, can_castle) => {
+                editor.castling_ability[index] = can_castle;
+            }
+            EditorMessage::SetEnPassant(square) => {
+                editor.en_passant_file = square.0.map(|(file, _)| file);
+            }
+            EditorMessage::Clear => {
+                let mut cleared = BoardEditor::new(&ChessBoard::new());
+                cleared.pieces = [[None; COL_SIZE]; ROW_SIZE];
+                cleared.castling_ability = [false; 4];
+                cleared.tool = editor.tool;
+                *editor = cleared;
+            }
+            EditorMessage::StartPosition => {
+                let tool = editor.tool;
+                *editor = BoardEditor::new(&ChessBoard::new());
+                editor.tool = tool;
+            }
+            EditorMessage::Play => {
+                if editor.chess_board().is_ok() {
+                    return self.update(Message::StartNewGame);
+                }
+            }
+            /* Analysis needs a game between humans without clocks. */
+            EditorMessage::Analyse => {
+                if let Ok(chess_board) = editor.chess_board() {
+                    self.board_editor = None;
+                    self.engine.new_game();
+                    let mut game_state =
+                        GameState::from_game(NewGameSettings::default(), PgnGame::new(chess_board));
+                    game_state.analysis = Some(Analysis::new(DEFAULT_ANALYSIS_LINES, chess_board));
+                    self.game_instance = Some(game_state);
+                    self.notice = None;
+                    return self.analyze();
+                }
+            }
+        }
+
+        Task::none()
+    }
+}
+
+fn editor_message(message: EditorMessage) -> Message {
+    Message::Editor(message)
+}
+
+/** The setup board with the palette and the settings of the position beside it. */
+pub(crate) fn board_editor_view<'a>(
+    editor: &'a BoardEditor,
+    piece_images: &'a PieceImages,
+    flipped: bool,
+) -> Element<'a, Message> {
+    let tool_button = |content: Element<'a, Message>, tool: Tool| {
+        let style = match editor.tool == tool {
+            true => button::primary,
+            false => button::secondary,
+        };
+
+        button(content)
+            .padding(2)
+            .style(style)
+            .on_press(editor_message(EditorMessage::SelectTool(tool)))
+    };
+    let palette_row = |color: Color| {
+        let pieces = PALETTE_PIECES.iter().map(move |&piece_type| {
+            let piece = Piece::new(color, piece_type);
+            let image = svg(piece_images.handle(piece))
+                .width(PALETTE_SQUARE_SIZE)
+                .height(PALETTE_SQUARE_SIZE);
+            tool_button(image.into(), Tool::Place(piece)).into()
+        });
+        Row::with_children(pieces).spacing(4)
+    };
+    let eraser = tool_button(
+        text("Erase").height(PALETTE_SQUARE_SIZE).center().into(),
+        Tool::Erase,
+    );
+
+    let side_button = |color: Color, label| {
+        let style = match editor.side_to_move == color {
+            true => button::primary,
+            false => button::secondary,
+        };
+        button(label)
+            .style(style)
+            .on_press(editor_message(EditorMessage::SetSideToMove(color)))
+    };
+
+    let castling = CASTLING_LABELS.iter().enumerate().map(|(index, label)| {
+        checkbox(*label, editor.castling_ability[index])
+            .on_toggle(move |can_castle| {
+                editor_message(EditorMessage::SetCastling(index, can_castle))
+            })
+            .into()
+    });
+
+    let en_passant_choices: Vec<EnPassantSquare> = std::iter::once(EnPassantSquare(None))
+        .chain((0..COL_SIZE).map(|file| EnPassantSquare(Some(editor.en_passant_square(file)))))
+        .collect();
+    let en_passant = EnPassantSquare(
+        editor
+            .en_passant_file
+            .map(|file| editor.en_passant_square(file)),
+    );
+
+    let chess_board = editor.chess_board();
+    let validity = match &chess_board {
+        Ok(_) => "The position is valid".to_string(),
+        Err(e) => e.clone(),
+    };
+
+    let settings = column![
+        text("Set up a position").size(24),
+        palette_row(Color::White),
+        palette_row(Color::Black),
+        row![eraser, text("Right click empties a square")]
+            .spacing(10)
+            .align_y(iced::Alignment::Center),
+        row![
+            side_button(Color::White, "White to move"),
+            side_button(Color::Black, "Black to move"),
+        ]
+        .spacing(10),
+        Row::with_children(castling).spacing(10).wrap(),
+        row![
+            text("En passant"),
+            pick_list(en_passant_choices, Some(en_passant), |square| {
+                editor_message(EditorMessage::SetEnPassant(square))
+            }),
+        ]
+        .spacing(10)
+        .align_y(iced::Alignment::Center),
+        row![
+            button("Clear board").on_press(editor_message(EditorMessage::Clear)),
+            button("Start position").on_press(editor_message(EditorMessage::StartPosition)),
+        ]
+        .spacing(10),
+        text(editor.to_fen()).size(14),
+        text(validity),
+        row![
+            button("Play").on_press_maybe(
+                chess_board
+                    .is_ok()
+                    .then_some(editor_message(EditorMessage::Play))
+            ),
+            button("Analyse").on_press_maybe(
+                chess_board
+                    .is_ok()
+                    .then_some(editor_message(EditorMessage::Analyse))
+            ),
+            button("Cancel")
+                .style(button::secondary)
+                .on_press(editor_message(EditorMessage::Close)),
+        ]
+        .spacing(10),
+    ]
+    .width(Length::Fixed(EDITOR_WIDTH))
+    .spacing(16);
+
+    row![
+        setup_board_view(
+            |coordinate| editor.pieces[coordinate.y][coordinate.x],
+            piece_images,
+            flipped
+        ),
+        settings
+    ]
+    .spacing(20)
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(name: &str) -> Coordinate {
+        let bytes = name.as_bytes();
+        Coordinate {
+            x: (bytes[0] - b'a') as usize,
+            y: (bytes[1] - b'1') as usize,
+        }
+    }
+
+    fn edit(application: &mut ChessApplication, message: EditorMessage) {
+        let _ = application.update(Message::Editor(message));
+    }
+
+    fn place(application: &mut ChessApplication, color: Color, piece_type: PieceType, at: &str) {
+        let piece = Piece::new(color, piece_type);
+        edit(application, EditorMessage::SelectTool(Tool::Place(piece)));
+        edit(application, EditorMessage::PlaceAt(square(at)));
+    }
+
+    #[test]
+    fn test_setup_position() {
+        let mut application = ChessApplication::default();
+        edit(&mut application, EditorMessage::Open);
+        let editor = application.board_editor.as_ref().unwrap();
+        assert_eq!(editor.to_fen(), ChessBoard::new().to_fen());
+
+        edit(&mut application, EditorMessage::Clear);
+        place(&mut application, Color::White, PieceType::King, "e1");
+        place(&mut application, Color::White, PieceType::Rook, "h1");
+        place(&mut application, Color::White, PieceType::Pawn, "e5");
+        place(&mut application, Color::Black, PieceType::Pawn, "d5");
+        edit(&mut application, EditorMessage::SetCastling(0, true));
+        edit(
+            &mut application,
+            EditorMessage::SetEnPassant(EnPassantSquare(Some((3, 5)))),
+        );
+
+        let editor = application.board_editor.as_ref().unwrap();
+        let fen = "8/8/8/3pP3/8/8/8/4K2R w K d6 0 1";
+        assert_eq!(editor.to_fen(), fen);
+        assert_eq!(
+            editor.chess_board().err().as_deref(),
+            Some("Invalid position: Black has no king")
+        );
+
+        place(&mut application, Color::Black, PieceType::King, "e8");
+        place(&mut application, Color::White, PieceType::Knight, "a1");
+        edit(&mut application, EditorMessage::RemoveAt(square("a1")));
+        let editor = application.board_editor.as_ref().unwrap();
+        let fen = "4k3/8/8/3pP3/8/8/8/4K2R w K d6 0 1";
+        assert_eq!(
+            editor.chess_board().map(|chess_board| chess_board.to_fen()),
+            Ok(ChessBoard::new_from_fen(fen).unwrap().to_fen())
+        );
+
+        /* Playing asks for the settings of the game, which starts from the position. */
+        edit(&mut application, EditorMessage::Play);
+        assert!(application.new_game_dialog.is_some());
+        let _ = application.update(Message::ConfirmNewGame);
+        assert!(application.board_editor.is_none());
+        let game_state = application.game_instance.as_ref().unwrap();
+        assert_eq!(game_state.chess_board.to_fen(), fen);
+        assert!(game_state
+            .chess_board
+            .legal_moves()
+            .iter()
+            .any(|chess_move| chess_move.meta_data.is_en_passant_move()));
+
+        edit(&mut application, EditorMessage::Open);
+        edit(&mut application, EditorMessage::SetSideToMove(Color::Black));
+        edit(&mut application, EditorMessage::Analyse);
+        assert!(application.board_editor.is_some());
+        edit(
+            &mut application,
+            EditorMessage::SetEnPassant(EnPassantSquare(None)),
+        );
+        edit(&mut application, EditorMessage::Analyse);
+        let game_state = application.game_instance.as_ref().unwrap();
+        assert!(game_state.analysis.is_some());
+        assert_eq!(
+            game_state.chess_board.to_fen(),
+            "4k3/8/8/3pP3/8/8/8/4K2R b K - 0 1"
+        );
+    }
+}
@@ -1,21 +1,75 @@
 use crate::UserMove;
+use std::fs;
 use std::io;
 use std::str::SplitWhitespace;
-use unified_chess_engine::array_engine::{ChessBoard, Move, Piece, PieceType, Position};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use unified_chess_engine::array_engine::{ChessBoard, Color, Move, PieceType};
+use unified_chess_engine::epd::parse_epd;
+use unified_chess_engine::opening_book::book_builder::{BookBuilder, BookBuilderOptions};
+use unified_chess_engine::opening_book::{BookMoveSelection, OpeningBook};
+use unified_chess_engine::pgn::{GameResult, PgnGame, PgnMove, PgnReader};
+use unified_chess_engine::search::mate_search::{find_mate, mate_length, MateSearchResult};
+use unified_chess_engine::search::test_suite::run_test_suite;
+use unified_chess_engine::search::time_management::{PonderSignal, TimeControl};
+use unified_chess_engine::search::transposition_table::DEFAULT_TABLE_SIZE_MB;
+use unified_chess_engine::search::{
+    SearchInfo, SearchLimits, Searcher, MATE_BOUND, MATE_SCORE, MAX_MULTI_PV, MAX_THREADS,
+};
+use unified_chess_engine::syzygy::{Tablebases, Wdl};
+
+const ENGINE_NAME: &str = "Chess-rs";
+const ENGINE_AUTHOR: &str = "Sebastian Mygind";
+const MAX_TABLE_SIZE_MB: usize = 4096;
+const DEFAULT_TEST_SUITE_MOVE_TIME: Duration = Duration::from_secs(1);
 
 enum Action {
     Quit,
     MakeMove(UserMove),
     RunPerft(i64),
+    FindMate(u32),
+    BuildBook(String, String, BookBuilderOptions),
+    RunTestSuite(String, SearchLimits),
+    /* Probes the tablebases for the given position, or for the current one. */
+    Probe(Option<ChessBoard>),
+    Search(SearchLimits),
+    StopSearch,
+    PonderHit,
+    /* The game given by the position command, from its starting position. */
+    SetPosition(PgnGame),
+    SavePgn(String),
+    SetOption(String, String),
+    Identify,
+    IsReady,
+    NewGame,
     Continue,
-    PrintBoard,
+    /* Prints the board with the given side at the bottom. */
+    PrintBoard(Color),
 }
 
 pub struct UniversalChessInterface {}
 
+/** The engine side of the interface. The search runs on its own thread, so commands like `stop`
+and `isready` are answered while the engine is thinking. */
+struct EngineState {
+    chess_board: ChessBoard,
+    /* Hashes of the positions before the current one, used to detect repetitions. */
+    position_history: Vec<u64>,
+    searcher: Arc<Mutex<Searcher>>,
+    stop_signal: Arc<AtomicBool>,
+    ponder_signal: Arc<PonderSignal>,
+    search_thread: Option<JoinHandle<()>>,
+    own_book: bool,
+    opening_book: Option<OpeningBook>,
+    /* The moves played since the last position command, written by savepgn. */
+    game: PgnGame,
+}
+
 impl UniversalChessInterface {
     pub fn run(_args: Vec<String>) {
-        let mut chess_board: ChessBoard = ChessBoard::new();
+        let mut engine_state = EngineState::new();
 
         println!("{INTRO_STRING}");
 
@@ -23,6 +77,7 @@ impl UniversalChessInterface {
             let mut input = String::new();
 
             match io::stdin().read_line(&mut input) {
+                Ok(0) => break,
                 Ok(_) => {}
                 Err(e) => {
                     println!("{e}");
@@ -37,51 +92,265 @@ impl UniversalChessInterface {
                 Action::Quit => break,
 
                 Action::MakeMove(parsed_move) => {
-                    let mut legal_move: Option<Move> = None;
-                    for chess_move in chess_board.legal_moves() {
-                        if parsed_move.promotion_piece.is_some() {
-                            if chess_move.start_pos == parsed_move.start_position
-                                && chess_move.end_pos == parsed_move.end_position
-                            {
-                                if parsed_move.promotion_piece
-                                    == chess_move.meta_data.promotion_piece
-                                {
-                                    legal_move = Some(chess_move);
-                                    break;
-                                }
-                            }
-                        } else {
-                            if chess_move.start_pos == parsed_move.start_position
-                                && chess_move.end_pos == parsed_move.end_position
-                            {
-                                legal_move = Some(chess_move);
-                                break;
-                            }
-                        }
-                    }
-                    if let Some(move_to_make) = legal_move {
-                        match chess_board.make_move(move_to_make) {
-                            Ok(_) => {}
+                    if let Some(move_to_make) =
+                        parsed_move.find_legal_move(&engine_state.chess_board)
+                    {
+                        engine_state.wait_for_search();
+                        let board_before_move = engine_state.chess_board;
+                        engine_state
+                            .position_history
+                            .push(engine_state.chess_board.zobrist_hash());
+
+                        match engine_state.chess_board.make_move(move_to_make) {
+                            Ok(_) => engine_state
+                                .game
+                                .moves
+                                .push(PgnMove::new(&board_before_move, move_to_make)),
                             Err(e) => {
                                 println!("{e}")
                             }
                         }
-                    } else {
                     }
                 }
 
                 Action::Continue => {}
 
                 Action::RunPerft(depth) => {
-                    chess_board.perft(depth);
+                    engine_state.chess_board.perft(depth);
                 }
 
-                Action::PrintBoard => {
-                    println!("{chess_board}");
+                Action::FindMate(moves) => {
+                    engine_state.wait_for_search();
+                    print_mate_search(&engine_state.chess_board, moves);
                 }
+
+                Action::BuildBook(pgn_path, book_path, options) => {
+                    build_opening_book(&pgn_path, &book_path, options)
+                }
+
+                Action::RunTestSuite(epd_path, limits) => {
+                    engine_state.wait_for_search();
+                    run_epd_test_suite(&mut engine_state.lock_searcher(), &epd_path, limits);
+                }
+
+                Action::Probe(chess_board) => {
+                    engine_state.wait_for_search();
+                    let chess_board = chess_board.unwrap_or(engine_state.chess_board);
+                    print_tablebase_probe(engine_state.lock_searcher().tablebases(), &chess_board);
+                }
+
+                Action::PrintBoard(perspective) => {
+                    println!("{}", engine_state.chess_board.display_from(perspective));
+                }
+
+                Action::Search(limits) => engine_state.start_search(limits),
+
+                Action::StopSearch => engine_state.stop_search(),
+
+                Action::PonderHit => engine_state.ponder_signal.ponderhit(),
+
+                Action::SetPosition(game) => {
+                    engine_state.wait_for_search();
+                    engine_state.chess_board = game.final_position();
+                    engine_state.position_history = game
+                        .positions()
+                        .iter()
+                        .map(|(chess_board, _)| chess_board.zobrist_hash())
+                        .collect();
+                    engine_state.game = game;
+                }
+
+                Action::SavePgn(path) => save_game(&engine_state, &path),
+
+                Action::SetOption(name, value) => engine_state.set_option(&name, &value),
+
+                Action::Identify => print_identification(),
+
+                Action::IsReady => println!("readyok"),
+
+                Action::NewGame => {
+                    engine_state.wait_for_search();
+                    engine_state.lock_searcher().clear();
+                }
+            }
+        }
+
+        engine_state.stop_search();
+    }
+}
+
+impl EngineState {
+    fn new() -> EngineState {
+        let searcher = Searcher::default();
+        let stop_signal = searcher.stop_signal();
+        let ponder_signal = searcher.ponder_signal();
+
+        EngineState {
+            chess_board: ChessBoard::new(),
+            position_history: Vec::new(),
+            searcher: Arc::new(Mutex::new(searcher)),
+            stop_signal,
+            ponder_signal,
+            search_thread: None,
+            own_book: false,
+            opening_book: None,
+            game: PgnGame::default(),
+        }
+    }
+
+    fn lock_searcher(&self) -> std::sync::MutexGuard<'_, Searcher> {
+        self.searcher
+            .lock()
+            .expect("The search thread panicked while holding the searcher")
+    }
+
+    /** Starts a search on a new thread, which prints the best move when the search is done. The
+    second move of the principal variation is sent as the move the engine wants to ponder on. */
+    fn start_search(&mut self, limits: SearchLimits) {
+        self.wait_for_search();
+
+        if let Some(book_move) = self.book_move(&limits) {
+            println!("info string book move");
+            println!("bestmove {}", book_move.move_to_string());
+            return;
+        }
+
+        let chess_board = self.chess_board;
+        let position_history = self.position_history.clone();
+        let searcher = Arc::clone(&self.searcher);
+
+        self.search_thread = Some(thread::spawn(move || {
+            let mut searcher = searcher
+                .lock()
+                .expect("The search thread panicked while holding the searcher");
+
+            searcher.set_position_history(position_history);
+            let result = searcher.search(&chess_board, limits, print_search_info);
+
+            match (result.best_move, result.principal_variation.get(1)) {
+                (Some(best_move), Some(ponder_move)) => println!(
+                    "bestmove {} ponder {}",
+                    best_move.move_to_string(),
+                    ponder_move.move_to_string()
+                ),
+                (Some(best_move), None) => println!("bestmove {}", best_move.move_to_string()),
+                (None, _) => println!("bestmove 0000"),
+            }
+        }));
+    }
+
+    fn stop_search(&mut self) {
+        if self.search_thread.is_some() {
+            self.stop_signal.store(true, Ordering::Relaxed);
+            self.wait_for_search();
+        }
+    }
+
+    fn wait_for_search(&mut self) {
+        if let Some(search_thread) = self.search_thread.take() {
+            if search_thread.join().is_err() {
+                println!("info string the search thread panicked");
+            }
+        }
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) {
+        self.wait_for_search();
+
+        match name.to_lowercase().as_str() {
+            /* Pondering is controlled by the GUI with `go ponder`, the option only announces it. */
+            "ponder" => return,
+            "ownbook" => {
+                self.own_book = value.eq_ignore_ascii_case("true");
+                return;
+            }
+            "bookfile" => {
+                self.load_opening_book(value);
+                return;
+            }
+            "syzygypath" => {
+                self.load_tablebases(value);
+                return;
             }
+            _ => {}
+        }
+
+        match (name.to_lowercase().as_str(), value.parse::<usize>()) {
+            ("threads", Ok(threads)) => self.lock_searcher().set_threads(threads),
+            ("multipv", Ok(multi_pv)) => self.lock_searcher().set_multi_pv(multi_pv),
+            ("hash", Ok(table_size_mb)) => self
+                .lock_searcher()
+                .set_table_size(table_size_mb.clamp(1, MAX_TABLE_SIZE_MB)),
+            _ => println!("info string unknown option {name} with value {value}"),
         }
     }
+
+    fn load_opening_book(&mut self, path: &str) {
+        if path.is_empty() || path == "<empty>" {
+            self.opening_book = None;
+            return;
+        }
+
+        match OpeningBook::open(path) {
+            Ok(opening_book) => {
+                println!("info string loaded {} book entries", opening_book.len());
+                self.opening_book = Some(opening_book);
+            }
+            Err(e) => {
+                println!("info string {e}");
+                self.opening_book = None;
+            }
+        }
+    }
+
+    fn load_tablebases(&mut self, path: &str) {
+        if path.is_empty() || path == "<empty>" {
+            self.lock_searcher().set_tablebases(None);
+            return;
+        }
+
+        match Tablebases::new(path) {
+            Ok(tablebases) => {
+                println!(
+                    "info string found {} tablebase files with up to {} pieces",
+                    tablebases.table_count(),
+                    tablebases.max_pieces()
+                );
+                self.lock_searcher()
+                    .set_tablebases(Some(Arc::new(tablebases)));
+            }
+            Err(e) => {
+                println!("info string {e}");
+                self.lock_searcher().set_tablebases(None);
+            }
+        }
+    }
+
+    /** Returns a move from the opening book, if the book is enabled and knows the position. */
+    fn book_move(&self, limits: &SearchLimits) -> Option<Move> {
+        if !self.own_book || limits.infinite || limits.ponder || limits.mate.is_some() {
+            return None;
+        }
+
+        self.opening_book
+            .as_ref()?
+            .pick_move(&self.chess_board, BookMoveSelection::WeightedRandom)
+    }
+}
+
+fn print_identification() {
+    println!("id name {ENGINE_NAME}");
+    println!("id author {ENGINE_AUTHOR}");
+    println!(
+        "option name Hash type spin default {DEFAULT_TABLE_SIZE_MB} min 1 max {MAX_TABLE_SIZE_MB}"
+    );
+    println!("option name Threads type spin default 1 min 1 max {MAX_THREADS}");
+    println!("option name Ponder type check default false");
+    println!("option name OwnBook type check default false");
+    println!("option name BookFile type string default <empty>");
+    println!("option name MultiPV type spin default 1 min 1 max {MAX_MULTI_PV}");
+    println!("option name SyzygyPath type string default <empty>");
+    println!("uciok");
 }
 
 fn handle_args(mut args: SplitWhitespace) -> Action {
@@ -93,7 +362,14 @@ fn handle_args(mut args: SplitWhitespace) -> Action {
     match argument {
         "quit" | "exit" => Action::Quit,
 
-        "print" => Action::PrintBoard,
+        "print" => match args.next() {
+            Some("black") => Action::PrintBoard(Color::Black),
+            Some("white") | None => Action::PrintBoard(Color::White),
+            Some(side) => {
+                println!("Invalid side to print the board from: {side}");
+                Action::Continue
+            }
+        },
 
         "perft" => {
             if let Some(depth) = args.next() {
@@ -105,6 +381,60 @@ fn handle_args(mut args: SplitWhitespace) -> Action {
             Action::Continue
         }
 
+        "mate" => {
+            if let Some(moves) = args.next().and_then(|moves| moves.parse::<u32>().ok()) {
+                if moves > 0 {
+                    return Action::FindMate(moves);
+                }
+            }
+            println!("You must provide a number of moves from 1 and up!");
+            Action::Continue
+        }
+
+        "buildbook" => parse_build_book(args),
+
+        "testsuite" => parse_test_suite(args),
+
+        "probe" => {
+            let fen: Vec<&str> = args.collect();
+
+            if fen.is_empty() {
+                return Action::Probe(None);
+            }
+
+            match ChessBoard::new_from_fen(&fen.join(" ")) {
+                Ok(chess_board) => Action::Probe(Some(chess_board)),
+                Err(e) => {
+                    println!("{e}");
+                    Action::Continue
+                }
+            }
+        }
+
+        "uci" => Action::Identify,
+
+        "isready" => Action::IsReady,
+
+        "ucinewgame" => Action::NewGame,
+
+        "stop" => Action::StopSearch,
+
+        "ponderhit" => Action::PonderHit,
+
+        "setoption" => parse_option(args),
+
+        "position" => parse_position(args),
+
+        "go" => Action::Search(parse_search_limits(args)),
+
+        "savepgn" => match args.next() {
+            Some(path) => Action::SavePgn(path.to_string()),
+            None => {
+                println!("Expected: savepgn <pgn file>");
+                Action::Continue
+            }
+        },
+
         "move" => {
             if let Some(move_string) = args.next() {
                 if let Some(move_to_make) = parse_move_string(move_string) {
@@ -122,56 +452,403 @@ fn handle_args(mut args: SplitWhitespace) -> Action {
     }
 }
 
+/** Parses the limits given after the go command, e.g. `go depth 6`, `go movetime 1000` or
+`go wtime 60000 btime 60000 winc 1000 binc 1000`, or `go mate 3` to search for a forced mate. */
+fn parse_search_limits(mut args: SplitWhitespace) -> SearchLimits {
+    let mut limits = SearchLimits::default();
+    let mut time_control = TimeControl::default();
+    let mut has_clock = false;
+
+    while let Some(argument) = args.next() {
+        if argument == "infinite" {
+            limits.infinite = true;
+            continue;
+        }
+
+        if argument == "ponder" {
+            limits.ponder = true;
+            continue;
+        }
+
+        let value = args.next().and_then(|value| value.parse::<u64>().ok());
+
+        match (argument, value) {
+            ("depth", Some(depth)) => limits.depth = Some(depth as u32),
+            ("movetime", Some(milliseconds)) => {
+                limits.move_time = Some(Duration::from_millis(milliseconds))
+            }
+            ("nodes", Some(nodes)) => limits.nodes = Some(nodes),
+            ("wtime", Some(milliseconds)) => {
+                time_control.white_time = Some(Duration::from_millis(milliseconds));
+                has_clock = true;
+            }
+            ("btime", Some(milliseconds)) => {
+                time_control.black_time = Some(Duration::from_millis(milliseconds));
+                has_clock = true;
+            }
+            ("winc", Some(milliseconds)) => {
+                time_control.white_increment = Duration::from_millis(milliseconds)
+            }
+            ("binc", Some(milliseconds)) => {
+                time_control.black_increment = Duration::from_millis(milliseconds)
+            }
+            ("movestogo", Some(moves)) => time_control.moves_to_go = Some(moves as u32),
+            ("mate", Some(moves)) if moves > 0 => limits.mate = Some(moves as u32),
+            _ => println!("Invalid search limit: {argument}"),
+        }
+    }
+
+    if has_clock {
+        limits.time_control = Some(time_control);
+    }
+
+    limits
+}
+
+/** Parses `setoption name <name> value <value>`, where the name may contain spaces. */
+fn parse_option(mut args: SplitWhitespace) -> Action {
+    if args.next() != Some("name") {
+        println!("Expected: setoption name <name> value <value>");
+        return Action::Continue;
+    }
+
+    let mut name: Vec<&str> = Vec::new();
+    let mut value: Vec<&str> = Vec::new();
+    let mut reading_value = false;
+
+    for argument in args {
+        if argument == "value" && !reading_value {
+            reading_value = true;
+        } else if reading_value {
+            value.push(argument);
+        } else {
+            name.push(argument);
+        }
+    }
+
+    Action::SetOption(name.join(" "), value.join(" "))
+}
+
+/** Parses `position startpos moves ...` and `position fen <fen> moves ...`. */
+fn parse_position(mut args: SplitWhitespace) -> Action {
+    let mut chess_board = match args.next() {
+        Some("startpos") => ChessBoard::new(),
+
+        Some("fen") => {
+            let fen: Vec<&str> = args.by_ref().take_while(|arg| *arg != "moves").collect();
+
+            match ChessBoard::new_from_fen(&fen.join(" ")) {
+                Ok(chess_board) => chess_board,
+                Err(e) => {
+                    println!("info string {e}");
+                    return Action::Continue;
+                }
+            }
+        }
+
+        _ => {
+            println!("Expected: position [startpos | fen <fen>] moves ...");
+            return Action::Continue;
+        }
+    };
+
+    let mut game = PgnGame::new(chess_board);
+
+    for move_string in args.filter(|arg| *arg != "moves") {
+        let legal_move = parse_move_string(move_string)
+            .and_then(|user_move| user_move.find_legal_move(&chess_board));
+
+        match legal_move {
+            Some(legal_move) => {
+                game.moves.push(PgnMove::new(&chess_board, legal_move));
+                chess_board.make_move_on_board(&legal_move);
+                chess_board.update_meta_data(&legal_move);
+            }
+            None => {
+                println!("info string illegal move in position command: {move_string}");
+                return Action::Continue;
+            }
+        }
+    }
+
+    Action::SetPosition(game)
+}
+
+/** Parses `buildbook <pgn file> <book file> [maxply <plies>] [mingames <games>] [color <color>]`. */
+fn parse_build_book(mut args: SplitWhitespace) -> Action {
+    let (Some(pgn_path), Some(book_path)) = (args.next(), args.next()) else {
+        println!("Expected: buildbook <pgn file> <book file> [maxply N] [mingames N] [color white|black]");
+        return Action::Continue;
+    };
+
+    let mut options = BookBuilderOptions::default();
+
+    while let (Some(name), Some(value)) = (args.next(), args.next()) {
+        let valid = match (name, value) {
+            ("maxply", value) => value.parse().map(|plies| options.max_ply = plies).is_ok(),
+            ("mingames", value) => value.parse().map(|games| options.min_games = games).is_ok(),
+            ("color", "white") => {
+                options.only_color = Some(Color::White);
+                true
+            }
+            ("color", "black") => {
+                options.only_color = Some(Color::Black);
+                true
+            }
+            _ => false,
+        };
+
+        if !valid {
+            println!("Invalid book option: {name} {value}");
+            return Action::Continue;
+        }
+    }
+
+    Action::BuildBook(pgn_path.to_string(), book_path.to_string(), options)
+}
+
+/** Parses `testsuite <epd file> [depth <plies>] [movetime <ms>]`, searching one second per
+position without limits. */
+fn parse_test_suite(mut args: SplitWhitespace) -> Action {
+    let Some(epd_path) = args.next() else {
+        println!("Expected: testsuite <epd file> [depth N] [movetime ms]");
+        return Action::Continue;
+    };
+
+    let mut limits = SearchLimits::default();
+
+    while let (Some(name), Some(value)) = (args.next(), args.next()) {
+        let valid = match name {
+            "depth" => value
+                .parse()
+                .map(|depth| limits.depth = Some(depth))
+                .is_ok(),
+            "movetime" => value
+                .parse()
+                .map(|ms| limits.move_time = Some(Duration::from_millis(ms)))
+                .is_ok(),
+            _ => false,
+        };
+
+        if !valid {
+            println!("Invalid test suite limit: {name} {value}");
+            return Action::Continue;
+        }
+    }
+
+    if limits.depth.is_none() && limits.move_time.is_none() {
+        limits.move_time = Some(DEFAULT_TEST_SUITE_MOVE_TIME);
+    }
+
+    Action::RunTestSuite(epd_path.to_string(), limits)
+}
+
+fn run_epd_test_suite(searcher: &mut Searcher, epd_path: &str, limits: SearchLimits) {
+    let suite = match fs::read_to_string(epd_path) {
+        Ok(text) => match parse_epd(&text) {
+            Ok(suite) => suite,
+            Err(e) => {
+                println!("{e}");
+                return;
+            }
+        },
+        Err(e) => {
+            println!("Could not read {epd_path}: {e}");
+            return;
+        }
+    };
+
+    let mut searched = 0;
+    let report = run_test_suite(searcher, &suite, limits, |result| {
+        searched += 1;
+        let outcome = if result.solved { "solved" } else { "failed" };
+        println!("[{searched}/{}] {} {outcome}", suite.len(), result.id);
+    });
+
+    match report {
+        Ok(report) => println!("{report}"),
+        Err(e) => println!("{e}"),
+    }
+}
+
+fn build_opening_book(pgn_path: &str, book_path: &str, options: BookBuilderOptions) {
+    let pgn_reader = match PgnReader::open(pgn_path) {
+        Ok(pgn_reader) => pgn_reader,
+        Err(e) => {
+            println!("Could not read {pgn_path}: {e}");
+            return;
+        }
+    };
+
+    let mut builder = BookBuilder::new(options);
+    let mut skipped_games = 0;
+
+    for game in pgn_reader {
+        match game {
+            Ok(game) => builder.add_game(&game),
+            Err(_) => skipped_games += 1,
+        }
+    }
+
+    let opening_book = builder.build();
+
+    match opening_book.save(book_path) {
+        Ok(()) => println!(
+            "Wrote {} entries from {} games to {book_path}, skipped {skipped_games} invalid games",
+            opening_book.len(),
+            builder.games()
+        ),
+        Err(e) => println!("{e}"),
+    }
+}
+
+/** Writes the current game to a PGN file, with the result if the game is over. */
+fn save_game(engine_state: &EngineState, path: &str) {
+    let mut game = engine_state.game.clone();
+    let chess_board = engine_state.chess_board;
+
+    if chess_board.legal_moves().is_empty() {
+        game.result = match (chess_board.king_is_in_check(), chess_board.side_to_move()) {
+            (true, Color::White) => GameResult::BlackWins,
+            (true, Color::Black) => GameResult::WhiteWins,
+            (false, _) => GameResult::Draw,
+        };
+    }
+
+    game.set_tag("Event", "Chess-rs CLI game");
+
+    match fs::write(path, game.to_string()) {
+        Ok(()) => println!("Wrote {} moves to {path}", game.moves.len()),
+        Err(e) => println!("Could not write {path}: {e}"),
+    }
+}
+
+fn print_mate_search(chess_board: &ChessBoard, moves: u32) {
+    match find_mate(chess_board, moves, &AtomicBool::new(false)) {
+        MateSearchResult::Mate(mating_line) => {
+            let moves: Vec<String> = mating_line
+                .iter()
+                .map(|chess_move| chess_move.move_to_string())
+                .collect();
+
+            println!("Mate in {}: {}", mate_length(&mating_line), moves.join(" "));
+        }
+        MateSearchResult::NoMate => println!("There is no forced mate in {moves}"),
+        MateSearchResult::Stopped => println!("The mate search was stopped"),
+    }
+}
+
+/** Prints the tablebase result of the position and the moves that keep the best result. */
+fn print_tablebase_probe(tablebases: Option<&Tablebases>, chess_board: &ChessBoard) {
+    let Some(tablebases) = tablebases else {
+        println!("No tablebases are loaded, set the SyzygyPath option first");
+        return;
+    };
+
+    if !tablebases.can_probe(chess_board) {
+        println!(
+            "The position has castling rights or more than {} pieces",
+            tablebases.max_pieces()
+        );
+        return;
+    }
+
+    let wdl = match tablebases.probe_wdl(chess_board) {
+        Some(Wdl::Win) => "win",
+        Some(Wdl::CursedWin) => "cursed win",
+        Some(Wdl::Draw) => "draw",
+        Some(Wdl::BlessedLoss) => "blessed loss",
+        Some(Wdl::Loss) => "loss",
+        None => {
+            println!("The table of the position is missing");
+            return;
+        }
+    };
+    println!("WDL: {wdl}");
+
+    match tablebases.probe_dtz(chess_board) {
+        Some(dtz) => println!("DTZ: {dtz}"),
+        None => println!("DTZ: the table of the position is missing"),
+    }
+
+    if let Some(ranked_moves) = tablebases.probe_root(chess_board, &[]) {
+        let best_moves: Vec<String> = ranked_moves
+            .iter()
+            .take_while(|ranked_move| ranked_move.rank == ranked_moves[0].rank)
+            .map(|ranked_move| {
+                format!(
+                    "{} (dtz {})",
+                    ranked_move.chess_move.move_to_string(),
+                    ranked_move.dtz
+                )
+            })
+            .collect();
+        println!("Best moves: {}", best_moves.join(" "));
+    }
+}
+
+fn print_search_info(info: &SearchInfo) {
+    let milliseconds = info.elapsed.as_millis().max(1);
+    let nodes_per_second = info.nodes as u128 * 1000 / milliseconds;
+
+    let score = if info.score.abs() >= MATE_BOUND {
+        let plies_to_mate = MATE_SCORE - info.score.abs();
+        let moves_to_mate = (plies_to_mate + 1) / 2;
+
+        format!("mate {}", moves_to_mate * info.score.signum())
+    } else {
+        format!("cp {}", info.score)
+    };
+
+    let principal_variation: Vec<String> = info
+        .principal_variation
+        .iter()
+        .map(|chess_move| chess_move.move_to_string())
+        .collect();
+
+    println!(
+        "info depth {} multipv {} score {score} nodes {} nps {nodes_per_second} time {milliseconds} pv {}",
+        info.depth,
+        info.multi_pv,
+        info.nodes,
+        principal_variation.join(" ")
+    );
+}
+
 fn parse_move_string(move_string: &str) -> Option<UserMove> {
     let mut move_chars = move_string.chars();
 
     let start_column: usize = if let Some(char) = move_chars.next() {
-        match char_move_file_to_usize(char) {
-            Some(num) => num,
-            None => return None,
-        }
+        char_move_file_to_usize(char)?
     } else {
         return None;
     };
 
     let start_row: usize = if let Some(char) = move_chars.next() {
-        match char_move_rank_to_usize(char) {
-            Some(num) => num,
-            None => return None,
-        }
+        char_move_rank_to_usize(char)?
     } else {
         return None;
     };
 
     let end_column: usize = if let Some(char) = move_chars.next() {
-        match char_move_file_to_usize(char) {
-            Some(num) => num,
-            None => return None,
-        }
+        char_move_file_to_usize(char)?
     } else {
         return None;
     };
 
     let end_row: usize = if let Some(char) = move_chars.next() {
-        match char_move_rank_to_usize(char) {
-            Some(num) => num,
-            None => return None,
-        }
+        char_move_rank_to_usize(char)?
     } else {
         return None;
     };
 
     match move_chars.next() {
         Some(promotion_char) => {
-            if let Some(piece) = char_move_promotion_to_piece(promotion_char) {
-                Some(UserMove {
-                    start_position: (start_column, start_row),
-                    end_position: (end_column, end_row),
-                    promotion_piece: Some(piece),
-                })
-            } else {
-                None
-            }
+            char_move_promotion_to_piece(promotion_char).map(|piece| UserMove {
+                start_position: (start_column, start_row),
+                end_position: (end_column, end_row),
+                promotion_piece: Some(piece),
+            })
         }
 
         None => Some(UserMove {
@@ -0,0 +1,332 @@
+/* This module keeps the chess clocks of the GUI. A time control has one or more periods, each
+ * giving time for a number of moves or for the rest of the game, and a bonus for every move: a
+ * Fischer increment added after the move, a Bronstein delay giving back the time used up to the
+ * delay, or a simple delay before the clock starts counting down. Only the clock of the side to
+ * move runs, and it can be stopped to pause a casual game. */
+use crate::Message;
+use iced::widget::{container, text};
+use iced::{Background, Element, Length};
+use std::fmt;
+use std::time::{Duration, Instant};
+use unified_chess_engine::array_engine::Color;
+
+const RUNNING_CLOCK: iced::Color = iced::Color::from_rgb(0.93, 0.93, 0.93);
+const WAITING_CLOCK: iced::Color = iced::Color::from_rgb(0.25, 0.25, 0.27);
+/* Below this time the clock shows tenths of seconds. */
+const LOW_TIME: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Period {
+    /* The moves to play in the period, None for the rest of the game. */
+    pub moves: Option<u32>,
+    pub time: Duration,
+}
+
+impl Period {
+    pub const fn moves_in(moves: u32, minutes: u64) -> Period {
+        Period {
+            moves: Some(moves),
+            time: Duration::from_secs(60 * minutes),
+        }
+    }
+
+    pub const fn rest_of_game(minutes: u64) -> Period {
+        Period {
+            moves: None,
+            time: Duration::from_secs(60 * minutes),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bonus {
+    /* Sudden death, only the time of the periods. */
+    None,
+    Increment(Duration),
+    Bronstein(Duration),
+    Delay(Duration),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeControl {
+    /* A last period with a number of moves is repeated for the rest of the game. */
+    pub periods: &'static [Period],
+    pub bonus: Bonus,
+}
+
+impl TimeControl {
+    /** The time a move earns on average, told to the engine as its increment. */
+    pub fn bonus_time(&self) -> Duration {
+        match self.bonus {
+            Bonus::None => Duration::ZERO,
+            Bonus::Increment(time) | Bonus::Bronstein(time) | Bonus::Delay(time) => time,
+        }
+    }
+}
+
+/** Writes the time control like "3+2", "40/90, 30+30" or "5 delay 3s", with the times of the
+periods in minutes and the bonus in seconds. */
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, period) in self.periods.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            if let Some(moves) = period.moves {
+                write!(f, "{moves}/")?;
+            }
+            write!(f, "{}", period.time.as_secs() / 60)?;
+        }
+
+        match self.bonus {
+            Bonus::None => write!(f, "+0"),
+            Bonus::Increment(time) => write!(f, "+{}", time.as_secs()),
+            Bonus::Bronstein(time) => write!(f, " Bronstein {}s", time.as_secs()),
+            Bonus::Delay(time) => write!(f, " delay {}s", time.as_secs()),
+        }
+    }
+}
+
+pub struct Clock {
+    time_control: TimeControl,
+    /* The time of white and black, not counting the current move of the side to move. */
+    remaining: [Duration; 2],
+    /* The period each side plays in, and the moves it played in that period. */
+    periods: [usize; 2],
+    period_moves: [u32; 2],
+    side_to_move: Color,
+    /* The time the side to move used before the clock was last stopped. */
+    spent: Duration,
+    /* When the clock was last started, None while it is stopped. */
+    running_since: Option<Instant>,
+}
+
+fn color_index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+impl Clock {
+    /** A stopped clock with the time of the first period for both sides. */
+    pub fn new(time_control: TimeControl, side_to_move: Color) -> Clock {
+        let time = time_control.periods[0].time;
+
+        Clock {
+            time_control,
+            remaining: [time, time],
+            periods: [0, 0],
+            period_moves: [0, 0],
+            side_to_move,
+            spent: Duration::ZERO,
+            running_since: None,
+        }
+    }
+
+    pub fn time_control(&self) -> TimeControl {
+        self.time_control
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running_since.is_some()
+    }
+
+    /** Starts the clock of the side to move, or lets it continue after it was stopped. */
+    pub fn start(&mut self, now: Instant) {
+        if self.running_since.is_none() {
+            self.running_since = Some(now);
+        }
+    }
+
+    pub fn stop(&mut self, now: Instant) {
+        if let Some(running_since) = self.running_since.take() {
+            self.spent += now.saturating_duration_since(running_since);
+        }
+    }
+
+    /** The time the side to move has used for its move. */
+    fn spent(&self, now: Instant) -> Duration {
+        match self.running_since {
+            Some(running_since) => self.spent + now.saturating_duration_since(running_since),
+            None => self.spent,
+        }
+    }
+
+    /** The time left on the clock of the color. */
+    pub fn remaining(&self, color: Color, now: Instant) -> Duration {
+        let remaining = self.remaining[color_index(color)];
+        if color != self.side_to_move {
+            return remaining;
+        }
+
+        let spent = self.spent(now);
+        let counted = match self.time_control.bonus {
+            Bonus::Delay(delay) => spent.saturating_sub(delay),
+            _ => spent,
+        };
+        remaining.saturating_sub(counted)
+    }
+
+    /** Whether the side to move ran out of time. */
+    pub fn has_flagged(&self, now: Instant) -> bool {
+        self.remaining(self.side_to_move, now).is_zero()
+    }
+
+    /** The moves the color has to play before its next period, None in the last period. */
+    pub fn moves_to_go(&self, color: Color) -> Option<u32> {
+        let index = color_index(color);
+        let period = self.time_control.periods[self.periods[index]];
+        period
+            .moves
+            .map(|moves| moves.saturating_sub(self.period_moves[index]))
+    }
+
+    /** Ends the move of the side to move and runs the clock of the other side. The bonus is added
+    to the remaining time, and so is the time of the next period once its moves are played.
+    Returns the time left to the side that moved. */
+    pub fn press(&mut self, now: Instant) -> Duration {
+        let index = color_index(self.side_to_move);
+        let spent = self.spent(now);
+        let mut remaining = self.remaining(self.side_to_move, now);
+
+        remaining += match self.time_control.bonus {
+            Bonus::Increment(increment) => increment,
+            Bonus::Bronstein(delay) => spent.min(delay),
+            Bonus::None | Bonus::Delay(_) => Duration::ZERO,
+        };
+
+        self.period_moves[index] += 1;
+        let periods = self.time_control.periods;
+        if periods[self.periods[index]].moves == Some(self.period_moves[index]) {
+            self.period_moves[index] = 0;
+            self.periods[index] = (self.periods[index] + 1).min(periods.len() - 1);
+            remaining += periods[self.periods[index]].time;
+        }
+
+        self.remaining[index] = remaining;
+        self.side_to_move = self.side_to_move.opposite();
+        self.spent = Duration::ZERO;
+        if self.running_since.is_some() {
+            self.running_since = Some(now);
+        }
+
+        remaining
+    }
+}
+
+/** Writes the time like 1:05:00, 4:59 or 0:09.3 in the last seconds. */
+pub fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+
+    if time < LOW_TIME {
+        format!("0:{:02}.{}", seconds, time.subsec_millis() / 100)
+    } else if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/** The clock of the color, highlighted while it runs. */
+pub(crate) fn clock_view<'a>(clock: &Clock, color: Color, now: Instant) -> Element<'a, Message> {
+    let name = match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    };
+    let is_running = clock.is_running() && clock.side_to_move() == color;
+    let (background, text_color) = match is_running {
+        true => (RUNNING_CLOCK, WAITING_CLOCK),
+        false => (WAITING_CLOCK, RUNNING_CLOCK),
+    };
+
+    let label = format!("{name}  {}", format_time(clock.remaining(color, now)));
+
+    container(text(label).size(24).color(text_color))
+        .width(Length::Fixed(220.0))
+        .padding([4, 12])
+        .style(move |_theme| container::Style {
+            background: Some(Background::Color(background)),
+            ..container::Style::default()
+        })
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+    const ONE_MINUTE: [Period; 1] = [Period::rest_of_game(1)];
+
+    #[test]
+    fn test_bonuses() {
+        let start = Instant::now();
+        let seconds = |n: u64| start + Duration::from_secs(n);
+        let clock = |bonus| {
+            let mut clock = Clock::new(
+                TimeControl {
+                    periods: &ONE_MINUTE,
+                    bonus,
+                },
+                Color::White,
+            );
+            clock.start(start);
+            clock
+        };
+
+        let mut fischer = clock(Bonus::Increment(2 * SECOND));
+        assert_eq!(fischer.press(seconds(5)), 57 * SECOND);
+        assert_eq!(fischer.remaining(Color::Black, seconds(6)), 59 * SECOND);
+
+        /* Bronstein gives back at most the delay, the simple delay waits before counting. */
+        let mut bronstein = clock(Bonus::Bronstein(3 * SECOND));
+        assert_eq!(bronstein.press(seconds(2)), 60 * SECOND);
+        let mut delay = clock(Bonus::Delay(3 * SECOND));
+        assert_eq!(delay.remaining(Color::White, seconds(2)), 60 * SECOND);
+        assert_eq!(delay.press(seconds(5)), 58 * SECOND);
+
+        let mut sudden_death = clock(Bonus::None);
+        sudden_death.stop(seconds(10));
+        assert_eq!(
+            sudden_death.remaining(Color::White, seconds(100)),
+            50 * SECOND
+        );
+        sudden_death.start(seconds(100));
+        assert!(!sudden_death.has_flagged(seconds(149)));
+        assert!(sudden_death.has_flagged(seconds(150)));
+    }
+
+    #[test]
+    fn test_periods() {
+        const PERIODS: [Period; 2] = [Period::moves_in(2, 1), Period::rest_of_game(1)];
+        let start = Instant::now();
+        let time_control = TimeControl {
+            periods: &PERIODS,
+            bonus: Bonus::Increment(10 * SECOND),
+        };
+        assert_eq!(time_control.to_string(), "2/1, 1+10");
+
+        let mut clock = Clock::new(time_control, Color::White);
+        clock.start(start);
+        for _ in 0..3 {
+            clock.press(start);
+            clock.press(start);
+        }
+
+        /* Two moves in the first period and one more in the second. */
+        assert_eq!(clock.remaining(Color::White, start), 150 * SECOND);
+        assert_eq!(clock.moves_to_go(Color::Black), None);
+        assert_eq!(format_time(3725 * SECOND), "1:02:05");
+        assert_eq!(format_time(Duration::from_millis(9_450)), "0:09.4");
+    }
+}
@@ -0,0 +1,603 @@
+/* This module plays matches between two UCI engines running as local processes, e.g. two builds
+ * of this program in uci-mode. Every opening is played twice with the colors swapped, several
+ * games can run at the same time, and the finished games are written to a PGN file. The match
+ * reports the score with the Elo difference and the likelihood of superiority of the first
+ * engine. Usage:
+ *
+ *   unified-chess match -engine cmd=<command> [name=<name>] [arg=<argument>]... [option.<name>=<value>]...
+ *                       -engine ... [-each <engine settings for both engines>]
+ *                       [-tc <seconds>[+<increment>] | -st <seconds> | -depth <plies> | -nodes <nodes>]
+ *                       [-games <games>] [-concurrency <games>] [-openings file=<epd or pgn> [plies=<plies>]]
+ *                       [-resign movecount=<moves> score=<cp>] [-draw movenumber=<move> movecount=<moves> score=<cp>]
+ *                       [-tb <syzygy path>] [-pgnout <file>]
+ */
+mod game;
+pub mod sprt;
+pub mod statistics;
+pub mod uci_engine;
+
+use game::{play_game, DrawAdjudication, ResignAdjudication};
+use statistics::MatchScore;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use uci_engine::{EngineConfig, UciEngine};
+use unified_chess_engine::array_engine::{ChessBoard, Move};
+use unified_chess_engine::epd::parse_epd;
+use unified_chess_engine::pgn::{GameResult, PgnGame, PgnReader};
+use unified_chess_engine::syzygy::Tablebases;
+
+const DEFAULT_TIME_CONTROL: MatchLimit = MatchLimit::Clock {
+    base: Duration::from_secs(10),
+    increment: Duration::from_millis(100),
+};
+
+/** The limit of each move of a game. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchLimit {
+    Clock { base: Duration, increment: Duration },
+    MoveTime(Duration),
+    Depth(u32),
+    Nodes(u64),
+}
+
+/** A position the games start from, with the moves played to reach it. */
+#[derive(Debug, Clone)]
+pub struct Opening {
+    pub starting_position: ChessBoard,
+    pub moves: Vec<Move>,
+}
+
+pub struct MatchSettings {
+    pub engines: [EngineConfig; 2],
+    pub limit: MatchLimit,
+    pub games: usize,
+    pub concurrency: usize,
+    pub openings: Vec<Opening>,
+    pub resign: Option<ResignAdjudication>,
+    pub draw: Option<DrawAdjudication>,
+    /* Games are adjudicated as soon as they reach a position of these tablebases. */
+    pub tablebases: Option<Arc<Tablebases>>,
+    pub pgn_output: Option<String>,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        MatchSettings {
+            engines: [EngineConfig::default(), EngineConfig::default()],
+            limit: DEFAULT_TIME_CONTROL,
+            games: 2,
+            concurrency: 1,
+            openings: Vec::new(),
+            resign: None,
+            draw: None,
+            tablebases: None,
+            pgn_output: None,
+        }
+    }
+}
+
+/** A game of the match. Game 2n and 2n + 1 are a pair, they start from the same opening with the
+colors swapped. */
+pub struct FinishedGame {
+    pub index: usize,
+    pub game: PgnGame,
+    pub reason: String,
+    pub first_engine_white: bool,
+}
+
+impl FinishedGame {
+    /** The points of the first engine: 1 for a win, 0.5 for a draw and 0 for a loss. */
+    pub fn first_engine_score(&self) -> Option<f64> {
+        let white_score = match self.game.result {
+            GameResult::WhiteWins => 1.0,
+            GameResult::BlackWins => 0.0,
+            GameResult::Draw => 0.5,
+            GameResult::Unknown => return None,
+        };
+
+        Some(if self.first_engine_white {
+            white_score
+        } else {
+            1.0 - white_score
+        })
+    }
+}
+
+/** Plays the games of the match and passes each finished game to `on_game`, in the order they
+finish. No new games are started once `on_game` returns false. Fails if an engine cannot be
+started. */
+pub fn play_match<F: FnMut(&FinishedGame) -> bool>(
+    settings: &MatchSettings,
+    mut on_game: F,
+) -> Result<(), String> {
+    let next_game = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    let mut error: Option<String> = None;
+
+    thread::scope(|scope| {
+        for _ in 0..settings.concurrency.max(1) {
+            let sender = sender.clone();
+            let (next_game, stop) = (&next_game, &stop);
+            scope.spawn(move || play_games(settings, next_game, stop, sender));
+        }
+        drop(sender);
+
+        for finished_game in receiver {
+            if stop.load(Ordering::Relaxed) {
+                continue;
+            }
+
+            let finished_game = match finished_game {
+                Ok(finished_game) => finished_game,
+                Err(e) => {
+                    error = Some(e);
+                    stop.store(true, Ordering::Relaxed);
+                    continue;
+                }
+            };
+
+            if let Some(path) = &settings.pgn_output {
+                if let Err(e) = append_game(path, &finished_game.game) {
+                    println!("Could not write the game to {path}: {e}");
+                }
+            }
+
+            if !on_game(&finished_game) {
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+
+    error.map_or(Ok(()), Err)
+}
+
+/** Plays games on one thread until all games are taken. The engines are kept running between
+games and only restarted after they failed. */
+fn play_games(
+    settings: &MatchSettings,
+    next_game: &AtomicUsize,
+    stop: &AtomicBool,
+    sender: Sender<Result<FinishedGame, String>>,
+) {
+    let mut engines: [Option<UciEngine>; 2] = [None, None];
+    let start_position = Opening {
+        starting_position: ChessBoard::new(),
+        moves: Vec::new(),
+    };
+
+    while !stop.load(Ordering::Relaxed) {
+        let index = next_game.fetch_add(1, Ordering::Relaxed);
+        if index >= settings.games {
+            return;
+        }
+
+        for (engine, config) in engines.iter_mut().zip(&settings.engines) {
+            if engine.is_none() {
+                match UciEngine::start(config) {
+                    Ok(started) => *engine = Some(started),
+                    Err(e) => {
+                        let _ = sender.send(Err(format!("Could not start {}: {e}", config.name)));
+                        return;
+                    }
+                }
+            }
+        }
+
+        let [Some(first), Some(second)] = &mut engines else {
+            unreachable!("Both engines were started");
+        };
+
+        if let Err(e) = first.new_game().and_then(|_| second.new_game()) {
+            let _ = sender.send(Err(format!("An engine stopped responding: {e}")));
+            return;
+        }
+
+        let (opening_index, first_engine_white) = game_pairing(index, settings.openings.len());
+        let opening = settings
+            .openings
+            .get(opening_index)
+            .unwrap_or(&start_position);
+
+        let (white, black) = if first_engine_white {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        let (mut game, outcome) = play_game(white, black, opening, settings);
+        game.set_tag("Event", "Chess-rs match");
+        game.set_tag("Round", &(index + 1).to_string());
+
+        if let Some(color) = outcome.failed_engine {
+            let white_index = if first_engine_white { 0 } else { 1 };
+            engines[white_index ^ color as usize] = None;
+        }
+
+        let finished_game = FinishedGame {
+            index,
+            game,
+            reason: outcome.reason,
+            first_engine_white,
+        };
+
+        if sender.send(Ok(finished_game)).is_err() {
+            return;
+        }
+    }
+}
+
+/** Returns the opening of the game and whether the first engine plays white. Game 2n and 2n + 1
+are a pair, they start from the same opening with the colors swapped. */
+fn game_pairing(index: usize, opening_count: usize) -> (usize, bool) {
+    ((index / 2) % opening_count.max(1), index.is_multiple_of(2))
+}
+
+fn append_game(path: &str, game: &PgnGame) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{game}")
+}
+
+/** Reads openings from an EPD file, or from the games of a PGN file with at most `plies` moves
+of each game. */
+pub fn load_openings(path: &str, plies: Option<usize>) -> Result<Vec<Opening>, String> {
+    let is_epd = Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("epd"));
+
+    if is_epd {
+        let text = fs::read_to_string(path).map_err(|e| format!("Could not read {path}: {e}"))?;
+        let positions = parse_epd(&text).map_err(|e| e.to_string())?;
+
+        return Ok(positions
+            .into_iter()
+            .map(|epd| Opening {
+                starting_position: epd.chess_board,
+                moves: Vec::new(),
+            })
+            .collect());
+    }
+
+    let pgn_reader = PgnReader::open(path).map_err(|e| format!("Could not read {path}: {e}"))?;
+    let mut openings: Vec<Opening> = Vec::new();
+
+    for game in pgn_reader {
+        let game = game.map_err(|e| e.to_string())?;
+        let moves = game.moves.iter().map(|pgn_move| pgn_move.chess_move);
+
+        openings.push(Opening {
+            starting_position: game.starting_position,
+            moves: moves.take(plies.unwrap_or(usize::MAX)).collect(),
+        });
+    }
+
+    Ok(openings)
+}
+
+/** Parses the arguments after `match`, see the top of this module. */
+pub fn parse_match_arguments(args: &[String]) -> Result<MatchSettings, String> {
+    let mut settings = MatchSettings::default();
+    let mut engine_count = 0;
+    let mut shared_settings: Vec<&str> = Vec::new();
+    let mut engine_settings: [Vec<&str>; 2] = [Vec::new(), Vec::new()];
+    let mut args = args.iter().map(String::as_str).peekable();
+
+    while let Some(option) = args.next() {
+        let mut values: Vec<&str> = Vec::new();
+        while let Some(value) = args.next_if(|value| !value.starts_with('-')) {
+            values.push(value);
+        }
+
+        let single_value = || match values.as_slice() {
+            [value] => Ok(*value),
+            _ => Err(format!("Expected one value after {option}")),
+        };
+        let number = |value: &str| {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid number {value} after {option}"))
+        };
+
+        match option {
+            "-engine" if engine_count < 2 => {
+                engine_settings[engine_count] = values.clone();
+                engine_count += 1;
+            }
+            "-engine" => return Err("A match is played by exactly two engines".to_string()),
+            "-each" => shared_settings.extend(values.iter()),
+            "-tc" => {
+                let value = single_value()?;
+                let (base, increment) = value.split_once('+').unwrap_or((value, "0"));
+                settings.limit = MatchLimit::Clock {
+                    base: parse_seconds(base)?,
+                    increment: parse_seconds(increment)?,
+                };
+            }
+            "-st" => settings.limit = MatchLimit::MoveTime(parse_seconds(single_value()?)?),
+            "-depth" => settings.limit = MatchLimit::Depth(number(single_value()?)? as u32),
+            "-nodes" => settings.limit = MatchLimit::Nodes(number(single_value()?)?),
+            "-games" => settings.games = number(single_value()?)? as usize,
+            "-concurrency" => settings.concurrency = number(single_value()?)?.max(1) as usize,
+            "-openings" => {
+                let pairs = key_value_pairs(option, &values)?;
+                let file = pairs
+                    .iter()
+                    .find(|(key, _)| *key == "file")
+                    .ok_or("Expected file=<path> after -openings")?
+                    .1;
+                let plies = match pairs.iter().find(|(key, _)| *key == "plies") {
+                    Some((_, plies)) => Some(number(plies)? as usize),
+                    None => None,
+                };
+                settings.openings = load_openings(file, plies)?;
+            }
+            "-resign" => {
+                let pairs = key_value_pairs(option, &values)?;
+                settings.resign = Some(ResignAdjudication {
+                    move_count: pair_number(&pairs, "movecount", 3)? as u32,
+                    score: pair_number(&pairs, "score", 600)? as i32,
+                });
+            }
+            "-draw" => {
+                let pairs = key_value_pairs(option, &values)?;
+                settings.draw = Some(DrawAdjudication {
+                    move_number: pair_number(&pairs, "movenumber", 40)?,
+                    move_count: pair_number(&pairs, "movecount", 8)? as u32,
+                    score: pair_number(&pairs, "score", 10)? as i32,
+                });
+            }
+            "-tb" => {
+                let tablebases = Tablebases::new(single_value()?).map_err(|e| e.to_string())?;
+                settings.tablebases = Some(Arc::new(tablebases));
+            }
+            "-pgnout" => settings.pgn_output = Some(single_value()?.to_string()),
+            _ => return Err(format!("Unknown match option {option}")),
+        }
+    }
+
+    if engine_count != 2 {
+        return Err("A match is played by exactly two engines".to_string());
+    }
+
+    for (config, own_settings) in settings.engines.iter_mut().zip(&engine_settings) {
+        let all_settings: Vec<&str> = shared_settings
+            .iter()
+            .chain(own_settings)
+            .copied()
+            .collect();
+        *config = parse_engine_config(&all_settings)?;
+    }
+
+    if settings.engines[0].name == settings.engines[1].name {
+        for (i, config) in settings.engines.iter_mut().enumerate() {
+            config.name = format!("{} ({})", config.name, i + 1);
+        }
+    }
+
+    Ok(settings)
+}
+
+/** Parses engine settings like `cmd=./engine arg=uci-mode option.Hash=64 name=Test`. */
+fn parse_engine_config(engine_settings: &[&str]) -> Result<EngineConfig, String> {
+    let mut config = EngineConfig::default();
+
+    for (key, value) in key_value_pairs("-engine", engine_settings)? {
+        match key {
+            "cmd" => config.command = value.to_string(),
+            "name" => config.name = value.to_string(),
+            "arg" => config.arguments.push(value.to_string()),
+            _ => match key.strip_prefix("option.") {
+                Some(option) => config.options.push((option.to_string(), value.to_string())),
+                None => return Err(format!("Unknown engine setting {key}")),
+            },
+        }
+    }
+
+    if config.command.is_empty() {
+        return Err("Every engine needs cmd=<command>".to_string());
+    }
+
+    if config.name.is_empty() {
+        config.name = Path::new(&config.command)
+            .file_stem()
+            .map_or(config.command.clone(), |stem| {
+                stem.to_string_lossy().to_string()
+            });
+    }
+
+    Ok(config)
+}
+
+fn key_value_pairs<'a>(
+    option: &str,
+    values: &[&'a str],
+) -> Result<Vec<(&'a str, &'a str)>, String> {
+    values
+        .iter()
+        .map(|value| {
+            value
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value after {option}, got {value}"))
+        })
+        .collect()
+}
+
+fn pair_number(pairs: &[(&str, &str)], key: &str, default: u64) -> Result<u64, String> {
+    match pairs.iter().find(|(name, _)| *name == key) {
+        Some((_, value)) => value
+            .parse()
+            .map_err(|_| format!("Invalid number {value} for {key}")),
+        None => Ok(default),
+    }
+}
+
+fn parse_seconds(seconds: &str) -> Result<Duration, String> {
+    seconds
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .map(Duration::from_secs_f64)
+        .ok_or_else(|| format!("Invalid number of seconds {seconds}"))
+}
+
+/** Runs the match subcommand and prints the result of every game and the final statistics. */
+pub fn run(args: &[String]) {
+    let settings = match parse_match_arguments(args) {
+        Ok(settings) => settings,
+        Err(e) => {
+            println!("{e}");
+            return;
+        }
+    };
+
+    let [first_name, second_name] = [&settings.engines[0].name, &settings.engines[1].name];
+    let mut score = MatchScore::default();
+
+    let result = play_match(&settings, |finished_game| {
+        let white = finished_game.game.tag("White").unwrap_or("?");
+        let black = finished_game.game.tag("Black").unwrap_or("?");
+        println!(
+            "Finished game {} ({white} vs {black}): {} {{{}}}",
+            finished_game.index + 1,
+            finished_game.game.result.to_pgn(),
+            finished_game.reason
+        );
+
+        if let Some(game_score) = finished_game.first_engine_score() {
+            score.add(game_score);
+        }
+        println!("Score of {first_name} vs {second_name}: {score}");
+        true
+    });
+
+    if let Err(e) = result {
+        println!("{e}");
+    }
+
+    if let Some((elo, margin)) = score.elo_difference() {
+        let los = score.likelihood_of_superiority().unwrap_or(0.5);
+        println!(
+            "Elo difference: {elo:.1} +/- {margin:.1}, LOS: {:.1} %",
+            100.0 * los
+        );
+    }
+    println!("Finished match");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arguments(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_match_arguments() {
+        let settings = parse_match_arguments(&arguments(
+            "-engine cmd=./engines/first option.Hash=64 name=First -engine cmd=second \
+             -each arg=uci-mode option.Threads=2 -tc 5+0.1 -games 10 -concurrency 2 \
+             -resign movecount=4 -draw score=20",
+        ))
+        .unwrap();
+
+        let [first, second] = &settings.engines;
+        assert_eq!(first.name, "First");
+        assert_eq!(first.command, "./engines/first");
+        assert_eq!(second.name, "second");
+        /* The settings of -each come before the own settings of each engine. */
+        assert_eq!(first.arguments, vec!["uci-mode"]);
+        assert_eq!(second.arguments, vec!["uci-mode"]);
+        assert_eq!(
+            first.options,
+            vec![
+                ("Threads".to_string(), "2".to_string()),
+                ("Hash".to_string(), "64".to_string())
+            ]
+        );
+        assert_eq!(
+            second.options,
+            vec![("Threads".to_string(), "2".to_string())]
+        );
+
+        assert_eq!(
+            settings.limit,
+            MatchLimit::Clock {
+                base: Duration::from_secs(5),
+                increment: Duration::from_millis(100)
+            }
+        );
+        assert_eq!((settings.games, settings.concurrency), (10, 2));
+        assert_eq!(settings.resign.unwrap().move_count, 4);
+        assert_eq!(settings.resign.unwrap().score, 600);
+        assert_eq!(settings.draw.unwrap().move_number, 40);
+        assert_eq!(settings.draw.unwrap().score, 20);
+    }
+
+    #[test]
+    fn test_engine_names_and_invalid_arguments() {
+        /* Engines with the same name get a number, an own name overrides the one of -each. */
+        let settings = parse_match_arguments(&arguments(
+            "-engine cmd=./engine -engine cmd=/bin/engine -st 1",
+        ))
+        .unwrap();
+        assert_eq!(settings.engines[0].name, "engine (1)");
+        assert_eq!(settings.engines[1].name, "engine (2)");
+        assert_eq!(settings.limit, MatchLimit::MoveTime(Duration::from_secs(1)));
+
+        let settings = parse_match_arguments(&arguments(
+            "-each cmd=./engine name=Shared -engine name=Own -engine -depth 8",
+        ))
+        .unwrap();
+        assert_eq!(settings.engines[0].name, "Own");
+        assert_eq!(settings.engines[1].name, "Shared");
+        assert_eq!(settings.limit, MatchLimit::Depth(8));
+
+        for invalid in [
+            "-engine cmd=./engine",
+            "-engine cmd=a -engine cmd=b -engine cmd=c",
+            "-engine name=a -engine cmd=b",
+            "-engine cmd=a -engine cmd=b -tc fast",
+            "-engine cmd=a -engine cmd=b -games",
+            "-engine cmd=a -engine cmd=b -ponder",
+            "-engine cmd=a -engine cmd=b hash=64",
+        ] {
+            assert!(
+                parse_match_arguments(&arguments(invalid)).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_games_are_paired_with_swapped_colors() {
+        assert_eq!(game_pairing(0, 0), (0, true));
+        assert_eq!(game_pairing(1, 0), (0, false));
+        assert_eq!(game_pairing(2, 3), (1, true));
+        assert_eq!(game_pairing(5, 3), (2, false));
+        assert_eq!(game_pairing(6, 3), (0, true));
+
+        let mut finished_game = FinishedGame {
+            index: 0,
+            game: PgnGame {
+                result: GameResult::WhiteWins,
+                ..PgnGame::default()
+            },
+            reason: String::new(),
+            first_engine_white: true,
+        };
+        assert_eq!(finished_game.first_engine_score(), Some(1.0));
+
+        finished_game.first_engine_white = false;
+        assert_eq!(finished_game.first_engine_score(), Some(0.0));
+        finished_game.game.result = GameResult::Draw;
+        assert_eq!(finished_game.first_engine_score(), Some(0.5));
+        finished_game.game.result = GameResult::Unknown;
+        assert_eq!(finished_game.first_engine_score(), None);
+    }
+}
@@ -0,0 +1,494 @@
+/* This module plays a single game between two UCI engines. The game keeps the clocks, ends games
+ * by the rules of chess, and adjudicates games the engines agree are decided or the tablebases
+ * know the result of. Engines that lose on time, disconnect or play an illegal move lose the
+ * game, unless on time against an opponent that could never mate. Every move is stored with its clock and evaluation, so the PGN shows how the game went. */
+use crate::engine_match::uci_engine::{EngineScore, UciEngine};
+use crate::engine_match::{MatchLimit, MatchSettings, Opening};
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+use unified_chess_engine::array_engine::termination::Termination;
+use unified_chess_engine::array_engine::{ChessBoard, Color, Move};
+use unified_chess_engine::pgn::{Evaluation, GameResult, PgnGame, PgnMove};
+use unified_chess_engine::syzygy::Wdl;
+
+/* Extra time an engine gets before its search is cut off, a late move still loses on time. */
+const TIME_MARGIN: Duration = Duration::from_secs(1);
+
+/** Stops a game when the engines agree it is decided. The resign adjudication gives the loss to
+an engine that saw itself losing by at least the score for the given number of its moves in a
+row. The draw adjudication draws the game when both engines saw a score within the given score
+for the given number of moves each, from the move number on. */
+#[derive(Debug, Clone, Copy)]
+pub struct ResignAdjudication {
+    pub move_count: u32,
+    pub score: i32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DrawAdjudication {
+    pub move_number: u64,
+    pub move_count: u32,
+    pub score: i32,
+}
+
+/** How a finished game ended, for the PGN and the match output. */
+pub struct GameOutcome {
+    pub result: GameResult,
+    pub reason: String,
+    /* The value of the Termination tag. */
+    pub termination: &'static str,
+    /* The engine of this color crashed or hangs and should be restarted. */
+    pub failed_engine: Option<Color>,
+}
+
+impl GameOutcome {
+    fn new(result: GameResult, reason: String, termination: &'static str) -> GameOutcome {
+        GameOutcome {
+            result,
+            reason,
+            termination,
+            failed_engine: None,
+        }
+    }
+
+    fn win(color: Color, reason: String, termination: &'static str) -> GameOutcome {
+        let result = match color {
+            Color::White => GameResult::WhiteWins,
+            Color::Black => GameResult::BlackWins,
+        };
+        GameOutcome::new(result, reason, termination)
+    }
+}
+
+/** Plays the game from the opening. The engines are given as white and black. */
+pub fn play_game(
+    white: &mut UciEngine,
+    black: &mut UciEngine,
+    opening: &Opening,
+    settings: &MatchSettings,
+) -> (PgnGame, GameOutcome) {
+    let mut game = PgnGame::new(opening.starting_position);
+    game.set_tag("White", white.name());
+    game.set_tag("Black", black.name());
+
+    if let MatchLimit::Clock { base, increment } = settings.limit {
+        game.set_tag(
+            "TimeControl",
+            &format!("{}+{}", base.as_secs_f64(), increment.as_secs_f64()),
+        );
+    }
+
+    let mut game_play = GamePlay::new(opening, settings);
+    for chess_move in &opening.moves {
+        game.moves
+            .push(PgnMove::new(&game_play.chess_board, *chess_move));
+        game_play.make_move(chess_move);
+    }
+
+    let outcome = game_play.play(white, black, &mut game);
+
+    game.result = outcome.result;
+    game.set_tag("Termination", outcome.termination);
+
+    if let Some(last_move) = game.moves.last_mut() {
+        last_move.comment = Some(match last_move.comment.take() {
+            Some(comment) => format!("{comment} {}", outcome.reason),
+            None => outcome.reason.clone(),
+        });
+    }
+
+    (game, outcome)
+}
+
+struct GamePlay<'a> {
+    settings: &'a MatchSettings,
+    chess_board: ChessBoard,
+    starting_fen: String,
+    /* The moves of the game in long algebraic notation, sent to the engines. */
+    uci_moves: Vec<String>,
+    position_history: Vec<u64>,
+    /* The clocks of white and black. */
+    clocks: [Duration; 2],
+    resign_counts: [u32; 2],
+    draw_count: u32,
+}
+
+impl<'a> GamePlay<'a> {
+    fn new(opening: &Opening, settings: &'a MatchSettings) -> GamePlay<'a> {
+        let base = match settings.limit {
+            MatchLimit::Clock { base, .. } => base,
+            _ => Duration::ZERO,
+        };
+
+        GamePlay {
+            settings,
+            chess_board: opening.starting_position,
+            starting_fen: opening.starting_position.to_fen(),
+            uci_moves: Vec::new(),
+            position_history: Vec::new(),
+            clocks: [base; 2],
+            resign_counts: [0; 2],
+            draw_count: 0,
+        }
+    }
+
+    fn play(
+        &mut self,
+        white: &mut UciEngine,
+        black: &mut UciEngine,
+        game: &mut PgnGame,
+    ) -> GameOutcome {
+        loop {
+            if let Some(outcome) = self.rules_outcome().or_else(|| self.tablebase_outcome()) {
+                return outcome;
+            }
+
+            let side_to_move = self.chess_board.side_to_move();
+            let engine = match side_to_move {
+                Color::White => &mut *white,
+                Color::Black => &mut *black,
+            };
+
+            let (chess_move, score) = match self.engine_move(engine, side_to_move) {
+                Ok(engine_move) => engine_move,
+                Err(outcome) => return outcome,
+            };
+
+            let mut pgn_move = PgnMove::new(&self.chess_board, chess_move);
+            if matches!(self.settings.limit, MatchLimit::Clock { .. }) {
+                pgn_move.set_clock(self.clocks[side_to_move as usize]);
+            }
+            if let Some(score) = score {
+                pgn_move.set_evaluation(white_view(score, side_to_move));
+            }
+            game.moves.push(pgn_move);
+            self.make_move(&chess_move);
+
+            if let Some(outcome) = self.adjudicate(side_to_move, score) {
+                return outcome;
+            }
+        }
+    }
+
+    fn make_move(&mut self, chess_move: &Move) {
+        self.position_history.push(self.chess_board.zobrist_hash());
+        self.uci_moves.push(chess_move.move_to_string());
+        self.chess_board.make_move_on_board(chess_move);
+        self.chess_board.update_meta_data(chess_move);
+    }
+
+    /** Lets the engine search and checks its move and clock. */
+    fn engine_move(
+        &mut self,
+        engine: &mut UciEngine,
+        side_to_move: Color,
+    ) -> Result<(Move, Option<EngineScore>), GameOutcome> {
+        let mut position = format!("position fen {}", self.starting_fen);
+        if !self.uci_moves.is_empty() {
+            position.push_str(" moves ");
+            position.push_str(&self.uci_moves.join(" "));
+        }
+
+        let side = side_to_move as usize;
+        let (go, timeout) = match self.settings.limit {
+            MatchLimit::Clock { increment, .. } => (
+                format!(
+                    "go wtime {} btime {} winc {} binc {}",
+                    self.clocks[0].as_millis(),
+                    self.clocks[1].as_millis(),
+                    increment.as_millis(),
+                    increment.as_millis()
+                ),
+                Some(self.clocks[side] + TIME_MARGIN),
+            ),
+            MatchLimit::MoveTime(move_time) => (
+                format!("go movetime {}", move_time.as_millis()),
+                Some(move_time + TIME_MARGIN),
+            ),
+            MatchLimit::Depth(depth) => (format!("go depth {depth}"), None),
+            MatchLimit::Nodes(nodes) => (format!("go nodes {nodes}"), None),
+        };
+
+        let name = engine.name().to_string();
+        let winner = side_to_move.opposite();
+        let start_time = Instant::now();
+
+        let engine_move = match engine.search(&position, &go, timeout) {
+            Ok(engine_move) => engine_move,
+            Err(e) => {
+                let mut outcome = match e.kind() {
+                    ErrorKind::TimedOut => self.time_forfeit(side_to_move, &name),
+                    _ => GameOutcome::win(winner, format!("{name} disconnects: {e}"), "abandoned"),
+                };
+                outcome.failed_engine = Some(side_to_move);
+                return Err(outcome);
+            }
+        };
+
+        self.use_clock(side_to_move, &name, start_time.elapsed())?;
+
+        self.chess_board
+            .legal_moves()
+            .into_iter()
+            .find(|chess_move| chess_move.move_to_string() == engine_move.best_move)
+            .map(|chess_move| (chess_move, engine_move.score))
+            .ok_or_else(|| {
+                GameOutcome::win(
+                    winner,
+                    format!("{name} makes an illegal move: {}", engine_move.best_move),
+                    "rules infraction",
+                )
+            })
+    }
+
+    /** Takes the time of a move off the clock of the side and adds the increment, in games with
+    a clock. The side loses on time when the move took longer than the time it had left. */
+    fn use_clock(&mut self, side: Color, name: &str, elapsed: Duration) -> Result<(), GameOutcome> {
+        let MatchLimit::Clock { increment, .. } = self.settings.limit else {
+            return Ok(());
+        };
+        let clock = &mut self.clocks[side as usize];
+
+        if elapsed > *clock {
+            return Err(self.time_forfeit(side, name));
+        }
+
+        *clock = *clock - elapsed + increment;
+        Ok(())
+    }
+
+    /** The outcome when the side runs out of time. It loses, unless the opponent could never mate
+    in the position, which draws. */
+    fn time_forfeit(&self, side: Color, name: &str) -> GameOutcome {
+        if self.chess_board.has_mating_material(side.opposite()) {
+            GameOutcome::win(
+                side.opposite(),
+                format!("{name} loses on time"),
+                "time forfeit",
+            )
+        } else {
+            GameOutcome::new(
+                GameResult::Draw,
+                format!("{name} runs out of time, but the opponent cannot mate"),
+                "time forfeit",
+            )
+        }
+    }
+
+    /** Ends the game by checkmate, stalemate, the fifty move rule, threefold repetition or
+    insufficient material. */
+    fn rules_outcome(&self) -> Option<GameOutcome> {
+        let reason = match self.chess_board.termination(&self.position_history)? {
+            Termination::Checkmate { winner } => {
+                let reason = match winner {
+                    Color::White => "White mates",
+                    Color::Black => "Black mates",
+                };
+                return Some(GameOutcome::win(winner, reason.to_string(), "normal"));
+            }
+            Termination::Stalemate => "Draw by stalemate",
+            Termination::FiftyMoveRule => "Draw by fifty moves rule",
+            Termination::ThreefoldRepetition => "Draw by 3-fold repetition",
+            Termination::InsufficientMaterial => "Draw by insufficient mating material",
+        };
+
+        Some(GameOutcome::new(
+            GameResult::Draw,
+            reason.to_string(),
+            "normal",
+        ))
+    }
+
+    fn tablebase_outcome(&self) -> Option<GameOutcome> {
+        let tablebases = self.settings.tablebases.as_ref()?;
+
+        if !tablebases.can_probe(&self.chess_board) {
+            return None;
+        }
+
+        let side_to_move = self.chess_board.side_to_move();
+        let reason = "Tablebase adjudication".to_string();
+
+        Some(match tablebases.probe_wdl(&self.chess_board)? {
+            Wdl::Win => GameOutcome::win(side_to_move, reason, "adjudication"),
+            Wdl::Loss => GameOutcome::win(side_to_move.opposite(), reason, "adjudication"),
+            _ => GameOutcome::new(GameResult::Draw, reason, "adjudication"),
+        })
+    }
+
+    /** Counts the moves for the resign and draw adjudication after the move of the side. */
+    fn adjudicate(&mut self, side: Color, score: Option<EngineScore>) -> Option<GameOutcome> {
+        let centipawns = score.map(|score| score.centipawns());
+        let index = side as usize;
+
+        if let Some(resign) = self.settings.resign {
+            match centipawns {
+                Some(centipawns) if centipawns <= -resign.score => self.resign_counts[index] += 1,
+                _ => self.resign_counts[index] = 0,
+            }
+
+            if self.resign_counts[index] >= resign.move_count {
+                let name = match side {
+                    Color::White => "White",
+                    Color::Black => "Black",
+                };
+                return Some(GameOutcome::win(
+                    side.opposite(),
+                    format!("{name} resigns"),
+                    "adjudication",
+                ));
+            }
+        }
+
+        if let Some(draw) = self.settings.draw {
+            let in_range = self.chess_board.full_move_counter() >= draw.move_number
+                && centipawns.is_some_and(|centipawns| centipawns.abs() <= draw.score);
+
+            if in_range {
+                self.draw_count += 1;
+            } else {
+                self.draw_count = 0;
+            }
+
+            if self.draw_count >= 2 * draw.move_count {
+                return Some(GameOutcome::new(
+                    GameResult::Draw,
+                    "Draw by adjudication".to_string(),
+                    "adjudication",
+                ));
+            }
+        }
+
+        None
+    }
+}
+
+/** Converts a score of the side to move to the view of white, as PGN evaluations are. */
+fn white_view(score: EngineScore, side_to_move: Color) -> Evaluation {
+    let sign = match side_to_move {
+        Color::White => 1,
+        Color::Black => -1,
+    };
+
+    match score {
+        EngineScore::Centipawns(centipawns) => Evaluation::Centipawns(sign * centipawns),
+        EngineScore::Mate(moves) => Evaluation::Mate(sign * moves),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(limit: MatchLimit) -> MatchSettings {
+        MatchSettings {
+            limit,
+            resign: Some(ResignAdjudication {
+                move_count: 2,
+                score: 500,
+            }),
+            draw: Some(DrawAdjudication {
+                move_number: 1,
+                move_count: 2,
+                score: 10,
+            }),
+            ..MatchSettings::default()
+        }
+    }
+
+    fn start_position() -> Opening {
+        Opening {
+            starting_position: ChessBoard::new(),
+            moves: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_resign_adjudication() {
+        let settings = settings(MatchLimit::Depth(1));
+        let opening = start_position();
+        let mut game_play = GamePlay::new(&opening, &settings);
+        let losing = Some(EngineScore::Centipawns(-600));
+
+        /* A better score in between starts the count of moves again. */
+        assert!(game_play.adjudicate(Color::Black, losing).is_none());
+        assert!(game_play
+            .adjudicate(Color::Black, Some(EngineScore::Centipawns(-400)))
+            .is_none());
+        assert!(game_play.adjudicate(Color::Black, losing).is_none());
+        assert!(game_play.adjudicate(Color::White, losing).is_none());
+
+        let outcome = game_play
+            .adjudicate(Color::Black, Some(EngineScore::Mate(-3)))
+            .unwrap();
+        assert_eq!(outcome.result, GameResult::WhiteWins);
+        assert_eq!(outcome.reason, "Black resigns");
+        assert_eq!(outcome.termination, "adjudication");
+    }
+
+    #[test]
+    fn test_draw_adjudication() {
+        let settings = settings(MatchLimit::Depth(1));
+        let opening = start_position();
+        let mut game_play = GamePlay::new(&opening, &settings);
+        let level = Some(EngineScore::Centipawns(5));
+
+        /* Both engines need a level score for the given number of moves each. */
+        for side in [Color::White, Color::Black, Color::White] {
+            assert!(game_play.adjudicate(side, level).is_none());
+        }
+        assert!(game_play.adjudicate(Color::Black, None).is_none());
+        for side in [Color::White, Color::Black, Color::White] {
+            assert!(game_play.adjudicate(side, level).is_none());
+        }
+
+        let outcome = game_play.adjudicate(Color::Black, level).unwrap();
+        assert_eq!(outcome.result, GameResult::Draw);
+        assert_eq!(outcome.termination, "adjudication");
+    }
+
+    #[test]
+    fn test_clocks_and_time_forfeit() {
+        let settings = settings(MatchLimit::Clock {
+            base: Duration::from_secs(10),
+            increment: Duration::from_secs(1),
+        });
+        let opening = start_position();
+        let mut game_play = GamePlay::new(&opening, &settings);
+
+        assert!(game_play
+            .use_clock(Color::White, "First", Duration::from_secs(3))
+            .is_ok());
+        assert_eq!(
+            game_play.clocks,
+            [Duration::from_secs(8), Duration::from_secs(10)]
+        );
+        assert!(game_play
+            .use_clock(Color::Black, "Second", Duration::from_secs(11))
+            .is_err_and(|outcome| outcome.result == GameResult::WhiteWins
+                && outcome.reason == "Second loses on time"
+                && outcome.termination == "time forfeit"));
+
+        /* Running out of time against a lone king only draws. */
+        let opening = Opening {
+            starting_position: ChessBoard::new_from_fen("4k3/8/8/8/8/8/P7/4K3 w - - 0 1").unwrap(),
+            moves: Vec::new(),
+        };
+        let mut game_play = GamePlay::new(&opening, &settings);
+        assert!(game_play
+            .use_clock(Color::White, "First", Duration::from_secs(11))
+            .is_err_and(|outcome| outcome.result == GameResult::Draw
+                && outcome.reason == "First runs out of time, but the opponent cannot mate"
+                && outcome.termination == "time forfeit"));
+
+        /* Games without a clock cannot be lost on time. */
+        let settings = MatchSettings {
+            limit: MatchLimit::Depth(1),
+            ..MatchSettings::default()
+        };
+        let mut game_play = GamePlay::new(&opening, &settings);
+        assert!(game_play
+            .use_clock(Color::White, "First", Duration::from_secs(100))
+            .is_ok());
+    }
+}
@@ -0,0 +1,200 @@
+/* This module tests patches with the sequential probability ratio test (SPRT): two builds play
+ * game pairs until the results show, with the error rates alpha and beta, whether the first build
+ * is elo0 or elo1 Elo stronger than the second. Most patches are decided after far fewer games
+ * than a fixed-length match needs. The log-likelihood ratio (LLR) is computed from the
+ * pentanomial pair results after every pair. The engines and limits take the same options as the
+ * match subcommand, usually the patched build first:
+ *
+ *   unified-chess sprt -engine cmd=./patched arg=uci-mode -engine cmd=./master arg=uci-mode
+ *                      -tc 10+0.1 -concurrency 4 -openings file=book.epd
+ *                      [-elo0 0] [-elo1 5] [-alpha 0.05] [-beta 0.05]
+ *
+ * Without -games the test runs until a bound is crossed. */
+use crate::engine_match::statistics::Pentanomial;
+use crate::engine_match::{parse_match_arguments, play_match};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SprtSettings {
+    pub elo0: f64,
+    pub elo1: f64,
+    /* The probability of accepting a patch that is only elo0 stronger. */
+    pub alpha: f64,
+    /* The probability of rejecting a patch that is elo1 stronger. */
+    pub beta: f64,
+}
+
+impl Default for SprtSettings {
+    fn default() -> Self {
+        SprtSettings {
+            elo0: 0.0,
+            elo1: 5.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtResult {
+    /* The first engine is elo1 stronger. */
+    AcceptH1,
+    /* The first engine is at most elo0 stronger. */
+    AcceptH0,
+    Undecided,
+}
+
+impl SprtSettings {
+    /** The lower and upper bound of the log-likelihood ratio. */
+    pub fn bounds(&self) -> (f64, f64) {
+        let lower = (self.beta / (1.0 - self.alpha)).ln();
+        let upper = ((1.0 - self.beta) / self.alpha).ln();
+        (lower, upper)
+    }
+
+    pub fn result(&self, pairs: &Pentanomial) -> SprtResult {
+        let llr = pairs.log_likelihood_ratio(self.elo0, self.elo1);
+        let (lower, upper) = self.bounds();
+
+        if llr >= upper {
+            SprtResult::AcceptH1
+        } else if llr <= lower {
+            SprtResult::AcceptH0
+        } else {
+            SprtResult::Undecided
+        }
+    }
+}
+
+/** Takes the SPRT options out of the arguments and returns the remaining match arguments. */
+fn parse_sprt_arguments(args: &[String]) -> Result<(SprtSettings, Vec<String>), String> {
+    let mut settings = SprtSettings::default();
+    let mut match_arguments: Vec<String> = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(option) = args.next() {
+        let value = match option.as_str() {
+            "-elo0" => &mut settings.elo0,
+            "-elo1" => &mut settings.elo1,
+            "-alpha" => &mut settings.alpha,
+            "-beta" => &mut settings.beta,
+            _ => {
+                match_arguments.push(option.clone());
+                continue;
+            }
+        };
+
+        *value = args
+            .next()
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| format!("Expected a number after {option}"))?;
+    }
+
+    if settings.elo0 >= settings.elo1 {
+        return Err("elo0 must be lower than elo1".to_string());
+    }
+
+    let valid_rate = |rate: f64| rate > 0.0 && rate < 0.5;
+    if !valid_rate(settings.alpha) || !valid_rate(settings.beta) {
+        return Err("alpha and beta must be between 0 and 0.5".to_string());
+    }
+
+    Ok((settings, match_arguments))
+}
+
+/** Runs the sprt subcommand and prints the state of the test after every pair. */
+pub fn run(args: &[String]) {
+    let parsed = parse_sprt_arguments(args).and_then(|(sprt, match_arguments)| {
+        let mut settings = parse_match_arguments(&match_arguments)?;
+        if !match_arguments.iter().any(|argument| argument == "-games") {
+            settings.games = usize::MAX;
+        }
+        Ok((sprt, settings))
+    });
+
+    let (sprt, settings) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            println!("{e}");
+            return;
+        }
+    };
+
+    let (lower, upper) = sprt.bounds();
+    println!(
+        "SPRT: elo0 {} elo1 {} alpha {} beta {}, LLR bounds [{lower:.2}, {upper:.2}]",
+        sprt.elo0, sprt.elo1, sprt.alpha, sprt.beta
+    );
+
+    let mut pairs = Pentanomial::default();
+    /* The scores of the first engine in games whose pair is not finished yet. */
+    let mut unpaired_scores: HashMap<usize, Option<f64>> = HashMap::new();
+    let mut result = SprtResult::Undecided;
+
+    let match_result = play_match(&settings, |finished_game| {
+        let pair = finished_game.index / 2;
+        let score = finished_game.first_engine_score();
+
+        let Some(other_score) = unpaired_scores.remove(&pair) else {
+            unpaired_scores.insert(pair, score);
+            return true;
+        };
+
+        /* Pairs with an unfinished game are left out of the test. */
+        if let (Some(score), Some(other_score)) = (score, other_score) {
+            pairs.add_pair(score, other_score);
+        }
+
+        let llr = pairs.log_likelihood_ratio(sprt.elo0, sprt.elo1);
+        println!(
+            "Pairs {}: {pairs}, LLR {llr:.2} [{lower:.2}, {upper:.2}]",
+            pairs.pairs()
+        );
+
+        result = sprt.result(&pairs);
+        result == SprtResult::Undecided
+    });
+
+    if let Err(e) = match_result {
+        println!("{e}");
+    }
+
+    let llr = pairs.log_likelihood_ratio(sprt.elo0, sprt.elo1);
+    println!("Final LLR: {llr:.2} [{lower:.2}, {upper:.2}]");
+
+    if let Some((elo, margin)) = pairs.elo_difference() {
+        println!("Elo difference: {elo:.1} +/- {margin:.1}");
+    }
+
+    match result {
+        SprtResult::AcceptH1 => println!("H1 accepted"),
+        SprtResult::AcceptH0 => println!("H0 accepted"),
+        SprtResult::Undecided => println!("No bound was crossed"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sprt_bounds() {
+        let arguments: Vec<String> = ["-elo1", "10", "-engine", "cmd=a", "-alpha", "0.1"]
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect();
+        let (sprt, match_arguments) = parse_sprt_arguments(&arguments).unwrap();
+        assert_eq!(sprt.elo1, 10.0);
+        assert_eq!(match_arguments, ["-engine", "cmd=a"]);
+
+        let (lower, upper) = SprtSettings::default().bounds();
+        assert!((lower + 2.944).abs() < 0.001 && (upper - 2.944).abs() < 0.001);
+
+        let mut pairs = Pentanomial::default();
+        assert_eq!(sprt.result(&pairs), SprtResult::Undecided);
+        pairs.counts = [0, 10, 100, 200, 50];
+        assert_eq!(sprt.result(&pairs), SprtResult::AcceptH1);
+        pairs.counts = [50, 200, 100, 10, 0];
+        assert_eq!(sprt.result(&pairs), SprtResult::AcceptH0);
+    }
+}
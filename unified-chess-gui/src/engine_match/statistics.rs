@@ -0,0 +1,235 @@
+/* This module computes the statistics of a match from the view of the first engine: the Elo
+ * difference with its 95% error margin and the likelihood of superiority (LOS), the probability
+ * that the first engine is really the stronger one. Draws do not change the LOS. Game pairs are
+ * counted separately for the log-likelihood ratio of SPRT tests. */
+use std::fmt;
+
+/* The two-sided 95% quantile of the normal distribution. */
+const NORMAL_QUANTILE_95: f64 = 1.959964;
+/* Added to every pentanomial count for the log-likelihood ratio, a weak prior of two and a half
+pairs. */
+const PSEUDO_COUNT: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl MatchScore {
+    /** Adds a game scored 1, 0.5 or 0 for the first engine. */
+    pub fn add(&mut self, score: f64) {
+        if score > 0.75 {
+            self.wins += 1;
+        } else if score < 0.25 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    /** The points per game, from 0 to 1. */
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games().max(1) as f64
+    }
+
+    /** The Elo difference and the margin of its 95% confidence interval. The margin is infinite
+    when the interval reaches a score of 0 or 1, as after few games or when one engine scored every
+    point. */
+    pub fn elo_difference(&self) -> Option<(f64, f64)> {
+        let games = self.games() as f64;
+
+        if games == 0.0 {
+            return None;
+        }
+
+        let score = self.score();
+        let variance = (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games;
+        let deviation = (variance / games).sqrt();
+
+        let lower = elo_from_score((score - NORMAL_QUANTILE_95 * deviation).max(0.0));
+        let upper = elo_from_score((score + NORMAL_QUANTILE_95 * deviation).min(1.0));
+
+        Some((elo_from_score(score), (upper - lower) / 2.0))
+    }
+
+    pub fn likelihood_of_superiority(&self) -> Option<f64> {
+        let decisive = (self.wins + self.losses) as f64;
+
+        if decisive == 0.0 {
+            return None;
+        }
+
+        let difference = self.wins as f64 - self.losses as f64;
+        Some(0.5 * (1.0 + erf(difference / (2.0 * decisive).sqrt())))
+    }
+}
+
+/** Writes the score like "12 - 8 - 20 [0.550] 40", as wins, losses, draws, score and games. */
+impl fmt::Display for MatchScore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} - {} - {} [{:.3}] {}",
+            self.wins,
+            self.losses,
+            self.draws,
+            self.score(),
+            self.games()
+        )
+    }
+}
+
+/** The results of game pairs, which play the same opening with the colors swapped. Counting
+pairs instead of games removes the variance the openings add, so fewer games are needed to tell
+two engines apart. `counts[i]` is the number of pairs in which the first engine scored i / 2
+points, from a lost pair to a won pair. */
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pentanomial {
+    pub counts: [u32; 5],
+}
+
+impl Pentanomial {
+    /** Adds a pair with the scores of the first engine in both games. */
+    pub fn add_pair(&mut self, first_score: f64, second_score: f64) {
+        let half_points = (2.0 * (first_score + second_score)).round() as usize;
+        self.counts[half_points.min(4)] += 1;
+    }
+
+    pub fn pairs(&self) -> u32 {
+        self.counts.iter().sum()
+    }
+
+    /** The mean and the variance of the pair scores, each scaled to 0 to 1. Every count is raised
+    by the pseudo count first. */
+    fn mean_and_variance(&self, pseudo_count: f64) -> Option<(f64, f64)> {
+        if self.pairs() == 0 {
+            return None;
+        }
+
+        let pairs = self.pairs() as f64 + 5.0 * pseudo_count;
+        let scores = (0..5).map(|i| i as f64 / 4.0);
+        let frequencies = self
+            .counts
+            .iter()
+            .map(|&count| (count as f64 + pseudo_count) / pairs);
+        let mean: f64 = scores
+            .clone()
+            .zip(frequencies.clone())
+            .map(|(x, p)| x * p)
+            .sum();
+        let variance = scores
+            .zip(frequencies)
+            .map(|(x, p)| p * (x - mean).powi(2))
+            .sum();
+
+        Some((mean, variance))
+    }
+
+    /** The Elo difference and the margin of its 95% confidence interval. */
+    pub fn elo_difference(&self) -> Option<(f64, f64)> {
+        let (mean, variance) = self.mean_and_variance(0.0)?;
+        let deviation = (variance / self.pairs() as f64).sqrt();
+
+        let lower = elo_from_score((mean - NORMAL_QUANTILE_95 * deviation).max(0.0));
+        let upper = elo_from_score((mean + NORMAL_QUANTILE_95 * deviation).min(1.0));
+
+        Some((elo_from_score(mean), (upper - lower) / 2.0))
+    }
+
+    /** The log-likelihood ratio of the hypothesis that the first engine is elo1 stronger over the
+    hypothesis that it is elo0 stronger, with the normal approximation of the generalized SPRT.
+    The pseudo counts keep the variance realistic while only a few pairs are played, and above 0
+    when all pairs had the same result, as between identical deterministic engines. */
+    pub fn log_likelihood_ratio(&self, elo0: f64, elo1: f64) -> f64 {
+        let Some((mean, variance)) = self.mean_and_variance(PSEUDO_COUNT) else {
+            return 0.0;
+        };
+
+        let (score0, score1) = (score_from_elo(elo0), score_from_elo(elo1));
+        self.pairs() as f64 * (score1 - score0) * (2.0 * mean - score0 - score1) / (2.0 * variance)
+    }
+}
+
+/** Writes the pairs like "[3, 10, 25, 12, 4]", from lost pairs to won pairs. */
+impl fmt::Display for Pentanomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [ll, ld, dd_wl, wd, ww] = self.counts;
+        write!(f, "[{ll}, {ld}, {dd_wl}, {wd}, {ww}]")
+    }
+}
+
+/** The Elo difference for which the expected score is the given score. */
+pub fn elo_from_score(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+/** The expected score of an engine that is the given number of Elo stronger. */
+pub fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/** The error function, approximated with formula 7.1.26 of Abramowitz and Stegun. The error is
+below 1.5e-7, which is plenty for match statistics. */
+pub fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let result = 1.0 - polynomial * (-x * x).exp();
+
+    if x < 0.0 {
+        -result
+    } else {
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elo_and_los() {
+        let score = MatchScore {
+            wins: 60,
+            losses: 40,
+            draws: 100,
+        };
+
+        let (elo, margin) = score.elo_difference().unwrap();
+        assert!((elo - 34.86).abs() < 0.01);
+        assert!(margin > 25.0 && margin < 40.0);
+        assert!((score.likelihood_of_superiority().unwrap() - 0.9772).abs() < 0.001);
+        assert_eq!(score.to_string(), "60 - 40 - 100 [0.550] 200");
+
+        assert!((score_from_elo(elo) - 0.55).abs() < 1e-9);
+        assert!((erf(1.0) - 0.842700).abs() < 1e-6);
+        assert_eq!(MatchScore::default().elo_difference(), None);
+    }
+
+    #[test]
+    fn test_pentanomial() {
+        let mut pairs = Pentanomial::default();
+        assert_eq!(pairs.log_likelihood_ratio(0.0, 5.0), 0.0);
+
+        pairs.add_pair(1.0, 0.5);
+        pairs.add_pair(0.0, 0.5);
+        assert_eq!(pairs.to_string(), "[0, 1, 0, 1, 0]");
+
+        pairs.counts = [100, 400, 1000, 450, 110];
+        let (elo, margin) = pairs.elo_difference().unwrap();
+        assert!(elo > 0.0 && elo < margin);
+        /* A gain of some Elo favours a small elo1 over no gain, but not a large one. */
+        assert!(pairs.log_likelihood_ratio(0.0, 5.0) > 0.0);
+        assert!(pairs.log_likelihood_ratio(0.0, 40.0) < 0.0);
+    }
+}
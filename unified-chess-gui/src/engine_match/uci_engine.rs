@@ -0,0 +1,241 @@
+/* This module runs a UCI engine as a child process. The lines the engine writes are read on their
+ * own thread and passed through a channel, so waiting for an answer can time out when an engine
+ * hangs instead of blocking the match forever. */
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/* How long an engine may take to answer uci, isready and quit. */
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+const QUIT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/** How to start an engine: the command, its arguments and the UCI options to set. */
+#[derive(Debug, Clone, Default)]
+pub struct EngineConfig {
+    pub name: String,
+    pub command: String,
+    pub arguments: Vec<String>,
+    pub options: Vec<(String, String)>,
+}
+
+/** A score from the view of the engine, as sent in its info lines. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EngineScore {
+    Centipawns(i32),
+    /* Moves until mate, negative when the engine is getting mated. */
+    Mate(i32),
+}
+
+impl EngineScore {
+    /** The score in centipawns, with mates beyond any centipawn score. */
+    pub fn centipawns(&self) -> i32 {
+        match *self {
+            EngineScore::Centipawns(centipawns) => centipawns,
+            EngineScore::Mate(moves) if moves > 0 => 100_000 - moves,
+            EngineScore::Mate(moves) => -100_000 - moves,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EngineMove {
+    /* The move in long algebraic notation, like "e2e4" or "e7e8q". */
+    pub best_move: String,
+    /* The score and depth of the last info line before the move. */
+    pub score: Option<EngineScore>,
+    pub depth: u32,
+}
+
+pub struct UciEngine {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl UciEngine {
+    /** Starts the engine, waits for it to accept UCI and sets its options. */
+    pub fn start(config: &EngineConfig) -> io::Result<UciEngine> {
+        let mut child = Command::new(&config.command)
+            .args(&config.arguments)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdin = child.stdin.take().expect("The standard input is piped");
+        let stdout = child.stdout.take().expect("The standard output is piped");
+        let (sender, lines) = mpsc::channel();
+
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = UciEngine {
+            name: config.name.clone(),
+            child,
+            stdin,
+            lines,
+        };
+
+        engine.send("uci")?;
+        engine.wait_for("uciok", Some(HANDSHAKE_TIMEOUT))?;
+
+        for (name, value) in &config.options {
+            engine.send(&format!("setoption name {name} value {value}"))?;
+        }
+
+        engine.send("isready")?;
+        engine.wait_for("readyok", Some(HANDSHAKE_TIMEOUT))?;
+
+        Ok(engine)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.send("isready")?;
+        self.wait_for("readyok", Some(HANDSHAKE_TIMEOUT))?;
+        Ok(())
+    }
+
+    /** Sends the position and go commands and waits for the best move. Fails with
+    `ErrorKind::TimedOut` if the engine does not move in time. */
+    pub fn search(
+        &mut self,
+        position: &str,
+        go: &str,
+        timeout: Option<Duration>,
+    ) -> io::Result<EngineMove> {
+        self.send(position)?;
+        self.send(go)?;
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut score: Option<EngineScore> = None;
+        let mut depth = 0;
+
+        loop {
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let line = self.read_line(remaining)?;
+            let mut words = line.split_whitespace();
+
+            match words.next() {
+                Some("info") => parse_info(words, &mut score, &mut depth),
+                Some("bestmove") => {
+                    let best_move = words.next().ok_or_else(|| {
+                        io::Error::new(ErrorKind::InvalidData, "bestmove without a move")
+                    })?;
+
+                    return Ok(EngineMove {
+                        best_move: best_move.to_string(),
+                        score,
+                        depth,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{command}")?;
+        self.stdin.flush()
+    }
+
+    /** Waits for a line starting with the token, skipping all other lines. */
+    fn wait_for(&mut self, token: &str, timeout: Option<Duration>) -> io::Result<String> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let line = self.read_line(remaining)?;
+
+            if line.split_whitespace().next() == Some(token) {
+                return Ok(line);
+            }
+        }
+    }
+
+    fn read_line(&mut self, timeout: Option<Duration>) -> io::Result<String> {
+        let line = match timeout {
+            Some(timeout) => self.lines.recv_timeout(timeout).map_err(|e| match e {
+                RecvTimeoutError::Timeout => ErrorKind::TimedOut,
+                RecvTimeoutError::Disconnected => ErrorKind::UnexpectedEof,
+            }),
+            None => self.lines.recv().map_err(|_| ErrorKind::UnexpectedEof),
+        };
+
+        line.map_err(|kind| match kind {
+            ErrorKind::TimedOut => io::Error::new(kind, "the engine did not answer in time"),
+            _ => io::Error::new(kind, "the engine disconnected"),
+        })
+    }
+}
+
+/** Reads the depth and score of an info line, like `info depth 12 score cp 35 pv e2e4`. The
+bounds of aspiration windows are ignored. */
+fn parse_info<'a>(
+    words: impl Iterator<Item = &'a str>,
+    score: &mut Option<EngineScore>,
+    depth: &mut u32,
+) {
+    let words: Vec<&str> = words.collect();
+    let mut i = 0;
+
+    while i < words.len() {
+        match words[i] {
+            "depth" => {
+                if let Some(value) = words.get(i + 1).and_then(|value| value.parse().ok()) {
+                    *depth = value;
+                }
+                i += 2;
+            }
+            "score" => {
+                let value = words.get(i + 2).and_then(|value| value.parse().ok());
+                let parsed = match words.get(i + 1) {
+                    Some(&"cp") => value.map(EngineScore::Centipawns),
+                    Some(&"mate") => value.map(EngineScore::Mate),
+                    _ => None,
+                };
+                let is_bound = matches!(words.get(i + 3), Some(&"lowerbound" | &"upperbound"));
+
+                if !is_bound && parsed.is_some() {
+                    *score = parsed;
+                }
+                i += 3;
+            }
+            "pv" | "string" => return,
+            _ => i += 1,
+        }
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + QUIT_TIMEOUT;
+
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
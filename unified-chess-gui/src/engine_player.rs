@@ -0,0 +1,123 @@
+/* This module lets the GUI search with the engine without blocking the interface. Every search
+ * runs on its own thread and is returned as an iced `Task` that resolves to the best move, or that
+ * streams the reports of an analysis. Starting a search or stopping cancels the previous search,
+ * whose results are then ignored by its id. */
+use iced::futures::channel::{mpsc, oneshot};
+use iced::Task;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use unified_chess_engine::array_engine::{ChessBoard, Move};
+use unified_chess_engine::search::{SearchInfo, SearchLimits, Searcher};
+
+pub(crate) struct EnginePlayer {
+    searcher: Arc<Mutex<Searcher>>,
+    stop_signal: Arc<AtomicBool>,
+    /* The id of the latest search, searches with an older id are canceled. */
+    search_id: Arc<AtomicU64>,
+}
+
+impl Default for EnginePlayer {
+    fn default() -> Self {
+        let searcher = Searcher::default();
+        let stop_signal = searcher.stop_signal();
+
+        EnginePlayer {
+            searcher: Arc::new(Mutex::new(searcher)),
+            stop_signal,
+            search_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+}
+
+impl EnginePlayer {
+    /** Starts searching the position, the history holds the hashes of the positions played
+    before it. Returns the id of the search and the task that yields its best move. */
+    pub(crate) fn search(
+        &self,
+        chess_board: ChessBoard,
+        position_history: Vec<u64>,
+        limits: SearchLimits,
+    ) -> (u64, Task<Option<Move>>) {
+        self.stop();
+        let id = self.search_id.load(Ordering::SeqCst);
+
+        let (sender, receiver) = oneshot::channel();
+        let searcher = Arc::clone(&self.searcher);
+        let stop_signal = Arc::clone(&self.stop_signal);
+        let search_id = Arc::clone(&self.search_id);
+
+        thread::spawn(move || {
+            let mut searcher = searcher.lock().unwrap_or_else(|e| e.into_inner());
+
+            /* The search was canceled while an older search still held the searcher. */
+            if search_id.load(Ordering::SeqCst) != id {
+                let _ = sender.send(None);
+                return;
+            }
+
+            stop_signal.store(false, Ordering::SeqCst);
+            searcher.set_position_history(position_history);
+            let search_result = searcher.search(&chess_board, limits, |_| {});
+            let _ = sender.send(search_result.best_move);
+        });
+
+        let task = Task::perform(receiver, |best_move| best_move.ok().flatten());
+        (id, task)
+    }
+
+    /** Starts analysing the position with the given number of best lines until the search is
+    stopped. Returns the id of the search and the task that yields every report of the search. */
+    pub(crate) fn analyze(
+        &self,
+        chess_board: ChessBoard,
+        position_history: Vec<u64>,
+        lines: usize,
+    ) -> (u64, Task<SearchInfo>) {
+        self.stop();
+        let id = self.search_id.load(Ordering::SeqCst);
+
+        let (sender, receiver) = mpsc::unbounded();
+        let searcher = Arc::clone(&self.searcher);
+        let stop_signal = Arc::clone(&self.stop_signal);
+        let search_id = Arc::clone(&self.search_id);
+
+        thread::spawn(move || {
+            let mut searcher = searcher.lock().unwrap_or_else(|e| e.into_inner());
+
+            if search_id.load(Ordering::SeqCst) != id {
+                return;
+            }
+
+            stop_signal.store(false, Ordering::SeqCst);
+            searcher.set_position_history(position_history);
+            searcher.set_multi_pv(lines);
+            let limits = SearchLimits {
+                infinite: true,
+                ..SearchLimits::default()
+            };
+            searcher.search(&chess_board, limits, |info| {
+                let _ = sender.unbounded_send(info.clone());
+            });
+            searcher.set_multi_pv(1);
+        });
+
+        (id, Task::run(receiver, |info| info))
+    }
+
+    /** Stops the running search and cancels the searches waiting for it. */
+    pub(crate) fn stop(&self) {
+        self.search_id.fetch_add(1, Ordering::SeqCst);
+        self.stop_signal.store(true, Ordering::SeqCst);
+    }
+
+    /** Stops searching and forgets the previous game. */
+    pub(crate) fn new_game(&self) {
+        self.stop();
+        let searcher = Arc::clone(&self.searcher);
+
+        thread::spawn(move || {
+            searcher.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        });
+    }
+}
@@ -0,0 +1,615 @@
+/* This module loads and saves games as PGN files. The GUI has no native file dialogs, so the
+ * dialogs are drawn in the window: they list the subdirectories and PGN files of a directory and
+ * take a typed file name. A file with several games opens a searchable list of their headers to
+ * pick the game from. The FEN of the shown position and the PGN of the game can be copied to the
+ * clipboard, and a pasted FEN starts a game from that position. The recently used files are kept
+ * in the configuration directory. */
+use crate::{ChessApplication, GameState, Message};
+use iced::widget::{button, column, container, row, scrollable, text, text_input, Column};
+use iced::{clipboard, Element, Length, Task};
+use std::fs;
+use std::path::{Path, PathBuf};
+use unified_chess_engine::array_engine::ChessBoard;
+use unified_chess_engine::pgn::{parse_pgn, write_pgn, PgnGame, PgnReader};
+
+const RECENT_FILES_LIMIT: usize = 8;
+/* More matching games are not listed, the search narrows them down. */
+const LISTED_GAMES_LIMIT: usize = 500;
+const DIALOG_WIDTH: f32 = 600.0;
+const LIST_HEIGHT: f32 = 320.0;
+
+#[derive(Debug, Clone)]
+pub enum FileMessage {
+    OpenDialog,
+    SaveDialog,
+    CloseDialog,
+    Browse(PathBuf),
+    SetFileName(String),
+    /* Opens the file, or takes its name when saving. */
+    ChooseFile(PathBuf),
+    /* Opens or saves the typed file name. */
+    Submit,
+    SetGameSearch(String),
+    PickGame(usize),
+    CopyFen,
+    CopyPgn,
+    PasteFen,
+    FenPasted(Option<String>),
+}
+
+pub(crate) enum FileDialog {
+    Open(FileBrowser),
+    Save(FileBrowser),
+    PickGame(GamePicker),
+}
+
+pub(crate) struct FileBrowser {
+    directory: PathBuf,
+    /* The subdirectories and PGN files of the directory, the subdirectories first. */
+    entries: Vec<DirectoryEntry>,
+    file_name: String,
+    error: Option<String>,
+}
+
+struct DirectoryEntry {
+    path: PathBuf,
+    is_directory: bool,
+}
+
+pub(crate) struct GamePicker {
+    path: PathBuf,
+    games: Vec<PgnGame>,
+    search: String,
+}
+
+/** The recently opened and saved files, the latest first. */
+#[derive(Default)]
+pub(crate) struct RecentFiles {
+    /* The file the list is stored in, None keeps it in memory only. */
+    storage: Option<PathBuf>,
+    paths: Vec<PathBuf>,
+}
+
+impl RecentFiles {
+    /** Reads the list from the configuration directory. */
+    pub(crate) fn load() -> RecentFiles {
+        let storage = dirs::config_dir()
+            .map(|directory| directory.join("unified-chess").join("recent_files.txt"));
+        let paths = storage
+            .as_ref()
+            .and_then(|storage| fs::read_to_string(storage).ok())
+            .map(|contents| contents.lines().map(PathBuf::from).collect())
+            .unwrap_or_default();
+
+        RecentFiles { storage, paths }
+    }
+
+    /** Moves the file to the top of the list. The list is only a convenience, so failing to store
+    it is ignored. */
+    fn add(&mut self, path: &Path) {
+        self.paths.retain(|recent| recent != path);
+        self.paths.insert(0, path.to_path_buf());
+        self.paths.truncate(RECENT_FILES_LIMIT);
+
+        if let Some(storage) = &self.storage {
+            let contents: Vec<String> = self
+                .paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            if let Some(directory) = storage.parent() {
+                let _ = fs::create_dir_all(directory);
+            }
+            let _ = fs::write(storage, contents.join("\n"));
+        }
+    }
+}
+
+impl FileBrowser {
+    fn new(directory: PathBuf, file_name: String) -> FileBrowser {
+        let mut file_browser = FileBrowser {
+            directory: PathBuf::new(),
+            entries: Vec::new(),
+            file_name,
+            error: None,
+        };
+        file_browser.browse(directory);
+        file_browser
+    }
+
+    /** Lists the directory, hidden entries and files other than PGN files are left out. */
+    fn browse(&mut self, directory: PathBuf) {
+        let read_entries = fs::read_dir(&directory).map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| DirectoryEntry {
+                    is_directory: entry.path().is_dir(),
+                    path: entry.path(),
+                })
+                .filter(|entry| {
+                    let name = entry.path.file_name().unwrap_or_default().to_string_lossy();
+                    !name.starts_with('.') && (entry.is_directory || is_pgn_file(&entry.path))
+                })
+                .collect::<Vec<_>>()
+        });
+
+        match read_entries {
+            Ok(mut entries) => {
+                entries.sort_by(|a, b| {
+                    b.is_directory
+                        .cmp(&a.is_directory)
+                        .then_with(|| a.path.cmp(&b.path))
+                });
+                self.directory = directory;
+                self.entries = entries;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("Cannot open {}: {e}", directory.display())),
+        }
+    }
+
+    /** The typed file name, relative to the listed directory unless it is absolute. */
+    fn typed_path(&self) -> PathBuf {
+        self.directory.join(self.file_name.trim())
+    }
+}
+
+fn is_pgn_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pgn"))
+}
+
+/** Reads the games of a PGN file. Games with errors are skipped, unless no game can be read. */
+fn read_games(path: &Path) -> Result<Vec<PgnGame>, String> {
+    let reader =
+        PgnReader::open(path).map_err(|e| format!("Cannot open {}: {e}", path.display()))?;
+
+    let mut games = Vec::new();
+    let mut first_error = None;
+    for game in reader {
+        match game {
+            Ok(game) => games.push(game),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    match (games.is_empty(), first_error) {
+        (true, Some(e)) => Err(e.to_string()),
+        (true, None) => Err(format!("{} has no games", path.display())),
+        (false, _) => Ok(games),
+    }
+}
+
+/** Saves the game to the file. The other games of an existing file are kept, the game replaces
+the game it was loaded from, or is added at the end. A file with a game that cannot be read is
+left alone, writing it would lose that game. Returns the index of the game in the file. */
+fn save_game(
+    path: &Path,
+    game: &PgnGame,
+    source: Option<&(PathBuf, usize)>,
+) -> Result<usize, String> {
+    let mut games = match path.exists() {
+        true => {
+            let contents = fs::read_to_string(path)
+                .map_err(|e| format!("Cannot open {}: {e}", path.display()))?;
+            parse_pgn(&contents).map_err(|e| format!("Not saved, {}: {e}", path.display()))?
+        }
+        false => Vec::new(),
+    };
+
+    let index = match source {
+        Some((source_path, index)) if source_path == path && *index < games.len() => {
+            games[*index] = game.clone();
+            *index
+        }
+        _ => {
+            games.push(game.clone());
+            games.len() - 1
+        }
+    };
+
+    let mut contents = Vec::new();
+    write_pgn(&mut contents, &games).map_err(|e| e.to_string())?;
+    fs::write(path, contents).map_err(|e| format!("Cannot write {}: {e}", path.display()))?;
+    Ok(index)
+}
+
+/** A line with the players, event, date and result of a game, which the search looks through. */
+fn game_label(game: &PgnGame) -> String {
+    let tag = |name| game.tag(name).unwrap_or("?");
+
+    format!(
+        "{} - {}, {}, {} {}",
+        tag("White"),
+        tag("Black"),
+        tag("Event"),
+        tag("Date"),
+        game.result.to_pgn()
+    )
+}
+
+impl GamePicker {
+    /** The indices and labels of the games matching the search, ignoring case. */
+    fn matching_games(&self) -> Vec<(usize, String)> {
+        let search = self.search.to_lowercase();
+
+        self.games
+            .iter()
+            .map(game_label)
+            .enumerate()
+            .filter(|(_, label)| label.to_lowercase().contains(&search))
+            .take(LISTED_GAMES_LIMIT)
+            .collect()
+    }
+}
+
+impl ChessApplication {
+    pub(crate) fn update_files(&mut self, message: FileMessage) -> Task<Message> {
+        match message {
+            FileMessage::OpenDialog => {
+                let browser = FileBrowser::new(self.start_directory(), String::new());
+                self.file_dialog = Some(FileDialog::Open(browser));
+            }
+
+            FileMessage::SaveDialog => {
+                let file_name = match self.game_source() {
+                    Some((path, _)) => path.file_name().unwrap_or_default().to_string_lossy(),
+                    None => "game.pgn".into(),
+                }
+                .to_string();
+                let browser = FileBrowser::new(self.start_directory(), file_name);
+                self.file_dialog = Some(FileDialog::Save(browser));
+            }
+
+            FileMessage::CloseDialog => {
+                self.file_dialog = None;
+            }
+
+            FileMessage::Browse(directory) => {
+                if let Some(FileDialog::Open(browser) | FileDialog::Save(browser)) =
+                    &mut self.file_dialog
+                {
+                    browser.browse(directory);
+                }
+            }
+
+            FileMessage::SetFileName(file_name) => {
+                if let Some(FileDialog::Open(browser) | FileDialog::Save(browser)) =
+                    &mut self.file_dialog
+                {
+                    browser.file_name = file_name;
+                }
+            }
+
+            FileMessage::ChooseFile(path) => match &mut self.file_dialog {
+                Some(FileDialog::Save(browser)) => {
+                    browser.file_name = path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into();
+                }
+                _ => return self.open_file(path),
+            },
+
+            FileMessage::Submit => match &mut self.file_dialog {
+                Some(FileDialog::Open(browser)) => {
+                    let path = browser.typed_path();
+                    if path.is_dir() {
+                        browser.browse(path);
+                    } else {
+                        return self.open_file(path);
+                    }
+                }
+                Some(FileDialog::Save(browser)) => {
+                    let mut path = browser.typed_path();
+                    if path.extension().is_none() {
+                        path.set_extension("pgn");
+                    }
+                    self.save_file(path);
+                }
+                _ => {}
+            },
+
+            FileMessage::SetGameSearch(search) => {
+                if let Some(FileDialog::PickGame(picker)) = &mut self.file_dialog {
+                    picker.search = search;
+                }
+            }
+
+            FileMessage::PickGame(index) => {
+                if let Some(FileDialog::PickGame(picker)) = self.file_dialog.take() {
+                    if let Some(game) = picker.games.into_iter().nth(index) {
+                        return self.open_game(game, Some((picker.path, index)));
+                    }
+                }
+            }
+
+            FileMessage::CopyFen => {
+                if let Some(game_state) = &self.game_instance {
+                    self.notice = Some("FEN copied".to_string());
+                    return clipboard::write(game_state.chess_board.to_fen());
+                }
+            }
+
+            FileMessage::CopyPgn => {
+                if let Some(game_state) = &self.game_instance {
+                    self.notice = Some("PGN copied".to_string());
+                    return clipboard::write(game_state.game.to_string());
+                }
+            }
+
+            FileMessage::PasteFen => {
+                return clipboard::read().map(|text| Message::File(FileMessage::FenPasted(text)));
+            }
+
+            FileMessage::FenPasted(text) => {
+                let text = text.unwrap_or_default();
+                match ChessBoard::new_from_fen(text.trim()) {
+                    Ok(chess_board) => match chess_board.validate() {
+                        Ok(()) => return self.open_game(PgnGame::new(chess_board), None),
+                        Err(e) => self.notice = Some(e.to_string()),
+                    },
+                    Err(_) => {
+                        self.notice = Some("The clipboard does not hold a valid FEN".to_string());
+                    }
+                }
+            }
+        }
+
+        Task::none()
+    }
+
+    /** The directory of the game or of the latest file, or the working directory. */
+    fn start_directory(&self) -> PathBuf {
+        self.game_source()
+            .map(|(path, _)| path)
+            .or(self.recent_files.paths.first())
+            .and_then(|path| path.parent())
+            .filter(|directory| directory.is_dir())
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default()
+    }
+
+    fn game_source(&self) -> Option<&(PathBuf, usize)> {
+        self.game_instance
+            .as_ref()
+            .and_then(|game_state| game_state.source.as_ref())
+    }
+
+    /** Opens the game of a file with a single game, or the list of games to pick from. */
+    fn open_file(&mut self, path: PathBuf) -> Task<Message> {
+        match read_games(&path) {
+            Ok(mut games) if games.len() == 1 => self.open_game(games.remove(0), Some((path, 0))),
+            Ok(games) => {
+                self.recent_files.add(&path);
+                self.file_dialog = Some(FileDialog::PickGame(GamePicker {
+                    path,
+                    games,
+                    search: String::new(),
+                }));
+                Task::none()
+            }
+            Err(e) => {
+                if let Some(FileDialog::Open(browser)) = &mut self.file_dialog {
+                    browser.error = Some(e);
+                }
+                Task::none()
+            }
+        }
+    }
+
+    /** Replaces the game with a loaded game, which is played on by the settings of the previous
+    game. The game starts from its first position. */
+    pub(crate) fn open_game(
+        &mut self,
+        game: PgnGame,
+        source: Option<(PathBuf, usize)>,
+    ) -> Task<Message> {
+        if let Some((path, _)) = &source {
+            self.recent_files.add(path);
+        }
+
+        let settings = self
+            .game_instance
+            .as_ref()
+            .map(|game_state| game_state.settings)
+            .unwrap_or_default();
+
+        let mut game_state = GameState::from_game(settings, game);
+        game_state.source = source;
+        self.engine.new_game();
+        self.game_instance = Some(game_state);
+        self.file_dialog = None;
+        self.notice = None;
+        self.engine_turn()
+    }
+
+    fn save_file(&mut self, path: PathBuf) {
+        let Some(game_state) = &mut self.game_instance else {
+            return;
+        };
+
+        match save_game(&path, &game_state.game, game_state.source.as_ref()) {
+            Ok(index) => {
+                self.recent_files.add(&path);
+                self.notice = Some(format!("Saved to {}", path.display()));
+                game_state.source = Some((path, index));
+                self.file_dialog = None;
+            }
+            Err(e) => {
+                if let Some(FileDialog::Save(browser)) = &mut self.file_dialog {
+                    browser.error = Some(e);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn file_dialog_view<'a>(
+    file_dialog: &'a FileDialog,
+    recent_files: &'a RecentFiles,
+) -> Element<'a, Message> {
+    let content = match file_dialog {
+        FileDialog::Open(browser) => browser_view("Open PGN", "Open", browser, recent_files),
+        FileDialog::Save(browser) => browser_view("Save PGN", "Save", browser, recent_files),
+        FileDialog::PickGame(picker) => game_picker_view(picker),
+    };
+
+    container(content)
+        .padding(20)
+        .width(DIALOG_WIDTH)
+        .style(container::rounded_box)
+        .into()
+}
+
+fn file_message(message: FileMessage) -> Message {
+    Message::File(message)
+}
+
+fn browser_view<'a>(
+    title: &'a str,
+    action: &'a str,
+    browser: &'a FileBrowser,
+    recent_files: &'a RecentFiles,
+) -> Element<'a, Message> {
+    let parent = browser.directory.parent().map(Path::to_path_buf);
+    let location = row![
+        button("Up").on_press_maybe(parent.map(|parent| file_message(FileMessage::Browse(parent)))),
+        text(browser.directory.display().to_string()),
+    ]
+    .spacing(10);
+
+    let entries = browser.entries.iter().map(|entry| {
+        let name = entry.path.file_name().unwrap_or_default().to_string_lossy();
+        let (label, message) = match entry.is_directory {
+            true => (format!("{name}/"), FileMessage::Browse(entry.path.clone())),
+            false => (
+                name.to_string(),
+                FileMessage::ChooseFile(entry.path.clone()),
+            ),
+        };
+
+        button(text(label))
+            .width(Length::Fill)
+            .style(button::text)
+            .on_press(file_message(message))
+            .into()
+    });
+
+    let file_name = row![
+        text_input("File name", &browser.file_name)
+            .on_input(|file_name| file_message(FileMessage::SetFileName(file_name)))
+            .on_submit(file_message(FileMessage::Submit)),
+        button(action).on_press(file_message(FileMessage::Submit)),
+        button("Cancel")
+            .style(button::secondary)
+            .on_press(file_message(FileMessage::CloseDialog)),
+    ]
+    .spacing(10);
+
+    let mut dialog = column![
+        text(title).size(24),
+        location,
+        scrollable(Column::with_children(entries)).height(LIST_HEIGHT),
+        file_name,
+    ]
+    .spacing(10);
+
+    if let Some(error) = &browser.error {
+        dialog = dialog.push(text(error).color(iced::Color::from_rgb(0.9, 0.4, 0.4)));
+    }
+
+    /* Saving keeps to the listed directory, a recent file is opened right away. */
+    if action == "Open" && !recent_files.paths.is_empty() {
+        let recent = recent_files.paths.iter().map(|path| {
+            button(text(path.display().to_string()).size(14))
+                .width(Length::Fill)
+                .style(button::text)
+                .on_press(file_message(FileMessage::ChooseFile(path.clone())))
+                .into()
+        });
+        dialog = dialog
+            .push(text("Recent files"))
+            .push(Column::with_children(recent));
+    }
+
+    dialog.into()
+}
+
+fn game_picker_view(picker: &GamePicker) -> Element<'_, Message> {
+    let file_name = picker
+        .path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let games = picker.matching_games().into_iter().map(|(index, label)| {
+        button(text(label).size(14))
+            .width(Length::Fill)
+            .style(button::text)
+            .on_press(file_message(FileMessage::PickGame(index)))
+            .into()
+    });
+
+    column![
+        text(format!("{} games in {file_name}", picker.games.len())).size(24),
+        text_input("Search players, event or date", &picker.search)
+            .on_input(|search| file_message(FileMessage::SetGameSearch(search))),
+        scrollable(Column::with_children(games)).height(LIST_HEIGHT),
+        button("Cancel")
+            .style(button::secondary)
+            .on_press(file_message(FileMessage::CloseDialog)),
+    ]
+    .spacing(10)
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Navigation;
+
+    #[test]
+    fn test_open_and_save() {
+        let directory = std::env::temp_dir().join(format!("chess-gui-test-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("games.pgn");
+        let pgn = "[White \"Anand\"]\n[Black \"Carlsen\"]\n\n1. e4 e5 *\n\n\
+                   [White \"Kasparov\"]\n[Black \"Karpov\"]\n\n1. d4 d5 2. c4 *\n";
+        fs::write(&path, pgn).unwrap();
+
+        let mut application = ChessApplication::default();
+        let _ = application.update(Message::File(FileMessage::OpenDialog));
+        let _ = application.update(Message::File(FileMessage::ChooseFile(path.clone())));
+        let _ = application.update(Message::File(FileMessage::SetGameSearch("KARPOV".into())));
+        let Some(FileDialog::PickGame(picker)) = &application.file_dialog else {
+            panic!("The games of the file are listed");
+        };
+        assert_eq!(picker.matching_games()[0].0, 1);
+
+        /* The picked game replaces its original in the file, the other game is kept. */
+        let _ = application.update(Message::File(FileMessage::PickGame(1)));
+        let _ = application.update(Message::Navigate(Navigation::End));
+        let game_state = application.game_instance.as_ref().unwrap();
+        assert_eq!(game_state.game.moves.len(), 3);
+        assert!(game_state.status().starts_with("Black to move"));
+
+        let _ = application.update(Message::File(FileMessage::SaveDialog));
+        let _ = application.update(Message::File(FileMessage::Submit));
+        let games = read_games(&path).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[1].tag("White"), Some("Kasparov"));
+        assert_eq!(application.recent_files.paths, [path]);
+
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1";
+        let _ = application.update(Message::File(FileMessage::FenPasted(Some(fen.into()))));
+        let game_state = application.game_instance.as_ref().unwrap();
+        assert_eq!(game_state.chess_board.to_fen(), fen);
+        assert!(game_state.source.is_none());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -1,12 +1,40 @@
+mod analysis;
+mod board;
+mod board_editor;
 pub mod cli;
+mod clock;
+pub mod engine_match;
+mod engine_player;
+mod game_files;
+mod move_list;
+mod new_game;
 
-use iced;
-use iced::application::Update;
-use iced::widget::{container, row, text};
-use iced::Element;
+use analysis::{analysis_view, evaluation_bar, Analysis, DEFAULT_ANALYSIS_LINES};
+use board::{board_view, coordinate_at, MoveTarget, PieceImages};
+use board_editor::{board_editor_view, BoardEditor, EditorMessage};
+use clock::{clock_view, Clock};
+use engine_player::EnginePlayer;
+use game_files::{file_dialog_view, FileDialog, FileMessage, RecentFiles};
+use iced::keyboard::key::Named;
+use iced::keyboard::{self, Key};
+use iced::widget::{button, checkbox, column, container, mouse_area, opaque, row, stack, text};
 use iced::Result;
+use iced::Task;
 use iced::Theme;
-use unified_chess_engine::array_engine::{ChessBoard, Piece, PieceType, Position};
+use iced::{time, Background, Element, Length, Point, Subscription};
+use move_list::move_list_view;
+use new_game::{new_game_view, GameMode, NewGameSettings, PlayerSide};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use unified_chess_engine::array_engine::termination::Termination;
+use unified_chess_engine::array_engine::{self, ChessBoard, Color, PieceType, Position};
+use unified_chess_engine::pgn::{GameResult, PgnGame, PgnMove};
+use unified_chess_engine::search::time_management::TimeControl;
+use unified_chess_engine::search::{SearchInfo, SearchLimits};
+
+/* The time the engine searches for a hint. */
+const HINT_MOVE_TIME: Duration = Duration::from_secs(1);
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
 
 struct UserMove {
     start_position: Position,
@@ -14,6 +42,28 @@ struct UserMove {
     promotion_piece: Option<PieceType>,
 }
 
+impl UserMove {
+    fn from_legal_move(legal_move: array_engine::Move) -> UserMove {
+        UserMove {
+            start_position: legal_move.start_pos,
+            end_position: legal_move.end_pos,
+            promotion_piece: legal_move.meta_data.promotion_piece,
+        }
+    }
+
+    /** Finds the legal move matching a move given by the user. Without a promotion piece the
+    first matching move is used. */
+    fn find_legal_move(&self, chess_board: &ChessBoard) -> Option<array_engine::Move> {
+        chess_board.legal_moves().into_iter().find(|legal_move| {
+            legal_move.start_pos == self.start_position
+                && legal_move.end_pos == self.end_position
+                && (self.promotion_piece.is_none()
+                    || self.promotion_piece == legal_move.meta_data.promotion_piece)
+        })
+    }
+}
+
+#[allow(dead_code)]
 enum ColoredPieces {
     WKing,
     BKing,
@@ -32,67 +82,1490 @@ enum ColoredPieces {
 #[derive(Default)]
 pub struct ChessApplication {
     pub game_instance: Option<GameState>,
+    piece_images: PieceImages,
+    /* Promote pawns to queens without showing the promotion picker. */
+    always_promote_to_queen: bool,
+    /* Black is at the bottom of the board. */
+    board_flipped: bool,
+    engine: EnginePlayer,
+    /* The settings of the new game dialog while it is open. */
+    new_game_dialog: Option<NewGameSettings>,
+    /* The open, save or game picking dialog while it is open. */
+    file_dialog: Option<FileDialog>,
+    recent_files: RecentFiles,
+    /* The position setup while it is open, it replaces the game on the screen. */
+    board_editor: Option<BoardEditor>,
+    /* A message about the last file or clipboard action, like an error reading the clipboard. */
+    notice: Option<String>,
 }
 
 pub struct GameState {
     selected_square: Option<Coordinate>,
+    /* The position shown on the board, after `current_ply` moves of the game and the moves of
+    the shown variation. */
     chess_board: ChessBoard,
+    game: PgnGame,
+    current_ply: usize,
+    /* The variation shown on the board, None on the main line. */
+    variation: Option<VariationCursor>,
+    drag: Option<Drag>,
+    /* The cursor relative to the board, while it is over the board. */
+    cursor_position: Option<Point>,
+    /* A pawn move to the last rank waiting for the promotion piece. */
+    pending_promotion: Option<Move>,
+    settings: NewGameSettings,
+    /* The ids of the running searches for the engine's move and for a hint. */
+    engine_search: Option<u64>,
+    hint_search: Option<u64>,
+    /* The move suggested by the engine, highlighted on the board. */
+    hint: Option<Move>,
+    /* The clocks in games with a time control. */
+    clock: Option<Clock>,
+    /* The side that ran out of time. */
+    flagged: Option<Color>,
+    /* The clocks are stopped and no moves can be made. */
+    paused: bool,
+    /* The engine analysis of the shown position, while analysis mode is on. */
+    analysis: Option<Analysis>,
+    /* The file the game was loaded from or saved to, and the index of the game in it. */
+    source: Option<(PathBuf, usize)>,
+}
+
+/** A position in a variation, which is an alternative to a move of the main line. */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VariationCursor {
+    /* The index of the main line move the variation replaces. */
+    move_index: usize,
+    /* The index of the variation among the variations of the move. */
+    variation: usize,
+    /* The moves of the variation played on the board. */
+    plies: usize,
+}
+
+/* A piece held with the mouse button. */
+struct Drag {
+    from: Coordinate,
+    /* The piece was already selected, so putting it back on its square deselects it. */
+    was_selected: bool,
 }
 
 #[derive(Clone, Copy, Debug)]
-struct Move {
+pub struct Move {
     start_position: Coordinate,
     end_position: Coordinate,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Move {
+    fn from_legal_move(legal_move: array_engine::Move) -> Move {
+        let (start_x, start_y) = legal_move.start_pos;
+        let (end_x, end_y) = legal_move.end_pos;
+        Move {
+            start_position: Coordinate {
+                x: start_x,
+                y: start_y,
+            },
+            end_position: Coordinate { x: end_x, y: end_y },
+        }
+    }
+
+    fn to_user_move(self, promotion_piece: Option<PieceType>) -> UserMove {
+        UserMove {
+            start_position: (self.start_position.x, self.start_position.y),
+            end_position: (self.end_position.x, self.end_position.y),
+            promotion_piece,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Coordinate {
     x: usize,
     y: usize,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum Message {
+    /* Opens the new game dialog. */
     StartNewGame,
+    SetNewGameSettings(NewGameSettings),
+    ConfirmNewGame,
+    CancelNewGame,
     QuitGame,
     ClickSquare(Coordinate),
+    MoveCursor(Point),
+    DropPiece,
+    CancelDrag,
     MakeMove(Move),
+    PromotePawn(PieceType),
+    CancelPromotion,
+    SetAlwaysPromoteToQueen(bool),
+    FlipBoard,
+    /* Shows the position after the given number of moves. */
+    GoToPly(usize),
+    GoToVariation(VariationCursor),
+    Navigate(Navigation),
+    /* The best move found by the engine search with the given id. */
+    EngineMoved(u64, Option<array_engine::Move>),
+    RequestHint,
+    HintFound(u64, Option<array_engine::Move>),
+    /* Redraws the running clock and checks whether its time is up. */
+    Tick(Instant),
+    TogglePause,
+    ToggleAnalysis,
+    SetAnalysisLines(usize),
+    /* A report of the analysis with the given id. */
+    AnalysisInfo(u64, SearchInfo),
+    /* Plays moves of an analysis line from the shown position. */
+    PlayAnalysisMoves(Vec<array_engine::Move>),
+    /* Loading and saving games, and the clipboard. */
+    File(FileMessage),
+    /* Setting up a position. */
+    Editor(EditorMessage),
+}
+
+/** Steps through the moves of the game, bound to the arrow keys. */
+#[derive(Clone, Copy, Debug)]
+pub enum Navigation {
+    Start,
+    Back,
+    Forward,
+    End,
 }
 
 impl ChessApplication {
+    /** The application with the recent files of earlier sessions. */
+    pub fn new() -> ChessApplication {
+        ChessApplication {
+            recent_files: RecentFiles::load(),
+            ..ChessApplication::default()
+        }
+    }
+
     pub fn run(&mut self) -> Result {
-        iced::run(Self::title, Self::update, Self::view)
+        let application = std::mem::take(self);
+        iced::application(Self::title, Self::update, Self::view)
+            .theme(Self::theme)
+            .subscription(Self::subscription)
+            .run_with(move || (application, Task::none()))
     }
     fn title(&self) -> String {
         String::from("Chess-rs")
     }
 
-    fn update(&mut self, message: Message) {
+    fn update(&mut self, message: Message) -> Task<Message> {
         match message {
-            Message::StartNewGame => {}
+            Message::StartNewGame => {
+                let settings = match &self.game_instance {
+                    Some(game_state) => game_state.settings,
+                    None => NewGameSettings::default(),
+                };
+                self.new_game_dialog = Some(settings);
+            }
+
+            Message::SetNewGameSettings(settings) => {
+                if self.new_game_dialog.is_some() {
+                    self.new_game_dialog = Some(settings);
+                }
+            }
+
+            Message::ConfirmNewGame => {
+                if let Some(settings) = self.new_game_dialog.take() {
+                    self.engine.new_game();
+                    /* The human player is at the bottom of the board. */
+                    self.board_flipped = settings.mode == GameMode::HumanVsEngine
+                        && settings.player_side == PlayerSide::Black;
+                    /* A game from the position setup starts from its position. */
+                    let starting_position = self
+                        .board_editor
+                        .take()
+                        .and_then(|editor| editor.chess_board().ok());
+                    self.game_instance = Some(match starting_position {
+                        Some(chess_board) => {
+                            GameState::from_game(settings, PgnGame::new(chess_board))
+                        }
+                        None => GameState::new(settings),
+                    });
+                    self.notice = None;
+                    return self.engine_turn();
+                }
+            }
+
+            Message::CancelNewGame => {
+                self.new_game_dialog = None;
+            }
+
+            Message::QuitGame => return iced::exit(),
+
+            Message::ClickSquare(coordinate) => {
+                if let Some(game_state) = &mut self.game_instance {
+                    if let Some(chess_move) = game_state.click_square(coordinate) {
+                        return self.update(Message::MakeMove(chess_move));
+                    }
+                }
+            }
 
-            Message::QuitGame => {}
+            Message::MoveCursor(position) => {
+                if let Some(game_state) = &mut self.game_instance {
+                    game_state.cursor_position = Some(position);
+                }
+            }
 
-            Message::ClickSquare(coordinate) => {}
+            Message::DropPiece => {
+                if let Some(game_state) = &mut self.game_instance {
+                    if let Some(chess_move) = game_state.drop_piece(self.board_flipped) {
+                        return self.update(Message::MakeMove(chess_move));
+                    }
+                }
+            }
 
-            Message::MakeMove(chess_move) => {}
+            Message::CancelDrag => {
+                if let Some(game_state) = &mut self.game_instance {
+                    game_state.drag = None;
+                    game_state.cursor_position = None;
+                }
+            }
+
+            Message::MakeMove(chess_move) => {
+                if let Some(game_state) = &mut self.game_instance {
+                    let played = if !game_state.is_promotion(chess_move) {
+                        game_state.make_move(&chess_move.to_user_move(None))
+                    } else if self.always_promote_to_queen {
+                        game_state.make_move(&chess_move.to_user_move(Some(PieceType::Queen)))
+                    } else {
+                        game_state.pending_promotion = Some(chess_move);
+                        false
+                    };
+
+                    if played {
+                        return self.after_move();
+                    }
+                }
+            }
+
+            Message::PromotePawn(piece_type) => {
+                if let Some(game_state) = &mut self.game_instance {
+                    if let Some(chess_move) = game_state.pending_promotion.take() {
+                        if game_state.make_move(&chess_move.to_user_move(Some(piece_type))) {
+                            return self.after_move();
+                        }
+                    }
+                }
+            }
+
+            Message::CancelPromotion => {
+                if let Some(game_state) = &mut self.game_instance {
+                    game_state.pending_promotion = None;
+                }
+            }
+
+            Message::SetAlwaysPromoteToQueen(always_promote_to_queen) => {
+                self.always_promote_to_queen = always_promote_to_queen;
+            }
+
+            Message::FlipBoard => {
+                self.board_flipped = !self.board_flipped;
+            }
+
+            Message::GoToPly(ply) => {
+                if let Some(game_state) = &mut self.game_instance {
+                    game_state.go_to_ply(ply);
+                }
+                return self.analyze();
+            }
+
+            Message::GoToVariation(cursor) => {
+                if let Some(game_state) = &mut self.game_instance {
+                    game_state.go_to_variation(cursor);
+                }
+                return self.analyze();
+            }
+
+            Message::Navigate(navigation) => {
+                if let Some(game_state) = &mut self.game_instance {
+                    /* The arrows step through the shown variation, the ends are on the main line. */
+                    match (game_state.variation, navigation) {
+                        (Some(cursor), Navigation::Back) => {
+                            game_state.go_to_variation(VariationCursor {
+                                plies: cursor.plies - 1,
+                                ..cursor
+                            });
+                        }
+                        (Some(cursor), Navigation::Forward) => {
+                            game_state.go_to_variation(VariationCursor {
+                                plies: cursor.plies + 1,
+                                ..cursor
+                            });
+                        }
+                        _ => {
+                            let ply = match navigation {
+                                Navigation::Start => 0,
+                                Navigation::Back => game_state.current_ply.saturating_sub(1),
+                                Navigation::Forward => game_state.current_ply + 1,
+                                Navigation::End => game_state.game.moves.len(),
+                            };
+                            game_state.go_to_ply(ply);
+                        }
+                    }
+                }
+                return self.analyze();
+            }
+
+            Message::EngineMoved(id, best_move) => {
+                if let Some(game_state) = &mut self.game_instance {
+                    if game_state.engine_search != Some(id) {
+                        return Task::none();
+                    }
+                    game_state.engine_search = None;
+
+                    /* The engine's move is shown even when looking at an earlier position. */
+                    game_state.go_to_ply(game_state.game.moves.len());
+                    let played = best_move.is_some_and(|best_move| {
+                        game_state.make_move(&UserMove::from_legal_move(best_move))
+                    });
+
+                    if played {
+                        return self.engine_turn();
+                    }
+                }
+            }
+
+            Message::RequestHint => {
+                if let Some(game_state) = &mut self.game_instance {
+                    if game_state.can_request_hint() {
+                        let limits = SearchLimits {
+                            move_time: Some(HINT_MOVE_TIME),
+                            ..SearchLimits::default()
+                        };
+                        let (id, task) = self.engine.search(
+                            game_state.last_position(),
+                            game_state.position_history(),
+                            limits,
+                        );
+                        game_state.hint_search = Some(id);
+                        return task.map(move |best_move| Message::HintFound(id, best_move));
+                    }
+                }
+            }
+
+            Message::HintFound(id, best_move) => {
+                if let Some(game_state) = &mut self.game_instance {
+                    if game_state.hint_search == Some(id) {
+                        game_state.hint_search = None;
+                        game_state.hint = best_move.map(Move::from_legal_move);
+                    }
+                }
+            }
+
+            Message::Tick(now) => {
+                if let Some(game_state) = &mut self.game_instance {
+                    if game_state.check_flag(now) {
+                        self.engine.stop();
+                        game_state.engine_search = None;
+                        game_state.hint_search = None;
+                    }
+                }
+            }
+
+            Message::TogglePause => {
+                if let Some(game_state) = &mut self.game_instance {
+                    game_state.toggle_pause(Instant::now());
+                }
+            }
+
+            Message::ToggleAnalysis => {
+                if let Some(game_state) = &mut self.game_instance {
+                    if game_state.analysis.take().is_some() {
+                        self.engine.stop();
+                    } else if game_state.can_analyze() {
+                        game_state.analysis = Some(Analysis::new(
+                            DEFAULT_ANALYSIS_LINES,
+                            game_state.chess_board,
+                        ));
+                        return self.analyze();
+                    }
+                }
+            }
+
+            Message::SetAnalysisLines(lines) => {
+                if let Some(analysis) = self
+                    .game_instance
+                    .as_mut()
+                    .and_then(|game_state| game_state.analysis.as_mut())
+                {
+                    analysis.lines = lines;
+                    return self.analyze();
+                }
+            }
+
+            Message::AnalysisInfo(id, info) => {
+                if let Some(analysis) = self
+                    .game_instance
+                    .as_mut()
+                    .and_then(|game_state| game_state.analysis.as_mut())
+                {
+                    if analysis.search == Some(id) {
+                        analysis.update(info);
+                    }
+                }
+            }
+
+            Message::PlayAnalysisMoves(moves) => {
+                if let Some(game_state) = &mut self.game_instance {
+                    for chess_move in moves {
+                        if !game_state.make_move(&UserMove::from_legal_move(chess_move)) {
+                            break;
+                        }
+                    }
+                    return self.after_move();
+                }
+            }
+
+            Message::File(message) => return self.update_files(message),
+            Message::Editor(message) => return self.update_editor(message),
         }
+
+        Task::none()
+    }
+
+    /** Continues the game after a move: the analysis follows the move, or the engine replies. */
+    fn after_move(&mut self) -> Task<Message> {
+        match self
+            .game_instance
+            .as_ref()
+            .is_some_and(|game_state| game_state.analysis.is_some())
+        {
+            true => self.analyze(),
+            false => self.engine_turn(),
+        }
+    }
+
+    /** Restarts the analysis on the shown position while analysis mode is on. */
+    fn analyze(&mut self) -> Task<Message> {
+        let Some(game_state) = &mut self.game_instance else {
+            return Task::none();
+        };
+        let chess_board = game_state.chess_board;
+        let position_history = game_state.shown_position_history();
+        let Some(analysis) = &mut game_state.analysis else {
+            return Task::none();
+        };
+
+        *analysis = Analysis::new(analysis.lines, chess_board);
+        if chess_board.legal_moves().is_empty() {
+            self.engine.stop();
+            return Task::none();
+        }
+
+        let (id, task) = self
+            .engine
+            .analyze(chess_board, position_history, analysis.lines);
+        analysis.search = Some(id);
+        task.map(move |info| Message::AnalysisInfo(id, info))
+    }
+
+    /** Starts the search for the engine's move when an engine is to move in a game that is not
+    over. */
+    fn engine_turn(&mut self) -> Task<Message> {
+        let Some(game_state) = &mut self.game_instance else {
+            return Task::none();
+        };
+
+        if !game_state.is_engine_turn() || game_state.is_over() {
+            return Task::none();
+        }
+
+        let (id, task) = self.engine.search(
+            game_state.last_position(),
+            game_state.position_history(),
+            game_state.engine_limits(),
+        );
+        game_state.engine_search = Some(id);
+        task.map(move |best_move| Message::EngineMoved(id, best_move))
     }
 
     fn view(&self) -> Element<'_, Message> {
-        container(row![
-            text("This should be left"),
-            text("This should be right")
-        ])
-        .padding(20)
-        .into()
+        let file_message = |message| self.game_instance.as_ref().map(|_| Message::File(message));
+        let file_buttons = column![
+            row![
+                button("Open PGN (Ctrl+O)").on_press(Message::File(FileMessage::OpenDialog)),
+                button("Save PGN (Ctrl+S)").on_press_maybe(file_message(FileMessage::SaveDialog)),
+            ]
+            .spacing(10),
+            row![
+                button("Copy FEN").on_press_maybe(file_message(FileMessage::CopyFen)),
+                button("Copy PGN").on_press_maybe(file_message(FileMessage::CopyPgn)),
+                button("Paste FEN").on_press(Message::File(FileMessage::PasteFen)),
+            ]
+            .spacing(10),
+        ]
+        .spacing(10);
+
+        let game_buttons = column![
+            button("New game").on_press(Message::StartNewGame),
+            button("Set up position").on_press(Message::Editor(EditorMessage::Open)),
+            file_buttons,
+            button("Flip board (F)").on_press(Message::FlipBoard),
+            button("Quit").on_press(Message::QuitGame),
+            checkbox("Always promote to queen", self.always_promote_to_queen)
+                .on_toggle(Message::SetAlwaysPromoteToQueen),
+        ]
+        .spacing(10);
+
+        let content = match (&self.board_editor, &self.game_instance) {
+            (Some(editor), _) => row![board_editor_view(
+                editor,
+                &self.piece_images,
+                self.board_flipped
+            )],
+            (None, Some(game_state)) => {
+                let thinking = match game_state.engine_search {
+                    Some(_) => "The engine is thinking",
+                    None => "",
+                };
+                let hint_button = button("Hint").on_press_maybe(
+                    game_state
+                        .can_request_hint()
+                        .then_some(Message::RequestHint),
+                );
+                let analysis_label = match game_state.analysis {
+                    Some(_) => "Stop analysis",
+                    None => "Analyse",
+                };
+                let analysis_button = button(analysis_label).on_press_maybe(
+                    (game_state.analysis.is_some() || game_state.can_analyze())
+                        .then_some(Message::ToggleAnalysis),
+                );
+
+                /* The clocks are in the order of the players at the top and bottom of the board. */
+                let clocks = game_state.clock.as_ref().map(|clock| {
+                    let now = Instant::now();
+                    let (top, bottom) = match self.board_flipped {
+                        false => (Color::Black, Color::White),
+                        true => (Color::White, Color::Black),
+                    };
+                    let pause_label = if game_state.paused { "Resume" } else { "Pause" };
+                    let pause_button = button(pause_label)
+                        .on_press_maybe(game_state.can_pause().then_some(Message::TogglePause));
+
+                    column![
+                        clock_view(clock, top, now),
+                        clock_view(clock, bottom, now),
+                        pause_button
+                    ]
+                    .spacing(10)
+                });
+
+                let analysis = game_state.analysis.as_ref();
+
+                row![]
+                    .push_maybe(
+                        analysis.map(|analysis| evaluation_bar(analysis, self.board_flipped)),
+                    )
+                    .push(board_view(
+                        game_state,
+                        &self.piece_images,
+                        self.board_flipped,
+                    ))
+                    .push(
+                        column![text(game_state.status()).size(20), text(thinking)]
+                            .push_maybe(self.notice.as_deref().map(text))
+                            .push_maybe(clocks)
+                            .push(move_list_view(game_state))
+                            .push(row![hint_button, analysis_button].spacing(10))
+                            .push(game_buttons)
+                            .spacing(20),
+                    )
+                    .push_maybe(analysis.map(analysis_view))
+                    .spacing(20)
+            }
+            (None, None) => row![column![text("Chess-rs").size(32)]
+                .push_maybe(self.notice.as_deref().map(text))
+                .push(game_buttons)
+                .spacing(20)],
+        };
+
+        let content = container(content).padding(20);
+
+        let dialog = match (&self.new_game_dialog, &self.file_dialog) {
+            (Some(settings), _) => Some((new_game_view(*settings), Message::CancelNewGame)),
+            (None, Some(file_dialog)) => Some((
+                file_dialog_view(file_dialog, &self.recent_files),
+                Message::File(FileMessage::CloseDialog),
+            )),
+            (None, None) => None,
+        };
+
+        match dialog {
+            /* The dialog covers the dimmed window, pressing outside of it cancels the dialog. */
+            Some((dialog, cancel)) => {
+                let backdrop = container(opaque(dialog))
+                    .center(Length::Fill)
+                    .style(|_theme| container::Style {
+                        background: Some(Background::Color(board::BACKDROP)),
+                        ..container::Style::default()
+                    });
+                stack![content, opaque(mouse_area(backdrop).on_press(cancel))].into()
+            }
+            None => content.into(),
+        }
     }
     fn theme(&self) -> Theme {
         Theme::Dark
     }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let keys = keyboard::on_key_press(|key, modifiers| match key.as_ref() {
+            Key::Character("o") if modifiers.command() => {
+                Some(Message::File(FileMessage::OpenDialog))
+            }
+            Key::Character("s") if modifiers.command() => {
+                Some(Message::File(FileMessage::SaveDialog))
+            }
+            Key::Character("f") => Some(Message::FlipBoard),
+            Key::Named(Named::ArrowLeft) => Some(Message::Navigate(Navigation::Back)),
+            Key::Named(Named::ArrowRight) => Some(Message::Navigate(Navigation::Forward)),
+            Key::Named(Named::ArrowUp | Named::Home) => Some(Message::Navigate(Navigation::Start)),
+            Key::Named(Named::ArrowDown | Named::End) => Some(Message::Navigate(Navigation::End)),
+            _ => None,
+        });
+
+        let clock_is_running = self
+            .game_instance
+            .as_ref()
+            .and_then(|game_state| game_state.clock.as_ref())
+            .is_some_and(Clock::is_running);
+
+        match clock_is_running {
+            true => Subscription::batch([keys, time::every(TICK_INTERVAL).map(Message::Tick)]),
+            false => keys,
+        }
+    }
 }
 
-const PIECES: [&str; 12] = [
-    "bB.svg", "bK.svg", "bN.svg", "bP.svg", "bQ.svg", "bR.svg", "wB.svg", "wK.svg", "wN.svg",
-    "wP.svg", "wQ.svg", "wR.svg",
-];
+impl GameState {
+    fn new(settings: NewGameSettings) -> GameState {
+        let clock = settings.time_control.time_control().map(|time_control| {
+            let mut clock = Clock::new(time_control, Color::White);
+            clock.start(Instant::now());
+            clock
+        });
+
+        GameState {
+            selected_square: None,
+            chess_board: ChessBoard::new(),
+            game: PgnGame::new(ChessBoard::new()),
+            current_ply: 0,
+            drag: None,
+            cursor_position: None,
+            pending_promotion: None,
+            settings,
+            engine_search: None,
+            hint_search: None,
+            hint: None,
+            variation: None,
+            clock,
+            flagged: None,
+            paused: false,
+            analysis: None,
+            source: None,
+        }
+    }
+
+    /** A loaded game, shown from its first position. The game goes on from its last position by
+    the settings, with the clocks started for the side to move there. */
+    fn from_game(settings: NewGameSettings, game: PgnGame) -> GameState {
+        let mut game_state = GameState::new(settings);
+        let side_to_move = game.final_position().side_to_move();
+        if let Some(clock) = &mut game_state.clock {
+            *clock = Clock::new(clock.time_control(), side_to_move);
+            clock.start(Instant::now());
+        }
+
+        game_state.game = game;
+        game_state.go_to_ply(0);
+        game_state
+    }
+
+    /** The position after the last move, the one the game continues from. */
+    fn last_position(&self) -> ChessBoard {
+        match self.is_at_last_move() {
+            true => self.chess_board,
+            false => self.game.final_position(),
+        }
+    }
+
+    /** The hashes of the positions before the last position. */
+    fn position_history(&self) -> Vec<u64> {
+        self.game
+            .positions()
+            .iter()
+            .map(|(chess_board, _)| chess_board.zobrist_hash())
+            .collect()
+    }
+
+    fn is_engine_turn(&self) -> bool {
+        match self.settings.mode {
+            GameMode::HumanVsHuman => false,
+            GameMode::HumanVsEngine => {
+                let engine_color = match self.settings.player_side {
+                    PlayerSide::White => Color::Black,
+                    PlayerSide::Black => Color::White,
+                };
+                self.last_position().side_to_move() == engine_color
+            }
+            GameMode::EngineVsEngine => true,
+        }
+    }
+
+    /** The hashes of the positions before the shown position, on the main line and in the shown
+    variation. */
+    fn shown_position_history(&self) -> Vec<u64> {
+        let positions = self.game.positions();
+        let mut history: Vec<u64> = positions[..self.current_ply]
+            .iter()
+            .map(|(chess_board, _)| chess_board.zobrist_hash())
+            .collect();
+
+        if let Some(cursor) = self.variation {
+            let mut chess_board = positions[cursor.move_index].0;
+            for pgn_move in &self.variation_moves(cursor)[..cursor.plies] {
+                history.push(chess_board.zobrist_hash());
+                chess_board.make_move_on_board(&pgn_move.chess_move);
+                chess_board.update_meta_data(&pgn_move.chess_move);
+            }
+        }
+
+        history
+    }
+
+    /** The moves of the variation the cursor is in, empty if the variation does not exist. */
+    fn variation_moves(&self, cursor: VariationCursor) -> &[PgnMove] {
+        self.game
+            .moves
+            .get(cursor.move_index)
+            .and_then(|pgn_move| pgn_move.variations.get(cursor.variation))
+            .map_or(&[], |variation| variation.as_slice())
+    }
+
+    /** Whether a human player can move in the shown position. */
+    fn is_human_turn(&self) -> bool {
+        self.is_at_last_move() && !self.paused && !self.is_engine_turn() && !self.is_over()
+    }
+
+    /** Whether the user can move on the board: in their turn at the end of the game, and in any
+    earlier position during analysis, where the moves make a variation. */
+    fn can_move(&self) -> bool {
+        self.is_human_turn() || (self.analysis.is_some() && !self.is_at_last_move())
+    }
+
+    /** Games are analysed after they ended, or while two humans play without clocks. */
+    fn can_analyze(&self) -> bool {
+        self.is_over() || (self.settings.mode == GameMode::HumanVsHuman && self.clock.is_none())
+    }
+
+    /** The engine cannot give a hint while it analyses. */
+    fn can_request_hint(&self) -> bool {
+        self.is_human_turn() && self.hint_search.is_none() && self.analysis.is_none()
+    }
+
+    /** Casual games can be paused while a human player is to move. */
+    fn can_pause(&self) -> bool {
+        self.clock.is_some() && (self.paused || self.is_human_turn())
+    }
+
+    fn toggle_pause(&mut self, now: Instant) {
+        if !self.can_pause() {
+            return;
+        }
+
+        if let Some(clock) = &mut self.clock {
+            match self.paused {
+                true => clock.start(now),
+                false => clock.stop(now),
+            }
+            self.paused = !self.paused;
+        }
+    }
+
+    /** Ends the game when the side to move has run out of time. Returns whether it did. */
+    fn check_flag(&mut self, now: Instant) -> bool {
+        let Some(clock) = &mut self.clock else {
+            return false;
+        };
+
+        if self.flagged.is_some() || !clock.is_running() || !clock.has_flagged(now) {
+            return false;
+        }
+
+        clock.stop(now);
+        self.flagged = Some(clock.side_to_move());
+        if let Some((result, _)) = self.outcome() {
+            self.game.result = result;
+        }
+        true
+    }
+
+    /** The limits of the engine's search, with the clocks in games with a time control. */
+    fn engine_limits(&self) -> SearchLimits {
+        let mut limits = self.settings.search_limits();
+
+        if let Some(clock) = &self.clock {
+            let now = Instant::now();
+            let bonus_time = clock.time_control().bonus_time();
+            let side_to_move = clock.side_to_move();
+
+            limits.time_control = Some(TimeControl {
+                white_time: Some(clock.remaining(Color::White, now)),
+                black_time: Some(clock.remaining(Color::Black, now)),
+                white_increment: bonus_time,
+                black_increment: bonus_time,
+                moves_to_go: clock.moves_to_go(side_to_move),
+            });
+        }
+
+        limits
+    }
+
+    /** The result of a finished game and how it ended, by checkmate, stalemate, the fifty move
+    rule, threefold repetition, insufficient material or running out of time. Running out of time
+    only draws when the opponent could never mate. */
+    fn outcome(&self) -> Option<(GameResult, &'static str)> {
+        let chess_board = self.last_position();
+
+        if let Some(flagged) = self.flagged {
+            let can_mate = chess_board.has_mating_material(flagged.opposite());
+            return Some(match (flagged, can_mate) {
+                (Color::White, true) => {
+                    (GameResult::BlackWins, "White ran out of time, Black wins")
+                }
+                (Color::Black, true) => {
+                    (GameResult::WhiteWins, "Black ran out of time, White wins")
+                }
+                (Color::White, false) => (
+                    GameResult::Draw,
+                    "White ran out of time, Black cannot mate, draw",
+                ),
+                (Color::Black, false) => (
+                    GameResult::Draw,
+                    "Black ran out of time, White cannot mate, draw",
+                ),
+            });
+        }
+
+        Some(match chess_board.termination(&self.position_history())? {
+            Termination::Checkmate {
+                winner: Color::White,
+            } => (GameResult::WhiteWins, "Checkmate, White wins"),
+            Termination::Checkmate {
+                winner: Color::Black,
+            } => (GameResult::BlackWins, "Checkmate, Black wins"),
+            Termination::Stalemate => (GameResult::Draw, "Stalemate, the game is drawn"),
+            Termination::FiftyMoveRule => (GameResult::Draw, "Draw by the fifty move rule"),
+            Termination::ThreefoldRepetition => (GameResult::Draw, "Draw by threefold repetition"),
+            Termination::InsufficientMaterial => {
+                (GameResult::Draw, "Draw by insufficient material")
+            }
+        })
+    }
+
+    /** Whether the game has ended, by its rules or with a result given in a loaded game. */
+    fn is_over(&self) -> bool {
+        self.game.result != GameResult::Unknown || self.outcome().is_some()
+    }
+
+    /** Whether the board shows the position after the last move, the only one moves are made in. */
+    fn is_at_last_move(&self) -> bool {
+        self.variation.is_none() && self.current_ply == self.game.moves.len()
+    }
+
+    /** Shows the position after the given number of moves, the later moves are kept. */
+    fn go_to_ply(&mut self, ply: usize) {
+        self.current_ply = ply.min(self.game.moves.len());
+        self.chess_board = match self.game.positions().get(self.current_ply) {
+            Some((chess_board, _)) => *chess_board,
+            None => self.game.final_position(),
+        };
+        self.variation = None;
+        self.selected_square = None;
+        self.drag = None;
+        self.pending_promotion = None;
+    }
+
+    /** Shows a position in a variation, the start of the variation is the main line position
+    before the move it replaces. */
+    fn go_to_variation(&mut self, cursor: VariationCursor) {
+        let moves = self.variation_moves(cursor);
+        let plies = cursor.plies.min(moves.len());
+        if plies == 0 {
+            self.go_to_ply(cursor.move_index);
+            return;
+        }
+
+        let mut chess_board = self.game.positions()[cursor.move_index].0;
+        for pgn_move in &moves[..plies] {
+            chess_board.make_move_on_board(&pgn_move.chess_move);
+            chess_board.update_meta_data(&pgn_move.chess_move);
+        }
+
+        self.chess_board = chess_board;
+        self.current_ply = cursor.move_index;
+        self.variation = Some(VariationCursor { plies, ..cursor });
+        self.selected_square = None;
+        self.drag = None;
+        self.pending_promotion = None;
+    }
+
+    /** Handles a press on a square. A piece of the side to move is selected and picked up,
+    otherwise the move from the selected piece to the square is returned. */
+    fn click_square(&mut self, coordinate: Coordinate) -> Option<Move> {
+        if !self.can_move() {
+            return None;
+        }
+
+        let clicked_piece = self.chess_board.piece_at((coordinate.x, coordinate.y));
+        let side_to_move = self.chess_board.side_to_move();
+
+        if clicked_piece.is_some_and(|piece| piece.color() == side_to_move) {
+            self.drag = Some(Drag {
+                from: coordinate,
+                was_selected: self.selected_square == Some(coordinate),
+            });
+            self.selected_square = Some(coordinate);
+            return None;
+        }
+
+        self.selected_square.take().map(|selected| Move {
+            start_position: selected,
+            end_position: coordinate,
+        })
+    }
+
+    /** Handles releasing the mouse button. A piece dropped on another square is moved there,
+    the caller snaps it back if the move is illegal. Dropping a piece on its own square keeps it
+    selected for a click on its destination, unless it was selected before. */
+    fn drop_piece(&mut self, board_flipped: bool) -> Option<Move> {
+        let drag = self.drag.take()?;
+        let target = self
+            .cursor_position
+            .and_then(|position| coordinate_at(position, board_flipped));
+
+        match target {
+            Some(target) if target != drag.from => {
+                self.selected_square = None;
+                Some(Move {
+                    start_position: drag.from,
+                    end_position: target,
+                })
+            }
+            Some(_) if drag.was_selected => {
+                self.selected_square = None;
+                None
+            }
+            Some(_) => None,
+            None => {
+                self.selected_square = None;
+                None
+            }
+        }
+    }
+
+    /** The squares the selected piece can move to. */
+    fn move_targets(&self) -> Vec<(Coordinate, MoveTarget)> {
+        let Some(selected) = self.selected_square else {
+            return Vec::new();
+        };
+
+        self.chess_board
+            .legal_moves()
+            .into_iter()
+            .filter(|legal_move| legal_move.start_pos == (selected.x, selected.y))
+            .map(|legal_move| {
+                let (x, y) = legal_move.end_pos;
+                let move_target = match legal_move.meta_data.piece_to_capture() {
+                    Some(_) => MoveTarget::Capture,
+                    None => MoveTarget::Quiet,
+                };
+                (Coordinate { x, y }, move_target)
+            })
+            .collect()
+    }
+
+    /** Whether the move is a legal pawn move to the last rank, which needs a promotion piece. */
+    fn is_promotion(&self, chess_move: Move) -> bool {
+        chess_move
+            .to_user_move(None)
+            .find_legal_move(&self.chess_board)
+            .is_some_and(|legal_move| legal_move.meta_data.promotion_piece.is_some())
+    }
+
+    /** Plays the move if it is legal and adds it to the game, or to a variation when it is made
+    before the end of the game during analysis. Returns whether the move was played. */
+    fn make_move(&mut self, user_move: &UserMove) -> bool {
+        let Some(legal_move) = user_move.find_legal_move(&self.chess_board) else {
+            return false;
+        };
+
+        if !self.is_at_last_move() {
+            if self.analysis.is_none() {
+                return false;
+            }
+            self.play_variation_move(legal_move);
+            return true;
+        }
+
+        let mut pgn_move = PgnMove::new(&self.chess_board, legal_move);
+
+        /* A move after the time ran out is too late. */
+        let now = Instant::now();
+        if self.check_flag(now) {
+            return false;
+        }
+        if let Some(clock) = &mut self.clock {
+            pgn_move.set_clock(clock.press(now));
+        }
+
+        self.game.moves.push(pgn_move);
+        self.current_ply += 1;
+        self.chess_board.make_move_on_board(&legal_move);
+        self.chess_board.update_meta_data(&legal_move);
+        self.selected_square = None;
+        self.hint = None;
+        self.hint_search = None;
+        if let Some((result, _)) = self.outcome() {
+            self.game.result = result;
+            if let Some(clock) = &mut self.clock {
+                clock.stop(now);
+            }
+        }
+        true
+    }
+
+    /** Plays a move in a position before the end of the game. A move already in the game or in
+    a variation there is followed, any other move starts a new variation, which repeats the moves
+    of the shown variation up to the position. */
+    fn play_variation_move(&mut self, legal_move: array_engine::Move) {
+        let new_move = PgnMove::new(&self.chess_board, legal_move);
+
+        let cursor = match self.variation {
+            None => {
+                let move_index = self.current_ply;
+                let main_move = &mut self.game.moves[move_index];
+                if main_move.chess_move == legal_move {
+                    self.go_to_ply(move_index + 1);
+                    return;
+                }
+
+                let variations = &mut main_move.variations;
+                let variation = variations
+                    .iter()
+                    .position(|line| line.first().is_some_and(|m| m.chess_move == legal_move))
+                    .unwrap_or_else(|| {
+                        variations.push(vec![new_move]);
+                        variations.len() - 1
+                    });
+                VariationCursor {
+                    move_index,
+                    variation,
+                    plies: 1,
+                }
+            }
+            Some(cursor) => {
+                let variations = &mut self.game.moves[cursor.move_index].variations;
+                let line = &mut variations[cursor.variation];
+
+                let variation = match line.get(cursor.plies) {
+                    Some(next) if next.chess_move == legal_move => cursor.variation,
+                    None => {
+                        line.push(new_move);
+                        cursor.variation
+                    }
+                    Some(_) => {
+                        let mut new_line = line[..cursor.plies].to_vec();
+                        new_line.push(new_move);
+                        variations.push(new_line);
+                        variations.len() - 1
+                    }
+                };
+
+                VariationCursor {
+                    move_index: cursor.move_index,
+                    variation,
+                    plies: cursor.plies + 1,
+                }
+            }
+        };
+
+        self.go_to_variation(cursor);
+    }
+
+    /** The side to move in the shown position, or how the game ended after its last move. */
+    fn status(&self) -> String {
+        if self.is_at_last_move() {
+            if let Some((_, reason)) = self.outcome() {
+                return reason.to_string();
+            }
+            if self.game.result != GameResult::Unknown {
+                return format!("Game over, {}", self.game.result.to_pgn());
+            }
+        }
+
+        if self.paused {
+            return "The game is paused".to_string();
+        }
+
+        let side_to_move = match self.chess_board.side_to_move() {
+            Color::White => "White",
+            Color::Black => "Black",
+        };
+
+        if self.chess_board.king_is_in_check() {
+            format!("{side_to_move} to move, check")
+        } else {
+            format!("{side_to_move} to move")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_click_moves() {
+        let mut game_state = GameState::new(NewGameSettings::default());
+        let square = |x, y| Coordinate { x, y };
+
+        /* Empty squares and pieces of the side not to move cannot be selected. */
+        assert!(game_state.click_square(square(4, 3)).is_none());
+        assert!(game_state.click_square(square(4, 6)).is_none());
+        assert_eq!(game_state.selected_square, None);
+
+        assert!(game_state.click_square(square(4, 1)).is_none());
+        let chess_move = game_state.click_square(square(4, 4)).unwrap();
+        assert!(!game_state.make_move(&chess_move.to_user_move(None)));
+
+        game_state.click_square(square(4, 1));
+        let chess_move = game_state.click_square(square(4, 3)).unwrap();
+        assert!(game_state.make_move(&chess_move.to_user_move(None)));
+        assert_eq!(game_state.status(), "Black to move");
+    }
+
+    #[test]
+    fn test_drag_moves() {
+        let mut game_state = GameState::new(NewGameSettings::default());
+        let square = |x, y| Coordinate { x, y };
+        /* The center of a square, with rank 8 at the top of the board. */
+        let center = |x: usize, y: usize| {
+            Point::new(
+                (x as f32 + 0.5) * board::SQUARE_SIZE,
+                (7.5 - y as f32) * board::SQUARE_SIZE,
+            )
+        };
+
+        game_state.click_square(square(6, 0));
+        assert_eq!(game_state.move_targets().len(), 2);
+
+        /* Dropping the knight on its own square keeps it selected, a second time deselects it. */
+        game_state.cursor_position = Some(center(6, 0));
+        assert!(game_state.drop_piece(false).is_none());
+        assert_eq!(game_state.selected_square, Some(square(6, 0)));
+        game_state.click_square(square(6, 0));
+        assert!(game_state.drop_piece(false).is_none());
+        assert_eq!(game_state.selected_square, None);
+
+        game_state.click_square(square(6, 0));
+        game_state.cursor_position = Some(center(5, 2));
+        let chess_move = game_state.drop_piece(false).unwrap();
+        assert!(game_state.make_move(&chess_move.to_user_move(None)));
+        assert!(game_state.drag.is_none());
+
+        /* With black at the bottom, the top left square is h1. */
+        game_state.click_square(square(1, 7));
+        game_state.cursor_position = Some(Point::new(0.0, 0.0));
+        let chess_move = game_state.drop_piece(true).unwrap();
+        assert_eq!(chess_move.end_position, square(7, 0));
+
+        game_state.chess_board =
+            ChessBoard::new_from_fen("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        game_state.click_square(square(4, 3));
+        let mut targets = game_state.move_targets();
+        targets.sort_by_key(|(coordinate, _)| coordinate.x);
+        assert!(
+            targets
+                == [
+                    (square(3, 4), MoveTarget::Capture),
+                    (square(4, 4), MoveTarget::Quiet)
+                ]
+        );
+    }
+
+    /* Plays a move given by the names of its squares, like e2 and e4. */
+    fn play(application: &mut ChessApplication, from: &str, to: &str) {
+        let square = |name: &str| {
+            let name = name.as_bytes();
+            Coordinate {
+                x: (name[0] - b'a') as usize,
+                y: (name[1] - b'1') as usize,
+            }
+        };
+        let chess_move = Move {
+            start_position: square(from),
+            end_position: square(to),
+        };
+        let _ = application.update(Message::MakeMove(chess_move));
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut application = ChessApplication::default();
+        let _ = application.update(Message::StartNewGame);
+        let _ = application.update(Message::ConfirmNewGame);
+
+        /* e4 e5 Nf3 */
+        play(&mut application, "e2", "e4");
+        play(&mut application, "e7", "e5");
+        play(&mut application, "g1", "f3");
+
+        let _ = application.update(Message::GoToPly(1));
+        let _ = application.update(Message::Navigate(Navigation::Forward));
+        let game_state = application.game_instance.as_ref().unwrap();
+        assert_eq!(game_state.current_ply, 2);
+        assert_eq!(game_state.status(), "White to move");
+        assert!(game_state.chess_board.piece_at((6, 0)).is_some());
+
+        /* Moves can only be made after the last move, which is kept while looking back. */
+        play(&mut application, "g1", "f3");
+        let _ = application.update(Message::Navigate(Navigation::Start));
+        let _ = application.update(Message::Navigate(Navigation::Back));
+        assert_eq!(application.game_instance.as_ref().unwrap().current_ply, 0);
+
+        let _ = application.update(Message::Navigate(Navigation::End));
+        let game_state = application.game_instance.as_ref().unwrap();
+        let sans: Vec<&str> = game_state
+            .game
+            .moves
+            .iter()
+            .map(|m| m.san.as_str())
+            .collect();
+        assert_eq!(sans, ["e4", "e5", "Nf3"]);
+        assert_eq!(game_state.status(), "Black to move");
+    }
+
+    #[test]
+    fn test_promotion() {
+        let mut application = ChessApplication::default();
+        let _ = application.update(Message::StartNewGame);
+        let _ = application.update(Message::ConfirmNewGame);
+        let promotion = Move {
+            start_position: Coordinate { x: 0, y: 6 },
+            end_position: Coordinate { x: 0, y: 7 },
+        };
+        let promoted_piece = |application: &ChessApplication| {
+            let game_state = application.game_instance.as_ref().unwrap();
+            game_state
+                .chess_board
+                .piece_at((0, 7))
+                .map(|piece| piece.piece_type())
+        };
+        let set_position = |application: &mut ChessApplication| {
+            let game_state = application.game_instance.as_mut().unwrap();
+            let chess_board = ChessBoard::new_from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+            game_state.game = PgnGame::new(chess_board);
+            game_state.go_to_ply(0);
+        };
+
+        /* Canceling the picker leaves the pawn on its square. */
+        set_position(&mut application);
+        let _ = application.update(Message::MakeMove(promotion));
+        let _ = application.update(Message::CancelPromotion);
+        assert_eq!(promoted_piece(&application), None);
+
+        let _ = application.update(Message::MakeMove(promotion));
+        let _ = application.update(Message::PromotePawn(PieceType::Knight));
+        assert_eq!(promoted_piece(&application), Some(PieceType::Knight));
+
+        set_position(&mut application);
+        let _ = application.update(Message::SetAlwaysPromoteToQueen(true));
+        let _ = application.update(Message::MakeMove(promotion));
+        assert_eq!(promoted_piece(&application), Some(PieceType::Queen));
+    }
+
+    #[test]
+    fn test_engine_game() {
+        let mut application = ChessApplication::default();
+        let _ = application.update(Message::StartNewGame);
+        let settings = NewGameSettings {
+            mode: GameMode::HumanVsEngine,
+            player_side: PlayerSide::Black,
+            ..NewGameSettings::default()
+        };
+        let _ = application.update(Message::SetNewGameSettings(settings));
+        let _ = application.update(Message::ConfirmNewGame);
+        assert!(application.board_flipped);
+
+        /* The engine plays white, the human cannot move while it is thinking. */
+        let game_state = application.game_instance.as_mut().unwrap();
+        let id = game_state.engine_search.unwrap();
+        assert!(game_state.click_square(Coordinate { x: 4, y: 1 }).is_none());
+        assert!(!game_state.is_human_turn());
+
+        let e4 = ChessBoard::new()
+            .legal_moves()
+            .into_iter()
+            .find(|legal_move| legal_move.start_pos == (4, 1) && legal_move.end_pos == (4, 3));
+        let _ = application.update(Message::EngineMoved(id + 1, e4));
+        assert!(application
+            .game_instance
+            .as_ref()
+            .unwrap()
+            .game
+            .moves
+            .is_empty());
+        let _ = application.update(Message::EngineMoved(id, e4));
+
+        let game_state = application.game_instance.as_ref().unwrap();
+        assert_eq!(game_state.game.moves[0].san, "e4");
+        assert!(game_state.is_human_turn());
+        assert_eq!(game_state.engine_search, None);
+
+        let _ = application.update(Message::RequestHint);
+        let game_state = application.game_instance.as_mut().unwrap();
+        let id = game_state.hint_search.unwrap();
+        let e5 = game_state
+            .chess_board
+            .legal_moves()
+            .into_iter()
+            .find(|legal_move| legal_move.start_pos == (4, 6) && legal_move.end_pos == (4, 4));
+        let _ = application.update(Message::HintFound(id, e5));
+        let hint = application.game_instance.as_ref().unwrap().hint.unwrap();
+        assert_eq!(hint.end_position, Coordinate { x: 4, y: 4 });
+
+        /* A finished game gives the engine nothing to search. */
+        let game_state = application.game_instance.as_mut().unwrap();
+        game_state.game =
+            PgnGame::new(ChessBoard::new_from_fen("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1").unwrap());
+        game_state.go_to_ply(0);
+        assert_eq!(game_state.status(), "Checkmate, White wins");
+        assert!(!game_state.is_human_turn());
+    }
+
+    #[test]
+    fn test_time_forfeit() {
+        let settings = NewGameSettings {
+            time_control: new_game::TIME_CONTROLS[1],
+            ..NewGameSettings::default()
+        };
+        let start = Instant::now();
+        let flag = |fen: &str| {
+            let mut game_state = GameState::new(settings);
+            game_state.game = PgnGame::new(ChessBoard::new_from_fen(fen).unwrap());
+            game_state.go_to_ply(0);
+
+            assert!(!game_state.check_flag(start));
+            game_state.toggle_pause(start);
+            assert!(!game_state.check_flag(start + Duration::from_secs(120)));
+            game_state.toggle_pause(start);
+            assert!(game_state.check_flag(start + Duration::from_secs(120)));
+            game_state
+        };
+
+        /* White runs out of time against a lone king, which cannot mate. */
+        let game_state = flag("4k3/8/8/8/8/8/P7/4K3 w - - 0 1");
+        assert_eq!(game_state.game.result, GameResult::Draw);
+        assert!(!game_state.is_human_turn());
+
+        /* A knight can mate when the pawn blocks the escape of the white king. */
+        let game_state = flag("4k3/8/8/8/8/8/P3n3/4K3 w - - 0 1");
+        assert_eq!(game_state.status(), "White ran out of time, Black wins");
+
+        let game_state = flag("4k3/8/8/8/8/8/P3q3/4K3 w - - 0 1");
+        assert_eq!(game_state.status(), "White ran out of time, Black wins");
+
+        /* Bishops on squares of one color mate when the pawn can block on the other color. */
+        let game_state = flag("4k3/8/8/8/2b1b3/8/P7/4K3 w - - 0 1");
+        assert_eq!(game_state.status(), "White ran out of time, Black wins");
+
+        /* Without such a piece the position is dead and the game already ended before the clock
+         * could run out, so the flag is set directly. */
+        for fen in [
+            "4k3/8/8/8/2b5/8/2B5/4K3 w - - 0 1",
+            "4k3/8/8/8/2b1b3/8/8/4K3 w - - 0 1",
+        ] {
+            let mut game_state = GameState::new(settings);
+            game_state.game = PgnGame::new(ChessBoard::new_from_fen(fen).unwrap());
+            game_state.go_to_ply(0);
+            game_state.flagged = Some(Color::White);
+            assert_eq!(
+                game_state.status(),
+                "White ran out of time, Black cannot mate, draw"
+            );
+        }
+    }
+
+    #[test]
+    fn test_analysis_variations() {
+        let mut application = ChessApplication::default();
+        let _ = application.update(Message::StartNewGame);
+        let _ = application.update(Message::ConfirmNewGame);
+        play(&mut application, "e2", "e4");
+        play(&mut application, "e7", "e5");
+        play(&mut application, "g1", "f3");
+
+        /* Without analysis the game cannot be changed before its last move. */
+        let _ = application.update(Message::GoToPly(1));
+        play(&mut application, "c7", "c5");
+        assert!(application
+            .game_instance
+            .as_ref()
+            .unwrap()
+            .variation
+            .is_none());
+
+        let _ = application.update(Message::ToggleAnalysis);
+        let game_state = application.game_instance.as_ref().unwrap();
+        let analysis = game_state.analysis.as_ref().unwrap();
+        assert_eq!(game_state.shown_position_history().len(), 1);
+        let id = analysis.search.unwrap();
+
+        /* The main line move is followed, another move starts a variation. */
+        play(&mut application, "e7", "e5");
+        assert_eq!(application.game_instance.as_ref().unwrap().current_ply, 2);
+        let _ = application.update(Message::Navigate(Navigation::Back));
+        play(&mut application, "c7", "c5");
+        play(&mut application, "g1", "f3");
+
+        let game_state = application.game_instance.as_ref().unwrap();
+        let cursor = game_state.variation.unwrap();
+        assert_eq!((cursor.move_index, cursor.plies), (1, 2));
+        assert_eq!(game_state.shown_position_history().len(), 3);
+        assert_ne!(game_state.analysis.as_ref().unwrap().search, Some(id));
+
+        /* A different move inside the variation keeps the old one. */
+        let _ = application.update(Message::Navigate(Navigation::Back));
+        let game_state = application.game_instance.as_ref().unwrap();
+        let c3 = game_state
+            .chess_board
+            .legal_moves()
+            .into_iter()
+            .find(|legal_move| legal_move.start_pos == (2, 1) && legal_move.end_pos == (2, 2))
+            .unwrap();
+        let _ = application.update(Message::PlayAnalysisMoves(vec![c3]));
+
+        let game_state = application.game_instance.as_ref().unwrap();
+        let variations: Vec<Vec<&str>> = game_state.game.moves[1]
+            .variations
+            .iter()
+            .map(|line| line.iter().map(|m| m.san.as_str()).collect())
+            .collect();
+        assert_eq!(variations, [vec!["c5", "Nf3"], vec!["c5", "c3"]]);
+        assert_eq!(game_state.game.moves.len(), 3);
+
+        let _ = application.update(Message::ToggleAnalysis);
+        assert!(application
+            .game_instance
+            .as_ref()
+            .unwrap()
+            .analysis
+            .is_none());
+    }
+}
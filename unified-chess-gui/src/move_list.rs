@@ -0,0 +1,126 @@
+/* This module draws the moves of the game next to the board, numbered in two columns for white
+ * and black, with the variations of a move in parentheses below it. Clicking a move shows the
+ * position after it, the buttons below the list step through the game like the arrow keys. */
+use crate::{GameState, Message, Navigation, VariationCursor};
+use iced::widget::{button, column, container, row, scrollable, text, Column, Row};
+use iced::{Element, Length, Padding};
+use unified_chess_engine::array_engine::Color;
+
+const MOVE_LIST_HEIGHT: f32 = 360.0;
+const MOVE_NUMBER_WIDTH: f32 = 44.0;
+const MOVE_WIDTH: f32 = 96.0;
+
+pub(crate) fn move_list_view(game_state: &GameState) -> Element<'_, Message> {
+    let game = &game_state.game;
+    let first_move_number = game.starting_position.full_move_counter();
+    /* A game starting with black to move leaves the first white move empty. */
+    let skipped_plies = match game.starting_position.side_to_move() {
+        Color::White => 0,
+        Color::Black => 1,
+    };
+
+    let move_cell = |slot: usize| -> Element<'_, Message> {
+        let Some(index) = slot.checked_sub(skipped_plies) else {
+            return text("...").width(MOVE_WIDTH).into();
+        };
+        let Some(pgn_move) = game.moves.get(index) else {
+            return text("").width(MOVE_WIDTH).into();
+        };
+
+        let ply = index + 1;
+        let style = match ply == game_state.current_ply && game_state.variation.is_none() {
+            true => button::primary,
+            false => button::text,
+        };
+
+        button(text(pgn_move.san.as_str()))
+            .width(MOVE_WIDTH)
+            .style(style)
+            .on_press(Message::GoToPly(ply))
+            .into()
+    };
+
+    /* A variation replaces the move at the index, its moves are numbered from there. */
+    let variation_view = |move_index: usize, variation: usize| -> Element<'_, Message> {
+        let first_slot = move_index + skipped_plies;
+        let moves = game.moves[move_index].variations[variation]
+            .iter()
+            .enumerate()
+            .map(|(i, pgn_move)| {
+                let slot = first_slot + i;
+                let move_number = first_move_number + (slot / 2) as u64;
+                let label = match (slot.is_multiple_of(2), i) {
+                    (true, _) => format!("{move_number}. {}", pgn_move.san),
+                    (false, 0) => format!("{move_number}... {}", pgn_move.san),
+                    (false, _) => pgn_move.san.clone(),
+                };
+                let cursor = VariationCursor {
+                    move_index,
+                    variation,
+                    plies: i + 1,
+                };
+                let style = match game_state.variation == Some(cursor) {
+                    true => button::primary,
+                    false => button::text,
+                };
+
+                button(text(label).size(14))
+                    .padding([2, 3])
+                    .style(style)
+                    .on_press(Message::GoToVariation(cursor))
+                    .into()
+            });
+
+        let items = std::iter::once(text("(").into())
+            .chain(moves)
+            .chain(std::iter::once(text(")").into()));
+
+        container(Row::with_children(items).wrap())
+            .padding(Padding {
+                left: MOVE_NUMBER_WIDTH,
+                ..Padding::ZERO
+            })
+            .into()
+    };
+
+    let row_count = (game.moves.len() + skipped_plies).div_ceil(2);
+    let rows = (0..row_count).flat_map(|i| {
+        let move_row = row![
+            text(format!("{}.", first_move_number + i as u64)).width(MOVE_NUMBER_WIDTH),
+            move_cell(2 * i),
+            move_cell(2 * i + 1),
+        ]
+        .into();
+
+        let variations = (2 * i..2 * i + 2)
+            .filter_map(|slot| slot.checked_sub(skipped_plies))
+            .filter(|&move_index| move_index < game.moves.len())
+            .flat_map(|move_index| {
+                (0..game.moves[move_index].variations.len())
+                    .map(move |variation| (move_index, variation))
+            })
+            .map(|(move_index, variation)| variation_view(move_index, variation));
+
+        std::iter::once(move_row)
+            .chain(variations)
+            .collect::<Vec<_>>()
+    });
+
+    let navigation = row![
+        button("|<").on_press(Message::Navigate(Navigation::Start)),
+        button("<").on_press(Message::Navigate(Navigation::Back)),
+        button(">").on_press(Message::Navigate(Navigation::Forward)),
+        button(">|").on_press(Message::Navigate(Navigation::End)),
+    ]
+    .spacing(4);
+
+    column![
+        scrollable(Column::with_children(rows).width(Length::Fill))
+            .height(MOVE_LIST_HEIGHT)
+            .anchor_bottom(),
+        navigation,
+    ]
+    .width(MOVE_NUMBER_WIDTH + 2.0 * MOVE_WIDTH + 16.0)
+    .spacing(10)
+    .into()
+}
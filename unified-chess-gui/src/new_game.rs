@@ -0,0 +1,217 @@
+/* This module holds the settings of the new game dialog: who plays the game, the color of the
+ * human player against the engine, the strength of the engine and the time control of the
+ * clocks. */
+use crate::clock::{Bonus, Period, TimeControl};
+use crate::Message;
+use iced::widget::{button, column, container, pick_list, radio, row, slider, text};
+use iced::{Element, Length};
+use std::fmt;
+use std::time::Duration;
+use unified_chess_engine::search::SearchLimits;
+
+pub(crate) const MAX_LEVEL: u8 = 8;
+/* The depth limit of each strength level, the highest level searches as deep as the time allows. */
+const LEVEL_DEPTHS: [Option<u32>; MAX_LEVEL as usize] = [
+    Some(1),
+    Some(2),
+    Some(3),
+    Some(4),
+    Some(6),
+    Some(8),
+    Some(12),
+    None,
+];
+/* The time per move of the engine in games without a time control. */
+const UNLIMITED_MOVE_TIME: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    HumanVsHuman,
+    HumanVsEngine,
+    EngineVsEngine,
+}
+
+/* The color of the human player against the engine. */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerSide {
+    White,
+    Black,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeControlChoice {
+    Unlimited,
+    Clock(TimeControl),
+}
+
+const fn timed(periods: &'static [Period], increment: u64) -> TimeControlChoice {
+    TimeControlChoice::Clock(TimeControl {
+        periods,
+        bonus: match increment {
+            0 => Bonus::None,
+            _ => Bonus::Increment(Duration::from_secs(increment)),
+        },
+    })
+}
+
+pub(crate) const TIME_CONTROLS: [TimeControlChoice; 13] = [
+    TimeControlChoice::Unlimited,
+    timed(&[Period::rest_of_game(1)], 0),
+    timed(&[Period::rest_of_game(3)], 0),
+    timed(&[Period::rest_of_game(3)], 2),
+    timed(&[Period::rest_of_game(5)], 0),
+    timed(&[Period::rest_of_game(5)], 3),
+    timed(&[Period::rest_of_game(10)], 5),
+    timed(&[Period::rest_of_game(15)], 10),
+    timed(&[Period::rest_of_game(30)], 0),
+    TimeControlChoice::Clock(TimeControl {
+        periods: &[Period::rest_of_game(5)],
+        bonus: Bonus::Bronstein(Duration::from_secs(3)),
+    }),
+    TimeControlChoice::Clock(TimeControl {
+        periods: &[Period::rest_of_game(5)],
+        bonus: Bonus::Delay(Duration::from_secs(3)),
+    }),
+    timed(&[Period::moves_in(40, 120)], 0),
+    timed(&[Period::moves_in(40, 90), Period::rest_of_game(30)], 30),
+];
+
+impl TimeControlChoice {
+    pub(crate) fn time_control(&self) -> Option<TimeControl> {
+        match *self {
+            TimeControlChoice::Unlimited => None,
+            TimeControlChoice::Clock(time_control) => Some(time_control),
+        }
+    }
+}
+
+impl fmt::Display for TimeControlChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeControlChoice::Unlimited => write!(f, "Unlimited"),
+            TimeControlChoice::Clock(time_control) => write!(f, "{time_control}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NewGameSettings {
+    pub mode: GameMode,
+    pub player_side: PlayerSide,
+    /* The strength of the engine from 1 to MAX_LEVEL. */
+    pub level: u8,
+    pub time_control: TimeControlChoice,
+}
+
+impl Default for NewGameSettings {
+    fn default() -> Self {
+        NewGameSettings {
+            mode: GameMode::HumanVsHuman,
+            player_side: PlayerSide::White,
+            level: MAX_LEVEL,
+            time_control: TimeControlChoice::Unlimited,
+        }
+    }
+}
+
+impl NewGameSettings {
+    /** The limits of an engine search, without the clocks of a game with a time control. */
+    pub(crate) fn search_limits(&self) -> SearchLimits {
+        let move_time = match self.time_control {
+            TimeControlChoice::Unlimited => Some(UNLIMITED_MOVE_TIME),
+            TimeControlChoice::Clock(_) => None,
+        };
+
+        SearchLimits {
+            depth: LEVEL_DEPTHS[self.level.clamp(1, MAX_LEVEL) as usize - 1],
+            move_time,
+            ..SearchLimits::default()
+        }
+    }
+}
+
+pub(crate) fn new_game_view(settings: NewGameSettings) -> Element<'static, Message> {
+    let mode = |label, mode| {
+        radio(label, mode, Some(settings.mode), move |mode| {
+            Message::SetNewGameSettings(NewGameSettings { mode, ..settings })
+        })
+    };
+    let side = |label, player_side| {
+        radio(
+            label,
+            player_side,
+            Some(settings.player_side),
+            move |player_side| {
+                Message::SetNewGameSettings(NewGameSettings {
+                    player_side,
+                    ..settings
+                })
+            },
+        )
+    };
+
+    let mut dialog = column![
+        text("New game").size(24),
+        mode("Human vs human", GameMode::HumanVsHuman),
+        mode("Human vs engine", GameMode::HumanVsEngine),
+        mode("Engine vs engine", GameMode::EngineVsEngine),
+    ]
+    .spacing(10);
+
+    if settings.mode == GameMode::HumanVsEngine {
+        dialog = dialog.push(
+            row![
+                side("Play white", PlayerSide::White),
+                side("Play black", PlayerSide::Black)
+            ]
+            .spacing(20),
+        );
+    }
+
+    if settings.mode != GameMode::HumanVsHuman {
+        dialog = dialog.push(
+            row![
+                text(format!("Engine level {}", settings.level)).width(130),
+                slider(1..=MAX_LEVEL, settings.level, move |level| {
+                    Message::SetNewGameSettings(NewGameSettings { level, ..settings })
+                })
+                .width(160),
+            ]
+            .spacing(10),
+        );
+    }
+
+    dialog = dialog.push(
+        row![
+            text("Time control").width(130),
+            pick_list(
+                TIME_CONTROLS,
+                Some(settings.time_control),
+                move |time_control| {
+                    Message::SetNewGameSettings(NewGameSettings {
+                        time_control,
+                        ..settings
+                    })
+                }
+            )
+            .width(160),
+        ]
+        .spacing(10),
+    );
+
+    dialog = dialog.push(
+        row![
+            button("Start").on_press(Message::ConfirmNewGame),
+            button("Cancel")
+                .style(button::secondary)
+                .on_press(Message::CancelNewGame),
+        ]
+        .spacing(10),
+    );
+
+    container(dialog)
+        .padding(20)
+        .width(Length::Shrink)
+        .style(container::rounded_box)
+        .into()
+}